@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::{get_return_data, invoke},
+};
+
+use crate::errors::EscrowError;
+use crate::state::Escrow;
+
+/// Return data a condition checker sets to let the take through
+pub const CONDITION_PASSED: u8 = 1;
+
+/// Runs the escrow's condition checker, if it has one. `checker` is the
+/// account the taker passed for it, it has to be that program.
+pub(crate) fn check_escrow<'info>(
+    escrow: &Account<'info, Escrow>,
+    checker: Option<&UncheckedAccount<'info>>,
+    taker: &AccountInfo<'info>,
+    extra_accounts: &[AccountInfo<'info>],
+    fill_amount: u64,
+) -> Result<()> {
+    let Some(expected) = escrow.condition_program else {
+        return Ok(());
+    };
+
+    let checker = checker.ok_or(EscrowError::InvalidConditionProgram)?;
+    require_keys_eq!(checker.key(), expected, EscrowError::InvalidConditionProgram);
+    require!(checker.executable, EscrowError::InvalidConditionProgram);
+
+    check(checker, &escrow.to_account_info(), taker, extra_accounts, fill_amount)
+}
+
+/// Ask `checker` whether the take may settle.
+///
+/// The checker gets the fill amount (LE u64) as instruction data and the
+/// escrow, the taker and `extra_accounts` as read-only, unsigned accounts.
+/// Anything other than `[CONDITION_PASSED]` as its return data blocks the take.
+pub fn check<'info>(
+    checker: &AccountInfo<'info>,
+    escrow: &AccountInfo<'info>,
+    taker: &AccountInfo<'info>,
+    extra_accounts: &[AccountInfo<'info>],
+    fill_amount: u64,
+) -> Result<()> {
+    let mut accounts = vec![
+        AccountMeta::new_readonly(escrow.key(), false),
+        AccountMeta::new_readonly(taker.key(), false),
+    ];
+    accounts.extend(extra_accounts.iter().map(|account| AccountMeta::new_readonly(account.key(), false)));
+
+    let ix = Instruction {
+        program_id: checker.key(),
+        accounts,
+        data: fill_amount.to_le_bytes().to_vec(),
+    };
+
+    let mut account_infos = vec![escrow.clone(), taker.clone()];
+    account_infos.extend_from_slice(extra_accounts);
+    account_infos.push(checker.clone());
+
+    invoke(&ix, &account_infos)?;
+
+    verdict(&checker.key(), get_return_data())
+}
+
+/// Only `[CONDITION_PASSED]` set by the checker itself lets the take through,
+/// return data left over from another program doesn't count
+fn verdict(checker: &Pubkey, return_data: Option<(Pubkey, Vec<u8>)>) -> Result<()> {
+    match return_data {
+        Some((program_id, data)) if program_id == *checker && data == [CONDITION_PASSED] => Ok(()),
+        _ => err!(EscrowError::ConditionFailed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn only_the_checker_passing_lets_takes_through() {
+        let checker = Pubkey::new_unique();
+
+        verdict(&checker, Some((checker, vec![CONDITION_PASSED]))).unwrap();
+        assert_eq!(err(verdict(&checker, Some((checker, vec![0])))), anchor_error(EscrowError::ConditionFailed));
+        assert_eq!(err(verdict(&checker, Some((checker, vec![CONDITION_PASSED, 0])))), anchor_error(EscrowError::ConditionFailed));
+        assert_eq!(err(verdict(&checker, None)), anchor_error(EscrowError::ConditionFailed));
+        // Set by some other program
+        assert_eq!(err(verdict(&checker, Some((Pubkey::new_unique(), vec![CONDITION_PASSED])))), anchor_error(EscrowError::ConditionFailed));
+    }
+
+    #[test]
+    fn conditional_escrows_need_their_checker() {
+        let mut market = Market::new(500, 1_000);
+        let checker = Pubkey::new_unique();
+        market.edit_escrow(|escrow| escrow.condition_program = Some(checker));
+        market.svm.set_account(checker, TestAccount { lamports: 1_000_000, ..TestAccount::default() });
+
+        let take = |condition_program| ix(
+            crate::accounts::Take { condition_program, ..market.take_accounts() },
+            crate::instruction::Take { proof: Vec::new(), fill_amount: 1_000, min_amount_a_out: 0, max_amount_b_in: u64::MAX, max_deviation_bps: None },
+        );
+        let missing = take(None);
+        let elsewhere = take(Some(Pubkey::new_unique()));
+        // Right key, but not a program
+        let not_executable = take(Some(checker));
+
+        for take in [missing, elsewhere, not_executable] {
+            assert_eq!(market.svm.process(&[take]), Err(anchor_error(EscrowError::InvalidConditionProgram)));
+        }
+    }
+}
@@ -10,4 +10,88 @@ pub enum EscrowError {
     InvalidMintA,
     #[msg("Invalid mint b")]
     InvalidMintB,
+    #[msg("Escrow is not a native SOL escrow")]
+    NotNativeEscrow,
+    #[msg("Invalid escrow bump")]
+    InvalidBump,
+    #[msg("Escrow vault is empty")]
+    EmptyVault,
+    #[msg("Insurance fund account is required for insured escrows")]
+    MissingInsuranceFund,
+    #[msg("Vault holds less than the requested quantity")]
+    InsufficientVaultForQuantity,
+    #[msg("Escrow is not a priced escrow")]
+    NotPricedEscrow,
+    #[msg("Priced escrows must be taken with take_priced")]
+    PricedEscrow,
+    #[msg("Payment amount overflow")]
+    Overflow,
+    #[msg("Taker is not on the escrow allowlist")]
+    NotAllowlisted,
+    #[msg("Invalid taker")]
+    InvalidTaker,
+    #[msg("Deal side is already funded")]
+    AlreadyFunded,
+    #[msg("Both sides of the deal must be funded")]
+    DealNotFunded,
+    #[msg("Fully funded deals can only be finalized")]
+    DealFunded,
+    #[msg("Signer is not a party to the deal")]
+    NotDealParty,
+    #[msg("Escrow has expired")]
+    Expired,
+    #[msg("Deadline must be in the future")]
+    InvalidDeadline,
+    #[msg("Fill is too small to receive any token A")]
+    FillTooSmall,
+    #[msg("Invalid relist policy")]
+    InvalidRelistPolicy,
+    #[msg("Escrow has no relist policy")]
+    NoRelistPolicy,
+    #[msg("Escrow has not expired yet")]
+    NotExpired,
+    #[msg("Taker is not allowed to take this escrow")]
+    UnauthorizedTaker,
+    #[msg("Trade is outside the taker's slippage limits")]
+    SlippageExceeded,
+    #[msg("Mint a and mint b must differ")]
+    SameMint,
+    #[msg("Escrow price deviates too far from the oracle")]
+    PriceDeviationTooHigh,
+    #[msg("An oracle account is required for a price impact check")]
+    MissingOracle,
+    #[msg("Invalid oracle price account")]
+    InvalidOracle,
+    #[msg("Maker doesn't hold enough Token A for the deposit")]
+    InsufficientFunds,
+    #[msg("Condition program account is missing or doesn't match the escrow")]
+    InvalidConditionProgram,
+    #[msg("Settlement condition not met")]
+    ConditionFailed,
+    #[msg("Escrow terms are locked")]
+    EscrowLocked,
+    #[msg("Burn share must be below 100%")]
+    InvalidBurnBps,
+    #[msg("Maker token account for Token A is required")]
+    MissingMakerAta,
+    #[msg("Fee can't exceed 10000 bps")]
+    InvalidFee,
+    #[msg("Signer is not the escrow config authority")]
+    Unauthorized,
+    #[msg("No settlement prices recorded yet")]
+    NoPriceHistory,
+    #[msg("Escrows don't have matching mints and terms")]
+    IncompatibleEscrows,
+    #[msg("Treasury token account is missing or not owned by the treasury")]
+    InvalidTreasury,
+    #[msg("Signer is not the program's upgrade authority")]
+    NotUpgradeAuthority,
+    #[msg("Escrow config account is required for insured escrows")]
+    MissingConfig,
+    #[msg("Closing the rest of an escrow needs the maker's signature")]
+    MakerMustSign,
+    #[msg("SOL and wSOL escrows can't burn")]
+    CantBurn,
+    #[msg("Burning escrows need the Token A mint passed as writable")]
+    MissingBurnMint,
 }
\ No newline at end of file
@@ -0,0 +1,200 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token::
+{
+    transfer_checked, close_account, 
+    CloseAccount, TransferChecked
+};
+
+use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::OtcDeal;
+use crate::errors::EscrowError;
+
+
+/// Either party can walk away while the deal is still one-sided
+#[derive(Accounts)]
+pub struct AbortOtc<'info> {
+    #[account(
+        mut,
+        constraint = signer.key() == deal.maker || signer.key() == deal.taker @ EscrowError::NotDealParty,
+    )]
+    pub signer: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    pub taker: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = maker,
+        seeds = [b"otc", maker.key().as_ref(), deal.seed.to_le_bytes().as_ref()],
+        bump = deal.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        has_one = taker @ EscrowError::InvalidTaker,
+        has_one = mint_a @ EscrowError::InvalidMintA,
+        has_one = mint_b @ EscrowError::InvalidMintB,
+    )]
+    pub deal: Box<Account<'info, OtcDeal>>,
+ 
+    /// Token Accounts
+    pub mint_a: Box<InterfaceAccount<'info, Mint>>,
+    pub mint_b: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = deal,
+        associated_token::token_program = token_program
+    )]
+    pub vault_a: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = deal,
+        associated_token::token_program = token_program
+    )]
+    pub vault_b: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
+    )]
+    pub taker_ata_b: Box<InterfaceAccount<'info, TokenAccount>>,
+ 
+    /// Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> AbortOtc<'info> {
+    fn return_and_close_vaults(&mut self) -> Result<()> {
+        // Create the signer seeds for the Vaults
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"otc",
+            self.maker.to_account_info().key.as_ref(),
+            &self.deal.seed.to_le_bytes()[..],
+            &[self.deal.bump],
+        ]];
+ 
+        // Return Token A (Vault A -> Maker)
+        if self.vault_a.amount > 0 {
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from: self.vault_a.to_account_info(),
+                        to: self.maker_ata_a.to_account_info(),
+                        mint: self.mint_a.to_account_info(),
+                        authority: self.deal.to_account_info(),
+                    },
+                    &signer_seeds
+                ),
+                self.vault_a.amount,
+                self.mint_a.decimals
+            )?;
+        }
+ 
+        // Return Token B (Vault B -> Taker)
+        if self.vault_b.amount > 0 {
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    TransferChecked {
+                        from: self.vault_b.to_account_info(),
+                        to: self.taker_ata_b.to_account_info(),
+                        mint: self.mint_b.to_account_info(),
+                        authority: self.deal.to_account_info(),
+                    },
+                    &signer_seeds
+                ),
+                self.vault_b.amount,
+                self.mint_b.decimals
+            )?;
+        }
+ 
+        // Close both Vaults, the maker paid their rent
+        for vault in [self.vault_a.to_account_info(), self.vault_b.to_account_info()] {
+            close_account(
+                CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    CloseAccount {
+                        account: vault,
+                        authority: self.deal.to_account_info(),
+                        destination: self.maker.to_account_info(),
+                    },
+                    &signer_seeds
+                ),
+            )?;
+        }
+ 
+        Ok(())
+    }
+}
+ 
+pub fn handler(ctx: Context<AbortOtc>) -> Result<()> {
+    // Once both sides are in the deal can only settle
+    require!(!ctx.accounts.deal.is_funded(), EscrowError::DealFunded);
+ 
+    // Return each side's deposit and close the Vaults
+    ctx.accounts.return_and_close_vaults()?;
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn abort_ix(otc: &Otc, signer: Pubkey) -> anchor_lang::solana_program::instruction::Instruction {
+        let market = &otc.market;
+        ix(
+            crate::accounts::AbortOtc {
+                signer,
+                maker: market.maker,
+                taker: market.taker,
+                deal: otc.deal,
+                mint_a: market.mint_a,
+                mint_b: market.mint_b,
+                vault_a: otc.vault(&market.mint_a),
+                vault_b: otc.vault(&market.mint_b),
+                maker_ata_a: market.ata(&market.maker, &market.mint_a),
+                taker_ata_b: market.ata(&market.taker, &market.mint_b),
+                associated_token_program: anchor_spl::associated_token::ID,
+                token_program: anchor_spl::token::ID,
+                system_program: system_program::ID,
+            },
+            crate::instruction::AbortOtc {},
+        )
+    }
+
+    #[test]
+    fn only_the_two_parties_abort() {
+        let mut otc = Otc::new(500, 1_000);
+        let stranger = otc.market.svm.wallet();
+        let abort = abort_ix(&otc, stranger);
+
+        assert_eq!(otc.market.svm.process(&[abort]), Err(anchor_error(EscrowError::NotDealParty)));
+    }
+
+    #[test]
+    fn funded_deals_cant_be_aborted() {
+        let mut otc = Otc::new(500, 1_000);
+        otc.set_deal(OtcDeal { funded_b: true, ..otc.deal() });
+
+        for party in [otc.market.maker, otc.market.taker] {
+            let abort = abort_ix(&otc, party);
+            assert_eq!(otc.market.svm.process(&[abort]), Err(anchor_error(EscrowError::DealFunded)));
+        }
+    }
+}
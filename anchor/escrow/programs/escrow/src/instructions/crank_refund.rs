@@ -0,0 +1,170 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Escrow, EscrowConfig, Stats};
+use crate::errors::EscrowError;
+use crate::EscrowRefunded;
+use super::refund::withdraw_and_close_vault;
+
+/// Same as `timeout_refund`, but the caller earns the configured
+/// `crank_bounty` out of the escrow's rent
+#[derive(Accounts)]
+pub struct CrankRefund<'info> {
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = maker,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        has_one = mint_a @ EscrowError::InvalidMintA,
+    )]
+    pub escrow: Account<'info, Escrow>,
+ 
+    /// Token Accounts
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = caller,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+
+    /// Leave out to crank without a bounty
+    #[account(
+        seeds = [EscrowConfig::SEED],
+        bump = escrow_config.bump,
+    )]
+    pub escrow_config: Option<Account<'info, EscrowConfig>>,
+ 
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+ 
+    /// Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CrankRefund<'info> {
+    fn withdraw_and_close_vault(&mut self) -> Result<()> {
+        withdraw_and_close_vault(
+            &self.escrow,
+            &self.maker.to_account_info(),
+            &self.mint_a,
+            &self.vault,
+            &self.maker_ata_a,
+            &self.token_program,
+        )
+    }
+
+    fn pay_bounty(&mut self) -> Result<()> {
+        // Taken from the escrow's rent, the rest still goes to the maker on close
+        let bounty = bounty(self.escrow_config.as_deref(), self.escrow.get_lamports());
+        self.escrow.sub_lamports(bounty)?;
+        self.caller.add_lamports(bounty)?;
+
+        Ok(())
+    }
+}
+ 
+/// Bounty for cranking an escrow holding `lamports`, nothing without a config
+fn bounty(escrow_config: Option<&EscrowConfig>, lamports: u64) -> u64 {
+    escrow_config.map_or(0, |config| config.crank_bounty).min(lamports)
+}
+ 
+pub fn handler(ctx: Context<CrankRefund>) -> Result<()> {
+    // Only escrows with a deadline can time out
+    require!(ctx.accounts.escrow.is_expired(Clock::get()?.unix_timestamp), EscrowError::NotExpired);
+
+    // Withdraw and close the Vault (Vault -> Maker)
+    ctx.accounts.withdraw_and_close_vault()?;
+
+    // Reward the caller for cleaning up
+    ctx.accounts.pay_bounty()?;
+
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_refund();
+    }
+
+    emit!(EscrowRefunded {
+        escrow: ctx.accounts.escrow.key(),
+        maker: ctx.accounts.maker.key(),
+    });
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn crank_ix(market: &Market, caller: Pubkey) -> anchor_lang::solana_program::instruction::Instruction {
+        let refund = market.timeout_refund_accounts(caller);
+        ix(
+            crate::accounts::CrankRefund {
+                caller,
+                maker: refund.maker,
+                escrow: refund.escrow,
+                mint_a: refund.mint_a,
+                vault: refund.vault,
+                maker_ata_a: refund.maker_ata_a,
+                escrow_config: Some(market.config),
+                stats: refund.stats,
+                associated_token_program: refund.associated_token_program,
+                token_program: refund.token_program,
+                system_program: refund.system_program,
+            },
+            crate::instruction::CrankRefund {},
+        )
+    }
+
+    #[test]
+    fn cranking_waits_for_the_deadline() {
+        let mut market = Market::new(500, 1_000);
+        let deadline = market.svm.now + 60;
+        market.edit_escrow(|escrow| escrow.deadline = deadline);
+        let keeper = market.svm.wallet();
+
+        market.svm.now = deadline;
+        assert_eq!(market.svm.process(&[crank_ix(&market, keeper)]), Err(anchor_error(EscrowError::NotExpired)));
+    }
+
+    #[test]
+    fn bounty_comes_from_the_config() {
+        let config = EscrowConfig {
+            authority: Pubkey::new_unique(),
+            treasury: Pubkey::new_unique(),
+            fee_bps: 0,
+            bump: 255,
+            crank_bounty: 2_000_000,
+            insurance_premium: 0,
+        };
+
+        assert_eq!(bounty(Some(&config), 5_000_000), 2_000_000);
+        assert_eq!(bounty(None, 5_000_000), 0);
+
+        // Never more than the escrow holds
+        assert_eq!(bounty(Some(&config), 1_500_000), 1_500_000);
+    }
+}
@@ -0,0 +1,63 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Escrow;
+use crate::errors::EscrowError;
+
+/// One-way: after this `update`, relisting and condition changes are rejected
+#[derive(Accounts)]
+pub struct Finalize<'info> {
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+ 
+pub fn handler(ctx: Context<Finalize>) -> Result<()> {
+    ctx.accounts.escrow.locked = true;
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::RelistPolicy;
+    use crate::test_utils::*;
+
+    #[test]
+    fn finalized_escrows_reject_every_change() {
+        let mut market = Market::new(500, 1_000);
+        let (maker, escrow) = (market.maker, market.escrow);
+        let keeper = market.svm.wallet();
+
+        market.svm.process(&[ix(crate::accounts::Finalize { maker, escrow }, crate::instruction::Finalize {})]).unwrap();
+        assert!(market.escrow().locked);
+
+        // Otherwise valid for a relist policy
+        let deadline = market.svm.now + 60;
+        market.edit_escrow(|escrow| escrow.deadline = deadline);
+
+        let changes = [
+            ix(market.update_accounts(), crate::instruction::Update { receive: 2_000, deadline: 0 }),
+            ix(market.update_accounts(), crate::instruction::UpdateReceive { new_receive: 2_000 }),
+            ix(crate::accounts::SetCondition { maker, escrow }, crate::instruction::SetCondition { condition_program: Some(Pubkey::new_unique()) }),
+            ix(crate::accounts::SetBurn { maker, escrow }, crate::instruction::SetBurn { burn_bps: 250 }),
+            ix(
+                crate::accounts::SetRelistPolicy { maker, escrow },
+                crate::instruction::SetRelistPolicy { relist: Some(RelistPolicy { adjust_bps: 1_000, period: 3_600 }) },
+            ),
+            ix(crate::accounts::RelistExpired { keeper, maker, escrow }, crate::instruction::RelistExpired {}),
+        ];
+        for change in changes {
+            assert_eq!(market.svm.process(&[change]), Err(anchor_error(EscrowError::EscrowLocked)));
+        }
+
+        // Takes still go ahead, this one gets past the checks and fails on the fill
+        assert_eq!(market.svm.process(&[market.take_ix(0)]), Err(anchor_error(EscrowError::InvalidAmount)));
+        assert_eq!((market.escrow().receive, market.escrow().burn_bps), (1_000, 0));
+    }
+}
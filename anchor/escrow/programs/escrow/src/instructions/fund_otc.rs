@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer_checked, TransferChecked};
+use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
+
+use crate::state::OtcDeal;
+use crate::errors::EscrowError;
+
+#[derive(Accounts)]
+pub struct FundOtc<'info> {
+    pub taker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"otc", deal.maker.as_ref(), deal.seed.to_le_bytes().as_ref()],
+        bump = deal.bump,
+        has_one = taker @ EscrowError::InvalidTaker,
+        has_one = mint_b @ EscrowError::InvalidMintB,
+    )]
+    pub deal: Box<Account<'info, OtcDeal>>,
+ 
+    /// Token Accounts
+    pub mint_b: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
+    )]
+    pub taker_ata_b: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = deal,
+        associated_token::token_program = token_program
+    )]
+    pub vault_b: Box<InterfaceAccount<'info, TokenAccount>>,
+ 
+    /// Programs
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FundOtc<'info> {
+    fn deposit_tokens(&mut self) -> Result<()> {
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.taker_ata_b.to_account_info(),
+                    mint: self.mint_b.to_account_info(),
+                    to: self.vault_b.to_account_info(),
+                    authority: self.taker.to_account_info(),
+                },
+            ),
+            self.deal.amount_b,
+            self.mint_b.decimals
+        )?;
+
+        self.deal.funded_b = true;
+ 
+        Ok(())
+    }
+}
+ 
+pub fn handler(ctx: Context<FundOtc>) -> Result<()> {
+    require!(!ctx.accounts.deal.funded_b, EscrowError::AlreadyFunded);
+ 
+    // Deposit the taker's side
+    ctx.accounts.deposit_tokens()?;
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn fund_ix(otc: &Otc, taker: Pubkey) -> anchor_lang::solana_program::instruction::Instruction {
+        let market = &otc.market;
+        ix(
+            crate::accounts::FundOtc {
+                taker,
+                deal: otc.deal,
+                mint_b: market.mint_b,
+                taker_ata_b: market.ata(&taker, &market.mint_b),
+                vault_b: otc.vault(&market.mint_b),
+                token_program: anchor_spl::token::ID,
+            },
+            crate::instruction::FundOtc {},
+        )
+    }
+
+    #[test]
+    fn only_the_named_taker_funds() {
+        let mut otc = Otc::new(500, 1_000);
+        let stranger = otc.market.svm.wallet();
+        let mint_b = otc.market.mint_b;
+        otc.market.svm.set_token_account(otc.market.ata(&stranger, &mint_b), mint_b, stranger, 1_000);
+        let fund = fund_ix(&otc, stranger);
+
+        assert_eq!(otc.market.svm.process(&[fund]), Err(anchor_error(EscrowError::InvalidTaker)));
+        assert!(!otc.deal().funded_b);
+    }
+
+    #[test]
+    fn taker_funds_once() {
+        let mut otc = Otc::new(500, 1_000);
+        otc.set_deal(OtcDeal { funded_b: true, ..otc.deal() });
+        let fund = fund_ix(&otc, otc.market.taker);
+
+        assert_eq!(otc.market.svm.process(&[fund]), Err(anchor_error(EscrowError::AlreadyFunded)));
+    }
+}
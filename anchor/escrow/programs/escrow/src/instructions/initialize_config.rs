@@ -0,0 +1,40 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{EscrowConfig, CRANK_BOUNTY, INSURANCE_PREMIUM, MAX_FEE_BPS};
+use crate::errors::EscrowError;
+use crate::program::BlueshiftAnchorEscrow;
+
+/// One-time setup by the program's upgrade authority, who becomes the config authority
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = EscrowConfig::INIT_SPACE + EscrowConfig::DISCRIMINATOR.len(),
+        seeds = [EscrowConfig::SEED],
+        bump,
+    )]
+    pub escrow_config: Account<'info, EscrowConfig>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, BlueshiftAnchorEscrow>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ EscrowError::NotUpgradeAuthority)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+ 
+pub fn handler(ctx: Context<InitializeConfig>, fee_bps: u16, treasury: Pubkey) -> Result<()> {
+    require!(fee_bps <= MAX_FEE_BPS, EscrowError::InvalidFee);
+ 
+    ctx.accounts.escrow_config.set_inner(EscrowConfig {
+        authority: ctx.accounts.authority.key(),
+        treasury,
+        fee_bps,
+        bump: ctx.bumps.escrow_config,
+        crank_bounty: CRANK_BOUNTY,
+        insurance_premium: INSURANCE_PREMIUM,
+    });
+ 
+    Ok(())
+}
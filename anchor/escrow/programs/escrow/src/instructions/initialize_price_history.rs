@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::Mint;
+
+use crate::state::PriceHistory;
+
+/// Opens the price history of a mint pair. Anyone can pay for it, takes only
+/// record into it when they pass it.
+#[derive(Accounts)]
+pub struct InitializePriceHistory<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        space = PriceHistory::INIT_SPACE + PriceHistory::DISCRIMINATOR.len(),
+        seeds = [PriceHistory::SEED, mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump,
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+    pub system_program: Program<'info, System>,
+}
+ 
+pub fn handler(ctx: Context<InitializePriceHistory>) -> Result<()> {
+    let price_history = &mut ctx.accounts.price_history;
+    price_history.mint_a = ctx.accounts.mint_a.key();
+    price_history.mint_b = ctx.accounts.mint_b.key();
+    price_history.bump = ctx.bumps.price_history;
+ 
+    Ok(())
+}
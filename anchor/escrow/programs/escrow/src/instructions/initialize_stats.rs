@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{EscrowConfig, Stats};
+use crate::errors::EscrowError;
+
+/// One-time setup of the protocol counters by the config authority. Every
+/// instruction that updates them takes them as an optional account.
+#[derive(Accounts)]
+pub struct InitializeStats<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [EscrowConfig::SEED],
+        bump = escrow_config.bump,
+        has_one = authority @ EscrowError::Unauthorized,
+    )]
+    pub escrow_config: Account<'info, EscrowConfig>,
+    #[account(
+        init,
+        payer = authority,
+        space = Stats::INIT_SPACE + Stats::DISCRIMINATOR.len(),
+        seeds = [Stats::SEED],
+        bump,
+    )]
+    pub stats: Account<'info, Stats>,
+    pub system_program: Program<'info, System>,
+}
+ 
+pub fn handler(ctx: Context<InitializeStats>) -> Result<()> {
+    ctx.accounts.stats.bump = ctx.bumps.stats;
+ 
+    Ok(())
+}
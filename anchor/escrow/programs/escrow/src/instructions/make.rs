@@ -1,10 +1,14 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{transfer_checked, TransferChecked};
+use anchor_lang::system_program;
+use anchor_lang::solana_program::{instruction::{AccountMeta, Instruction}, program::invoke};
+use anchor_spl::token::{spl_token, sync_native, SyncNative};
+use anchor_spl::token_2022::spl_token_2022;
 use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
 use anchor_spl::associated_token::AssociatedToken;
 
-use crate::state::Escrow;
+use crate::state::{Escrow, EscrowConfig, Stats};
 use crate::errors::EscrowError;
+use crate::EscrowMade;
 
 #[derive(Accounts)]
 #[instruction(seed: u64)]
@@ -16,7 +20,7 @@ pub struct Make<'info> {
         init,
         payer = maker,
         space = Escrow::INIT_SPACE + Escrow::DISCRIMINATOR.len(),
-        seeds = ["escrow".as_bytes(), maker.key().as_ref(), seed.to_le_bytes().as_ref()],
+        seeds = [Escrow::SEED, maker.key().as_ref(), seed.to_le_bytes().as_ref()],
         bump,
     )]
     pub escrow: Account<'info, Escrow>,
@@ -32,13 +36,14 @@ pub struct Make<'info> {
     )]
     pub mint_b: InterfaceAccount<'info, Mint>,
 
+    /// Not needed when the deposit is wrapped from the maker's lamports
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = maker,
         associated_token::token_program = token_program
     )]
-    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         init,
@@ -48,6 +53,29 @@ pub struct Make<'info> {
         associated_token::token_program = token_program
     )]
     pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// Only needed for insured escrows
+    #[account(
+        mut,
+        seeds = [b"insurance"],
+        bump,
+    )]
+    pub insurance_fund: Option<SystemAccount<'info>>,
+
+    /// Only needed for insured escrows, it sets the premium
+    #[account(
+        seeds = [EscrowConfig::SEED],
+        bump = escrow_config.bump,
+    )]
+    pub escrow_config: Option<Box<Account<'info, EscrowConfig>>>,
+
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
  
     /// Programs
     pub associated_token_program: Program<'info, AssociatedToken>,
@@ -57,48 +85,211 @@ pub struct Make<'info> {
 
 
 impl<'info> Make<'info> {
-    fn populate_escrow(&mut self, seed: u64, amount: u64, bump: u8) -> Result<()> {
+    #[allow(clippy::too_many_arguments)]
+    fn populate_escrow(&mut self, seed: u64, amount: u64, bump: u8, insured: bool, allowlist_root: Option<[u8; 32]>, deadline: i64, allowed_taker: Pubkey, deposit: u64, unwrap_sol: bool) -> Result<()> {
         self.escrow.set_inner(Escrow {
             seed,
             maker: self.maker.key(),
             mint_a: self.mint_a.key(),
             mint_b: self.mint_b.key(),
             receive: amount,
+            amount: deposit,
             bump,
+            insured,
+            price_per_unit: 0,
+            allowlist_root,
+            deadline,
+            relist: None,
+            allowed_taker,
+            condition_program: None,
+            locked: false,
+            created_at: Clock::get()?.unix_timestamp,
+            burn_bps: 0,
+            unwrap_sol,
         });
  
         Ok(())
     }
  
-    fn deposit_tokens(&self, amount: u64) -> Result<()> {
-        transfer_checked(
+    fn deposit_tokens(&self, amount: u64, extra_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        let maker_ata_a = self.maker_ata_a.as_ref().ok_or(EscrowError::MissingMakerAta)?;
+        require!(maker_ata_a.amount >= amount, EscrowError::InsufficientFunds);
+
+        let mut ix = spl_token_2022::instruction::transfer_checked(
+            self.token_program.key,
+            &maker_ata_a.key(),
+            &self.mint_a.key(),
+            &self.vault.key(),
+            &self.maker.key(),
+            &[],
+            amount,
+            self.mint_a.decimals,
+        )?;
+
+        forward_extra_accounts(&mut ix, extra_accounts);
+
+        let mut account_infos = vec![
+            maker_ata_a.to_account_info(),
+            self.mint_a.to_account_info(),
+            self.vault.to_account_info(),
+            self.maker.to_account_info(),
+        ];
+        account_infos.extend_from_slice(extra_accounts);
+
+        invoke(&ix, &account_infos)?;
+ 
+        Ok(())
+    }
+
+    fn wrap_lamports(&self, amount: u64) -> Result<()> {
+        require_keys_eq!(self.mint_a.key(), spl_token::native_mint::ID, EscrowError::InvalidMintA);
+        require!(self.maker.lamports() >= amount, EscrowError::InsufficientFunds);
+
+        // Lamports sent to a wSOL account only count as tokens once synced
+        system_program::transfer(
             CpiContext::new(
-                self.token_program.to_account_info(),
-                TransferChecked {
-                    from: self.maker_ata_a.to_account_info(),
-                    mint: self.mint_a.to_account_info(),
+                self.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: self.maker.to_account_info(),
                     to: self.vault.to_account_info(),
-                    authority: self.maker.to_account_info(),
                 },
             ),
             amount,
-            self.mint_a.decimals
         )?;
- 
+
+        sync_native(CpiContext::new(
+            self.token_program.to_account_info(),
+            SyncNative {
+                account: self.vault.to_account_info(),
+            },
+        ))?;
+
+        Ok(())
+    }
+
+    fn pay_premium(&self) -> Result<()> {
+        let insurance_fund = self.insurance_fund.as_ref().ok_or(EscrowError::MissingInsuranceFund)?;
+        let escrow_config = self.escrow_config.as_ref().ok_or(EscrowError::MissingConfig)?;
+
+        system_program::transfer(
+            CpiContext::new(
+                self.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: self.maker.to_account_info(),
+                    to: insurance_fund.to_account_info(),
+                },
+            ),
+            escrow_config.insurance_premium,
+        )?;
+
         Ok(())
     }
 }
  
-pub fn handler(ctx: Context<Make>, seed: u64, receive: u64, amount: u64) -> Result<()> {
+/// Token-2022 extensions like transfer hooks need their extra accounts on the
+/// transfer itself, the client resolves them into remaining accounts
+fn forward_extra_accounts(ix: &mut Instruction, extra_accounts: &[AccountInfo]) {
+    ix.accounts.extend(extra_accounts.iter().map(|account| AccountMeta {
+        pubkey: *account.key,
+        is_signer: account.is_signer,
+        is_writable: account.is_writable,
+    }));
+}
+
+/// Checks every make runs on its terms. `receive` is whatever the maker asks
+/// for in Token B, a total or a per-unit price.
+pub fn check_terms(mint_a: &Pubkey, mint_b: &Pubkey, receive: u64, amount: u64) -> Result<()> {
     // Validate the amount
     require!(receive > 0, EscrowError::InvalidAmount);
     require!(amount > 0, EscrowError::InvalidAmount);
+
+    // Swapping a mint for itself makes no sense
+    require_keys_neq!(*mint_a, *mint_b, EscrowError::SameMint);
+
+    Ok(())
+}
+ 
+#[allow(clippy::too_many_arguments)]
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, Make<'info>>, seed: u64, receive: u64, amount: u64, insured: bool, allowlist_root: Option<[u8; 32]>, deadline: i64, allowed_taker: Pubkey, wrap_sol: bool) -> Result<()> {
+    check_terms(&ctx.accounts.mint_a.key(), &ctx.accounts.mint_b.key(), receive, amount)?;
+
+    // A deadline, if any, has to be in the future
+    require!(deadline == 0 || deadline > Clock::get()?.unix_timestamp, EscrowError::InvalidDeadline);
  
     // Save the Escrow Data
-    ctx.accounts.populate_escrow(seed, receive, ctx.bumps.escrow)?;
+    ctx.accounts.populate_escrow(seed, receive, ctx.bumps.escrow, insured, allowlist_root, deadline, allowed_taker, amount, wrap_sol)?;
  
-    // Deposit Tokens
-    ctx.accounts.deposit_tokens(amount)?;
+    // Deposit Tokens, wSOL escrows can be funded straight from lamports and
+    // get unwrapped again on take and refund
+    if wrap_sol {
+        ctx.accounts.wrap_lamports(amount)?;
+    } else {
+        ctx.accounts.deposit_tokens(amount, ctx.remaining_accounts)?;
+    }
+
+    // Transfer-fee mints credit the vault less than was sent, record what
+    // actually arrived so takes and refunds never promise more than is there
+    ctx.accounts.vault.reload()?;
+    ctx.accounts.escrow.amount = ctx.accounts.vault.amount;
+
+    // Insured escrows pay the premium into the insurance fund
+    if insured {
+        ctx.accounts.pay_premium()?;
+    }
+
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_make();
+    }
+
+    emit!(EscrowMade {
+        escrow: ctx.accounts.escrow.key(),
+        maker: ctx.accounts.maker.key(),
+        mint_a: ctx.accounts.mint_a.key(),
+        mint_b: ctx.accounts.mint_b.key(),
+        amount: ctx.accounts.escrow.amount,
+        receive,
+    });
  
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn both_sides_need_an_amount() {
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        check_terms(&mint_a, &mint_b, 1_000, 500).unwrap();
+        assert_eq!(err(check_terms(&mint_a, &mint_b, 0, 500)), anchor_error(EscrowError::InvalidAmount));
+        assert_eq!(err(check_terms(&mint_a, &mint_b, 1_000, 0)), anchor_error(EscrowError::InvalidAmount));
+    }
+
+    #[test]
+    fn extra_accounts_keep_their_flags() {
+        let (from, mint, to, authority) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut ix = spl_token_2022::instruction::transfer_checked(&spl_token_2022::ID, &from, &mint, &to, &authority, &[], 500, 6).unwrap();
+
+        let hook_program = account_info(Pubkey::new_unique(), TestAccount::default());
+        let mut validation = account_info(Pubkey::new_unique(), TestAccount::default());
+        validation.is_writable = false;
+        let mut cosigner = account_info(Pubkey::new_unique(), TestAccount::default());
+        cosigner.is_signer = true;
+        forward_extra_accounts(&mut ix, &[hook_program.clone(), validation.clone(), cosigner.clone()]);
+
+        // Appended after the transfer's own accounts, in order
+        assert_eq!(ix.accounts.len(), 7);
+        assert_eq!(ix.accounts[4], AccountMeta::new(*hook_program.key, false));
+        assert_eq!(ix.accounts[5], AccountMeta::new_readonly(*validation.key, false));
+        assert_eq!(ix.accounts[6], AccountMeta::new(*cosigner.key, true));
+    }
+
+    #[test]
+    fn mints_have_to_differ() {
+        let mint = Pubkey::new_unique();
+
+        assert_eq!(err(check_terms(&mint, &mint, 1_000, 500)), anchor_error(EscrowError::SameMint));
+    }
+}
@@ -0,0 +1,115 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer_checked, TransferChecked};
+use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::OtcDeal;
+use super::make::check_terms;
+
+#[derive(Accounts)]
+#[instruction(seed: u64)]
+pub struct MakeOtc<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = OtcDeal::INIT_SPACE + OtcDeal::DISCRIMINATOR.len(),
+        seeds = [b"otc", maker.key().as_ref(), seed.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub deal: Box<Account<'info, OtcDeal>>,
+ 
+    /// Token Accounts
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_a: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_b: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init,
+        payer = maker,
+        associated_token::mint = mint_a,
+        associated_token::authority = deal,
+        associated_token::token_program = token_program
+    )]
+    pub vault_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Receives the taker's side, can be funded in this same transaction
+    #[account(
+        init,
+        payer = maker,
+        associated_token::mint = mint_b,
+        associated_token::authority = deal,
+        associated_token::token_program = token_program
+    )]
+    pub vault_b: Box<InterfaceAccount<'info, TokenAccount>>,
+ 
+    /// Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MakeOtc<'info> {
+    fn populate_deal(&mut self, seed: u64, taker: Pubkey, amount_a: u64, amount_b: u64, bump: u8) -> Result<()> {
+        self.deal.set_inner(OtcDeal {
+            seed,
+            maker: self.maker.key(),
+            taker,
+            mint_a: self.mint_a.key(),
+            mint_b: self.mint_b.key(),
+            amount_a,
+            amount_b,
+            funded_a: true,
+            funded_b: false,
+            bump,
+        });
+ 
+        Ok(())
+    }
+ 
+    fn deposit_tokens(&self, amount: u64) -> Result<()> {
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.maker_ata_a.to_account_info(),
+                    mint: self.mint_a.to_account_info(),
+                    to: self.vault_a.to_account_info(),
+                    authority: self.maker.to_account_info(),
+                },
+            ),
+            amount,
+            self.mint_a.decimals
+        )?;
+ 
+        Ok(())
+    }
+}
+ 
+pub fn handler(ctx: Context<MakeOtc>, seed: u64, taker: Pubkey, amount_a: u64, amount_b: u64) -> Result<()> {
+    check_terms(&ctx.accounts.mint_a.key(), &ctx.accounts.mint_b.key(), amount_b, amount_a)?;
+ 
+    // Save the Deal Data
+    ctx.accounts.populate_deal(seed, taker, amount_a, amount_b, ctx.bumps.deal)?;
+ 
+    // Deposit the maker's side
+    ctx.accounts.deposit_tokens(amount_a)?;
+ 
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer_checked, TransferChecked};
+use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Escrow, Stats};
+use super::make::check_terms;
+
+#[derive(Accounts)]
+#[instruction(seed: u64)]
+pub struct MakePriced<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = Escrow::INIT_SPACE + Escrow::DISCRIMINATOR.len(),
+        seeds = [Escrow::SEED, maker.key().as_ref(), seed.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+ 
+    /// Token Accounts
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = maker,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+ 
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+ 
+    /// Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> MakePriced<'info> {
+    fn populate_escrow(&mut self, seed: u64, price_per_unit: u64, bump: u8, deposit: u64) -> Result<()> {
+        self.escrow.set_inner(Escrow {
+            seed,
+            maker: self.maker.key(),
+            mint_a: self.mint_a.key(),
+            mint_b: self.mint_b.key(),
+            receive: 0,
+            amount: deposit,
+            bump,
+            insured: false,
+            price_per_unit,
+            allowlist_root: None,
+            deadline: 0,
+            relist: None,
+            allowed_taker: Pubkey::default(),
+            condition_program: None,
+            locked: false,
+            created_at: Clock::get()?.unix_timestamp,
+            burn_bps: 0,
+            unwrap_sol: false,
+        });
+ 
+        Ok(())
+    }
+ 
+    fn deposit_tokens(&self, amount: u64) -> Result<()> {
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.maker_ata_a.to_account_info(),
+                    mint: self.mint_a.to_account_info(),
+                    to: self.vault.to_account_info(),
+                    authority: self.maker.to_account_info(),
+                },
+            ),
+            amount,
+            self.mint_a.decimals
+        )?;
+ 
+        Ok(())
+    }
+}
+ 
+pub fn handler(ctx: Context<MakePriced>, seed: u64, price_per_unit: u64, amount: u64) -> Result<()> {
+    check_terms(&ctx.accounts.mint_a.key(), &ctx.accounts.mint_b.key(), price_per_unit, amount)?;
+ 
+    // Save the Escrow Data
+    ctx.accounts.populate_escrow(seed, price_per_unit, ctx.bumps.escrow, amount)?;
+ 
+    // Deposit Tokens
+    ctx.accounts.deposit_tokens(amount)?;
+
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_make();
+    }
+ 
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::token_interface::Mint;
+
+use crate::state::{Escrow, Stats};
+use crate::errors::EscrowError;
+
+#[derive(Accounts)]
+#[instruction(seed: u64)]
+pub struct MakeSol<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = Escrow::INIT_SPACE + Escrow::DISCRIMINATOR.len(),
+        seeds = [Escrow::SEED, maker.key().as_ref(), seed.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    /// Token asked for in exchange, SOL when omitted
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
+
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+ 
+    /// Programs
+    pub system_program: Program<'info, System>,
+}
+
+
+impl<'info> MakeSol<'info> {
+    fn populate_escrow(&mut self, seed: u64, receive: u64, bump: u8, deposit: u64) -> Result<()> {
+        self.escrow.set_inner(Escrow {
+            seed,
+            maker: self.maker.key(),
+            mint_a: system_program::ID,
+            mint_b: self.mint_b.as_ref().map_or(system_program::ID, |mint| mint.key()),
+            receive,
+            amount: deposit,
+            bump,
+            insured: false,
+            price_per_unit: 0,
+            allowlist_root: None,
+            deadline: 0,
+            relist: None,
+            allowed_taker: Pubkey::default(),
+            condition_program: None,
+            locked: false,
+            created_at: Clock::get()?.unix_timestamp,
+            burn_bps: 0,
+            unwrap_sol: false,
+        });
+
+        Ok(())
+    }
+
+    fn deposit_lamports(&self, amount: u64) -> Result<()> {
+        // The deposit sits on top of the rent-exempt balance of the escrow
+        transfer(
+            CpiContext::new(
+                self.system_program.to_account_info(),
+                Transfer {
+                    from: self.maker.to_account_info(),
+                    to: self.escrow.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        Ok(())
+    }
+}
+
+pub fn handler(ctx: Context<MakeSol>, seed: u64, receive: u64, amount: u64) -> Result<()> {
+    // Validate the amount
+    require!(receive > 0, EscrowError::InvalidAmount);
+    require!(amount > 0, EscrowError::InvalidAmount);
+
+    // Save the Escrow Data
+    ctx.accounts.populate_escrow(seed, receive, ctx.bumps.escrow, amount)?;
+
+    // Deposit Lamports
+    ctx.accounts.deposit_lamports(amount)?;
+
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_make();
+    }
+
+    Ok(())
+}
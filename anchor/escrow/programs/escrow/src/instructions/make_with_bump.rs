@@ -0,0 +1,251 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer};
+use anchor_spl::token::{transfer_checked, TransferChecked};
+use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Escrow, Stats};
+use super::make::check_terms;
+
+/// Same as `Make`, but the client supplies the escrow bump so the PDA is
+/// checked with `create_program_address` instead of searched for with
+/// `find_program_address`, which saves the bump search on every make.
+///
+/// Anchor's `init` always runs the bump search, so the seeds are checked
+/// here and the escrow is created the way `init` would in the handler.
+#[derive(Accounts)]
+#[instruction(seed: u64, receive: u64, amount: u64, bump: u8)]
+pub struct MakeWithBump<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [Escrow::SEED, maker.key().as_ref(), seed.to_le_bytes().as_ref()],
+        bump = bump,
+    )]
+    /// CHECK: Derived from the seeds and the supplied bump, created in the handler
+    pub escrow: UncheckedAccount<'info>,
+ 
+    /// Token Accounts
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_a: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_b: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = maker,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+ 
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+ 
+    /// Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+
+impl<'info> MakeWithBump<'info> {
+    fn create_escrow(&self, seed: u64, bump: u8) -> Result<()> {
+        let seed_binding = seed.to_le_bytes();
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            Escrow::SEED,
+            self.maker.to_account_info().key.as_ref(),
+            &seed_binding[..],
+            &[bump],
+        ]];
+
+        let space = Escrow::INIT_SPACE + Escrow::DISCRIMINATOR.len();
+        let rent = Rent::get()?.minimum_balance(space);
+        let lamports = self.escrow.lamports();
+
+        // Same as `init`: a PDA someone already sent lamports to can't be
+        // created, so top it up, then allocate and assign it instead
+        if lamports == 0 {
+            create_account(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    CreateAccount {
+                        from: self.maker.to_account_info(),
+                        to: self.escrow.to_account_info(),
+                    },
+                    &signer_seeds
+                ),
+                rent,
+                space as u64,
+                &crate::ID,
+            )?;
+        } else {
+            if rent > lamports {
+                transfer(
+                    CpiContext::new(
+                        self.system_program.to_account_info(),
+                        Transfer {
+                            from: self.maker.to_account_info(),
+                            to: self.escrow.to_account_info(),
+                        },
+                    ),
+                    rent - lamports,
+                )?;
+            }
+            allocate(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    Allocate {
+                        account_to_allocate: self.escrow.to_account_info(),
+                    },
+                    &signer_seeds
+                ),
+                space as u64,
+            )?;
+            assign(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    Assign {
+                        account_to_assign: self.escrow.to_account_info(),
+                    },
+                    &signer_seeds
+                ),
+                &crate::ID,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn populate_escrow(&mut self, seed: u64, amount: u64, bump: u8, deposit: u64) -> Result<()> {
+        let escrow = Escrow {
+            seed,
+            maker: self.maker.key(),
+            mint_a: self.mint_a.key(),
+            mint_b: self.mint_b.key(),
+            receive: amount,
+            amount: deposit,
+            bump,
+            insured: false,
+            price_per_unit: 0,
+            allowlist_root: None,
+            deadline: 0,
+            relist: None,
+            allowed_taker: Pubkey::default(),
+            condition_program: None,
+            locked: false,
+            created_at: Clock::get()?.unix_timestamp,
+            burn_bps: 0,
+            unwrap_sol: false,
+        };
+
+        let mut data = self.escrow.try_borrow_mut_data()?;
+        escrow.try_serialize(&mut &mut data[..])?;
+ 
+        Ok(())
+    }
+ 
+    fn deposit_tokens(&self, amount: u64) -> Result<()> {
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.maker_ata_a.to_account_info(),
+                    mint: self.mint_a.to_account_info(),
+                    to: self.vault.to_account_info(),
+                    authority: self.maker.to_account_info(),
+                },
+            ),
+            amount,
+            self.mint_a.decimals
+        )?;
+ 
+        Ok(())
+    }
+}
+ 
+pub fn handler(ctx: Context<MakeWithBump>, seed: u64, receive: u64, amount: u64, bump: u8) -> Result<()> {
+    check_terms(&ctx.accounts.mint_a.key(), &ctx.accounts.mint_b.key(), receive, amount)?;
+ 
+    // Create and save the Escrow Data, the seeds constraint already checked the bump
+    ctx.accounts.create_escrow(seed, bump)?;
+    ctx.accounts.populate_escrow(seed, receive, bump, amount)?;
+ 
+    // Deposit Tokens
+    ctx.accounts.deposit_tokens(amount)?;
+
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_make();
+    }
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compute units per `create_program_address`, and per bump `find_program_address` tries
+    const PDA_DERIVATION_CU: u64 = 1_500;
+
+    fn find_escrow(maker: &Pubkey, seed: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[Escrow::SEED, maker.as_ref(), &seed.to_le_bytes()], &crate::ID)
+    }
+
+    fn derive_with_bump(maker: &Pubkey, seed: u64, bump: u8) -> Option<Pubkey> {
+        Pubkey::create_program_address(&[Escrow::SEED, maker.as_ref(), &seed.to_le_bytes(), &[bump]], &crate::ID).ok()
+    }
+
+    #[test]
+    fn correct_bump_derives_the_escrow() {
+        let maker = Pubkey::new_unique();
+        let (escrow, bump) = find_escrow(&maker, 42);
+
+        assert_eq!(derive_with_bump(&maker, 42, bump), Some(escrow));
+    }
+
+    #[test]
+    fn incorrect_bump_doesnt_derive_the_escrow() {
+        let maker = Pubkey::new_unique();
+        let (escrow, bump) = find_escrow(&maker, 42);
+
+        // Each other bump is either on the curve or a different address
+        for wrong in (0..=u8::MAX).filter(|candidate| *candidate != bump) {
+            assert_ne!(derive_with_bump(&maker, 42, wrong), Some(escrow));
+        }
+    }
+
+    /// `make` pays one derivation per bump the search tries, `make_with_bump` exactly one
+    #[test]
+    fn supplied_bump_saves_the_bump_search() {
+        let maker = Pubkey::new_unique();
+        let makes = 256u64;
+        let tried: u64 = (0..makes).map(|seed| (u8::MAX - find_escrow(&maker, seed).1) as u64 + 1).sum();
+
+        let make_cu = tried * PDA_DERIVATION_CU / makes;
+        let make_with_bump_cu = PDA_DERIVATION_CU;
+        println!("PDA derivation: make {make_cu} CU on average, make_with_bump {make_with_bump_cu} CU");
+        assert!(make_with_bump_cu <= make_cu);
+    }
+}
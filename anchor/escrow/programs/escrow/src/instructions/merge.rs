@@ -0,0 +1,195 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token::
+{
+    transfer_checked, close_account, 
+    CloseAccount, TransferChecked,
+};
+
+use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
+
+use crate::state::{Escrow, Stats};
+use crate::errors::EscrowError;
+use crate::EscrowMerged;
+
+/// Folds `source` into `escrow`, the source escrow and its vault are closed
+#[derive(Accounts)]
+pub struct Merge<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        has_one = mint_a @ EscrowError::InvalidMintA,
+        constraint = !escrow.locked @ EscrowError::EscrowLocked,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+    #[account(
+        mut,
+        close = maker,
+        seeds = [Escrow::SEED, maker.key().as_ref(), source.seed.to_le_bytes().as_ref()],
+        bump = source.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        constraint = !source.locked @ EscrowError::EscrowLocked,
+        constraint = source.key() != escrow.key() @ EscrowError::IncompatibleEscrows,
+    )]
+    pub source: Box<Account<'info, Escrow>>,
+ 
+    /// Token Accounts
+    pub mint_a: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = source,
+        associated_token::token_program = token_program
+    )]
+    pub source_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+ 
+    /// Programs
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> Merge<'info> {
+    fn move_and_close_source_vault(&mut self) -> Result<()> {
+        // Create the signer seeds for the source Vault
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            Escrow::SEED,
+            self.maker.to_account_info().key.as_ref(),
+            &self.source.seed.to_le_bytes()[..],
+            &[self.source.bump],
+        ]];
+ 
+        // Transfer Token A (source Vault -> Vault)
+        transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.source_vault.to_account_info(),
+                    to: self.vault.to_account_info(),
+                    mint: self.mint_a.to_account_info(),
+                    authority: self.source.to_account_info(),
+                },
+                &signer_seeds
+            ),
+            self.source_vault.amount,
+            self.mint_a.decimals
+        )?;
+ 
+        // Close the source Vault
+        close_account(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                CloseAccount {
+                    account: self.source_vault.to_account_info(),
+                    authority: self.source.to_account_info(),
+                    destination: self.maker.to_account_info(),
+                },
+                &signer_seeds
+            ),
+        )?;
+ 
+        Ok(())
+    }
+}
+ 
+pub fn handler(ctx: Context<Merge>) -> Result<()> {
+    // Only escrows a taker would treat the same way can be combined
+    require!(ctx.accounts.escrow.can_merge(&ctx.accounts.source), EscrowError::IncompatibleEscrows);
+
+    ctx.accounts.move_and_close_source_vault()?;
+
+    // Take on the source's terms, against what actually arrived in the vault
+    ctx.accounts.vault.reload()?;
+    let vault_amount = ctx.accounts.vault.amount;
+    ctx.accounts.escrow.absorb(&ctx.accounts.source, vault_amount)?;
+
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_merge();
+    }
+
+    emit!(EscrowMerged {
+        escrow: ctx.accounts.escrow.key(),
+        source: ctx.accounts.source.key(),
+        amount: ctx.accounts.escrow.amount,
+        receive: ctx.accounts.escrow.receive,
+    });
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    /// Second escrow of the market's maker, seed 2, with its own vault
+    fn add_source(market: &mut Market, amount: u64, receive: u64) -> (Pubkey, Pubkey) {
+        let (source, bump) = Pubkey::find_program_address(&[Escrow::SEED, market.maker.as_ref(), &2u64.to_le_bytes()], &crate::ID);
+        let escrow = Escrow { seed: 2, bump, amount, receive, ..market.escrow() };
+        market.svm.set_state(source, &escrow, Escrow::INIT_SPACE + Escrow::DISCRIMINATOR.len());
+
+        let source_vault = market.ata(&source, &market.mint_a);
+        market.svm.set_token_account(source_vault, market.mint_a, source, amount);
+        (source, source_vault)
+    }
+
+    #[test]
+    fn merged_escrow_holds_both_vaults() {
+        let mut market = Market::new(500, 1_000);
+        let (source, _) = add_source(&mut market, 300, 600);
+        let source: Escrow = market.svm.state(&source);
+
+        // The vault now holds both deposits, the terms add up
+        let mut escrow = market.escrow();
+        escrow.absorb(&source, 800).unwrap();
+        assert_eq!((escrow.amount, escrow.receive), (800, 1_600));
+
+        // A transfer fee took 10 on the way in
+        let mut escrow = market.escrow();
+        escrow.absorb(&source, 790).unwrap();
+        assert_eq!((escrow.amount, escrow.receive), (790, 1_600));
+    }
+
+    #[test]
+    fn only_matching_escrows_merge() {
+        let mut market = Market::new(500, 1_000);
+        let (source, source_vault) = add_source(&mut market, 300, 600);
+        let mut differs: Escrow = market.svm.state(&source);
+        differs.deadline = market.svm.now + 60;
+        market.svm.set_state(source, &differs, Escrow::INIT_SPACE + Escrow::DISCRIMINATOR.len());
+
+        let merge = ix(
+            crate::accounts::Merge {
+                maker: market.maker,
+                escrow: market.escrow,
+                source,
+                mint_a: market.mint_a,
+                vault: market.vault,
+                source_vault,
+                stats: Some(market.stats_key()),
+                token_program: anchor_spl::token::ID,
+            },
+            crate::instruction::Merge {},
+        );
+        assert_eq!(market.svm.process(&[merge]), Err(anchor_error(EscrowError::IncompatibleEscrows)));
+        assert_eq!(market.escrow().amount, 500);
+        assert_eq!(market.stats().merged, 0);
+    }
+}
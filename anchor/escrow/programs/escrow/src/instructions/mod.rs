@@ -3,4 +3,54 @@ pub use make::*;
 pub mod take;
 pub use take::*;
 pub mod refund;
-pub use refund::*;
\ No newline at end of file
+pub use refund::*;
+pub mod make_sol;
+pub use make_sol::*;
+pub mod take_sol;
+pub use take_sol::*;
+pub mod refund_sol;
+pub use refund_sol::*;
+pub mod make_with_bump;
+pub use make_with_bump::*;
+pub mod make_priced;
+pub use make_priced::*;
+pub mod take_priced;
+pub use take_priced::*;
+pub mod make_otc;
+pub use make_otc::*;
+pub mod fund_otc;
+pub use fund_otc::*;
+pub mod finalize_otc;
+pub use finalize_otc::*;
+pub mod abort_otc;
+pub use abort_otc::*;
+pub mod set_relist_policy;
+pub use set_relist_policy::*;
+pub mod relist_expired;
+pub use relist_expired::*;
+pub mod timeout_refund;
+pub use timeout_refund::*;
+pub mod crank_refund;
+pub use crank_refund::*;
+pub mod update;
+pub use update::*;
+pub mod set_condition;
+pub use set_condition::*;
+pub mod take_sol_for_token;
+pub use take_sol_for_token::*;
+pub mod finalize;
+pub use finalize::*;
+pub mod set_burn;
+pub use set_burn::*;
+pub mod initialize_config;
+pub use initialize_config::*;
+pub mod set_fee;
+pub use set_fee::*;
+pub mod twap;
+pub use twap::*;
+pub mod merge;
+pub use merge::*;
+pub mod initialize_stats;
+pub use initialize_stats::*;
+pub mod initialize_price_history;
+pub use initialize_price_history::*;
@@ -9,8 +9,9 @@ use anchor_spl::token::
 use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
 use anchor_spl::associated_token::AssociatedToken;
 
-use crate::state::Escrow;
+use crate::state::{Escrow, Stats};
 use crate::errors::EscrowError;
+use crate::EscrowRefunded;
 
 #[derive(Accounts)]
 pub struct Refund<'info> {
@@ -19,7 +20,7 @@ pub struct Refund<'info> {
     #[account(
         mut,
         close = maker,
-        seeds = ["escrow".as_bytes(), maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump,
         has_one = maker @ EscrowError::InvalidMaker,
         has_one = mint_a @ EscrowError::InvalidMintA,
@@ -43,6 +44,14 @@ pub struct Refund<'info> {
         associated_token::token_program = token_program
     )]
     pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
  
     /// Programs
     pub associated_token_program: Program<'info, AssociatedToken>,
@@ -50,52 +59,84 @@ pub struct Refund<'info> {
     pub system_program: Program<'info, System>,
 }
 
-impl<'info> Refund<'info> {
-    fn withdraw_and_close_vault(&mut self) -> Result<()> {
-        // Create the signer seeds for the Vault
-        let signer_seeds: [&[&[u8]]; 1] = [&[
-            b"escrow",
-            self.maker.to_account_info().key.as_ref(),
-            &self.escrow.seed.to_le_bytes()[..],
-            &[self.escrow.bump],
-        ]];
- 
-        // Transfer Token A (Vault -> Maker)
+/// Hands the Vault's Token A back to the maker and closes the Vault. Shared by
+/// every refund path, the escrow itself is closed by their `close = maker`.
+pub fn withdraw_and_close_vault<'info>(
+    escrow: &Account<'info, Escrow>,
+    maker: &AccountInfo<'info>,
+    mint_a: &InterfaceAccount<'info, Mint>,
+    vault: &InterfaceAccount<'info, TokenAccount>,
+    maker_ata_a: &InterfaceAccount<'info, TokenAccount>,
+    token_program: &Interface<'info, TokenInterface>,
+) -> Result<()> {
+    // Create the signer seeds for the Vault
+    let signer_seeds: [&[&[u8]]; 1] = [&[
+        Escrow::SEED,
+        maker.key.as_ref(),
+        &escrow.seed.to_le_bytes()[..],
+        &[escrow.bump],
+    ]];
+
+    // Transfer Token A (Vault -> Maker), unwrapped wSOL skips this and
+    // reaches the maker as lamports when the Vault is closed
+    if !escrow.unwrap_sol {
         transfer_checked(
             CpiContext::new_with_signer(
-                self.token_program.to_account_info(),
+                token_program.to_account_info(),
                 TransferChecked {
-                    from: self.vault.to_account_info(),
-                    to: self.maker_ata_a.to_account_info(),
-                    mint: self.mint_a.to_account_info(),
-                    authority: self.escrow.to_account_info(),
-                },
-                &signer_seeds
-            ),
-            self.vault.amount,
-            self.mint_a.decimals
-        )?;
- 
-        // Close the Vault
-        close_account(
-            CpiContext::new_with_signer(
-                self.token_program.to_account_info(),
-                CloseAccount {
-                    account: self.vault.to_account_info(),
-                    authority: self.escrow.to_account_info(),
-                    destination: self.maker.to_account_info(),
+                    from: vault.to_account_info(),
+                    to: maker_ata_a.to_account_info(),
+                    mint: mint_a.to_account_info(),
+                    authority: escrow.to_account_info(),
                 },
                 &signer_seeds
             ),
+            vault.amount,
+            mint_a.decimals
         )?;
- 
-        Ok(())
+    }
+
+    // Close the Vault
+    close_account(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            CloseAccount {
+                account: vault.to_account_info(),
+                authority: escrow.to_account_info(),
+                destination: maker.clone(),
+            },
+            &signer_seeds
+        ),
+    )?;
+
+    Ok(())
+}
+
+impl<'info> Refund<'info> {
+    fn withdraw_and_close_vault(&mut self) -> Result<()> {
+        withdraw_and_close_vault(
+            &self.escrow,
+            &self.maker.to_account_info(),
+            &self.mint_a,
+            &self.vault,
+            &self.maker_ata_a,
+            &self.token_program,
+        )
     }
 }
  
 pub fn handler(ctx: Context<Refund>) -> Result<()> {
     // Withdraw and close the Vault (Vault -> Maker)
     ctx.accounts.withdraw_and_close_vault()?;
+
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_refund();
+    }
+
+    emit!(EscrowRefunded {
+        escrow: ctx.accounts.escrow.key(),
+        maker: ctx.accounts.maker.key(),
+    });
  
     Ok(())
 }
\ No newline at end of file
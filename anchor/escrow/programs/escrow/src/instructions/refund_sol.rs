@@ -0,0 +1,90 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Escrow, Stats};
+use crate::errors::EscrowError;
+
+#[derive(Accounts)]
+pub struct RefundSol<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        close = maker,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        constraint = escrow.holds_sol() @ EscrowError::NotNativeEscrow,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+ 
+    /// Programs
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<RefundSol>) -> Result<()> {
+    // Closing the escrow returns both the deposit and the rent to the maker
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_refund();
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn sol_market() -> Market {
+        let mut market = Market::new(500, 1_000);
+        market.edit_escrow(|escrow| {
+            escrow.mint_a = system_program::ID;
+            escrow.mint_b = system_program::ID;
+        });
+        market
+    }
+
+    fn refund_ix(market: &Market, stats: Option<Pubkey>) -> anchor_lang::solana_program::instruction::Instruction {
+        ix(
+            crate::accounts::RefundSol { maker: market.maker, escrow: market.escrow, stats, system_program: system_program::ID },
+            crate::instruction::RefundSol {},
+        )
+    }
+
+    #[test]
+    fn refund_counts_when_stats_are_passed() {
+        let mut market = sol_market();
+        market.svm.process(&[refund_ix(&market, Some(market.stats_key()))]).unwrap();
+
+        assert_eq!(market.stats().refunded, 1);
+        assert_eq!(market.svm.lamports(&market.escrow), 0);
+    }
+
+    #[test]
+    fn stats_are_optional() {
+        let mut market = sol_market();
+        market.svm.process(&[refund_ix(&market, None)]).unwrap();
+
+        assert_eq!(market.stats().refunded, 0);
+        assert_eq!(market.svm.lamports(&market.escrow), 0);
+    }
+
+    #[test]
+    fn stats_have_to_be_the_stats_pda() {
+        let mut market = sol_market();
+        let elsewhere = market.price_history_key();
+
+        assert_eq!(
+            market.svm.process(&[refund_ix(&market, Some(elsewhere))]),
+            Err(anchor_error(ErrorCode::AccountDiscriminatorMismatch))
+        );
+    }
+}
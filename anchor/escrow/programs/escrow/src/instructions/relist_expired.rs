@@ -0,0 +1,114 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Escrow;
+use crate::errors::EscrowError;
+
+/// Keeper-callable, the maker opted in through their relist policy
+#[derive(Accounts)]
+pub struct RelistExpired<'info> {
+    pub keeper: Signer<'info>,
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        constraint = !escrow.locked @ EscrowError::EscrowLocked,
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+ 
+pub fn handler(ctx: Context<RelistExpired>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let escrow = &mut ctx.accounts.escrow;
+
+    let policy = escrow.relist.ok_or(EscrowError::NoRelistPolicy)?;
+    require!(escrow.is_expired(now), EscrowError::NotExpired);
+
+    // Cut the price by the configured share, rounding in the taker's favour
+    let receive = (escrow.receive as u128)
+        .checked_mul(10_000 - policy.adjust_bps as u128)
+        .ok_or(EscrowError::Overflow)?
+        / 10_000;
+    require!(receive > 0, EscrowError::InvalidAmount);
+
+    escrow.receive = receive as u64;
+    escrow.deadline = now.checked_add(policy.period).ok_or(EscrowError::Overflow)?;
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::RelistPolicy;
+    use crate::test_utils::*;
+
+    fn relist_ix(market: &Market, keeper: Pubkey) -> anchor_lang::solana_program::instruction::Instruction {
+        ix(
+            crate::accounts::RelistExpired { keeper, maker: market.maker, escrow: market.escrow },
+            crate::instruction::RelistExpired {},
+        )
+    }
+
+    /// Escrow expiring in 60s that relists 10% cheaper for another hour
+    fn relisting_market() -> Market {
+        let mut market = Market::new(500, 1_000);
+        let deadline = market.svm.now + 60;
+        market.edit_escrow(|escrow| {
+            escrow.deadline = deadline;
+            escrow.relist = Some(RelistPolicy { adjust_bps: 1_000, period: 3_600 });
+        });
+        market
+    }
+
+    #[test]
+    fn keeper_relists_expired_escrows_cheaper() {
+        let mut market = relisting_market();
+        let keeper = market.svm.wallet();
+        market.svm.now += 61;
+
+        market.svm.process(&[relist_ix(&market, keeper)]).unwrap();
+        let escrow = market.escrow();
+        assert_eq!(escrow.receive, 900);
+        assert_eq!(escrow.deadline, market.svm.now + 3_600);
+        assert!(!escrow.is_expired(market.svm.now));
+
+        // Open again, so it can't be relisted until the new deadline passes
+        assert_eq!(market.svm.process(&[relist_ix(&market, keeper)]), Err(anchor_error(EscrowError::NotExpired)));
+    }
+
+    #[test]
+    fn only_expired_escrows_with_a_policy_relist() {
+        let mut market = relisting_market();
+        let keeper = market.svm.wallet();
+        assert_eq!(market.svm.process(&[relist_ix(&market, keeper)]), Err(anchor_error(EscrowError::NotExpired)));
+
+        market.svm.now += 61;
+        market.edit_escrow(|escrow| escrow.relist = None);
+        assert_eq!(market.svm.process(&[relist_ix(&market, keeper)]), Err(anchor_error(EscrowError::NoRelistPolicy)));
+        assert_eq!(market.escrow().receive, 1_000);
+    }
+
+    #[test]
+    fn relist_policy_needs_a_deadline() {
+        let mut market = Market::new(500, 1_000);
+        let set = |market: &Market, relist| ix(
+            crate::accounts::SetRelistPolicy { maker: market.maker, escrow: market.escrow },
+            crate::instruction::SetRelistPolicy { relist },
+        );
+        let policy = RelistPolicy { adjust_bps: 1_000, period: 3_600 };
+
+        let no_deadline = set(&market, Some(policy));
+        assert_eq!(market.svm.process(&[no_deadline]), Err(anchor_error(EscrowError::InvalidRelistPolicy)));
+
+        let deadline = market.svm.now + 60;
+        market.edit_escrow(|escrow| escrow.deadline = deadline);
+        let free = set(&market, Some(RelistPolicy { adjust_bps: 10_000, ..policy }));
+        assert_eq!(market.svm.process(&[free]), Err(anchor_error(EscrowError::InvalidRelistPolicy)));
+
+        let valid = set(&market, Some(policy));
+        market.svm.process(&[valid]).unwrap();
+        assert!(market.escrow().relist == Some(policy));
+    }
+}
@@ -0,0 +1,57 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Escrow;
+use crate::errors::EscrowError;
+
+#[derive(Accounts)]
+pub struct SetBurn<'info> {
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        constraint = !escrow.locked @ EscrowError::EscrowLocked,
+        constraint = escrow.can_burn() @ EscrowError::CantBurn,
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+ 
+pub fn handler(ctx: Context<SetBurn>, burn_bps: u16) -> Result<()> {
+    // 0 turns burning off, burning everything would leave the taker with nothing
+    require!(burn_bps < 10_000, EscrowError::InvalidBurnBps);
+    ctx.accounts.escrow.burn_bps = burn_bps;
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use anchor_spl::token::spl_token;
+
+    fn set_burn_ix(market: &Market, burn_bps: u16) -> anchor_lang::solana_program::instruction::Instruction {
+        ix(crate::accounts::SetBurn { maker: market.maker, escrow: market.escrow }, crate::instruction::SetBurn { burn_bps })
+    }
+
+    #[test]
+    fn maker_sets_a_burn_below_100_percent() {
+        let mut market = Market::new(500, 1_000);
+
+        market.svm.process(&[set_burn_ix(&market, 250)]).unwrap();
+        assert_eq!(market.escrow().burn_bps, 250);
+        assert_eq!(market.svm.process(&[set_burn_ix(&market, 10_000)]), Err(anchor_error(EscrowError::InvalidBurnBps)));
+    }
+
+    #[test]
+    fn wsol_escrows_cant_burn() {
+        let mut market = Market::new(500, 1_000);
+        market.edit_escrow(|escrow| escrow.mint_a = spl_token::native_mint::ID);
+        assert_eq!(market.svm.process(&[set_burn_ix(&market, 250)]), Err(anchor_error(EscrowError::CantBurn)));
+
+        market.edit_escrow(|escrow| escrow.mint_a = system_program::ID);
+        assert_eq!(market.svm.process(&[set_burn_ix(&market, 250)]), Err(anchor_error(EscrowError::CantBurn)));
+        assert_eq!(market.escrow().burn_bps, 0);
+    }
+}
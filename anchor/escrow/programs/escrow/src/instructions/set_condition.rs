@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Escrow;
+use crate::errors::EscrowError;
+
+#[derive(Accounts)]
+pub struct SetCondition<'info> {
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        constraint = !escrow.locked @ EscrowError::EscrowLocked,
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+ 
+pub fn handler(ctx: Context<SetCondition>, condition_program: Option<Pubkey>) -> Result<()> {
+    // None makes takes unconditional again
+    ctx.accounts.escrow.condition_program = condition_program;
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn set_condition_ix(market: &Market, condition_program: Option<Pubkey>) -> anchor_lang::solana_program::instruction::Instruction {
+        let update = market.update_accounts();
+        ix(
+            crate::accounts::SetCondition { maker: update.maker, escrow: update.escrow },
+            crate::instruction::SetCondition { condition_program },
+        )
+    }
+
+    #[test]
+    fn makers_set_and_clear_the_condition() {
+        let mut market = Market::new(500, 1_000);
+        let checker = Pubkey::new_unique();
+
+        market.svm.process(&[set_condition_ix(&market, Some(checker))]).unwrap();
+        assert_eq!(market.escrow().condition_program, Some(checker));
+
+        market.svm.process(&[set_condition_ix(&market, None)]).unwrap();
+        assert_eq!(market.escrow().condition_program, None);
+    }
+
+    #[test]
+    fn locked_escrows_keep_their_condition() {
+        let mut market = Market::new(500, 1_000);
+        market.edit_escrow(|escrow| escrow.locked = true);
+
+        assert_eq!(market.svm.process(&[set_condition_ix(&market, Some(Pubkey::new_unique()))]), Err(anchor_error(EscrowError::EscrowLocked)));
+        assert_eq!(market.escrow().condition_program, None);
+    }
+}
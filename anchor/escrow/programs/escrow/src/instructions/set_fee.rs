@@ -0,0 +1,78 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{EscrowConfig, MAX_FEE_BPS};
+use crate::errors::EscrowError;
+
+#[derive(Accounts)]
+pub struct SetFee<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [EscrowConfig::SEED],
+        bump = escrow_config.bump,
+        has_one = authority @ EscrowError::Unauthorized,
+    )]
+    pub escrow_config: Account<'info, EscrowConfig>,
+}
+ 
+pub fn handler(ctx: Context<SetFee>, fee_bps: u16, treasury: Pubkey) -> Result<()> {
+    // 0 turns the fee off
+    require!(fee_bps <= MAX_FEE_BPS, EscrowError::InvalidFee);
+ 
+    let escrow_config = &mut ctx.accounts.escrow_config;
+    escrow_config.fee_bps = fee_bps;
+    escrow_config.treasury = treasury;
+ 
+    Ok(())
+}
+ 
+pub fn handler_crank_bounty(ctx: Context<SetFee>, crank_bounty: u64) -> Result<()> {
+    // 0 turns the bounty off, it never takes more than the escrow's rent
+    ctx.accounts.escrow_config.crank_bounty = crank_bounty;
+ 
+    Ok(())
+}
+ 
+pub fn handler_insurance_premium(ctx: Context<SetFee>, insurance_premium: u64) -> Result<()> {
+    ctx.accounts.escrow_config.insurance_premium = insurance_premium;
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn authority_sets_the_crank_bounty() {
+        let mut market = Market::new(500, 1_000);
+        let authority = market.config().authority;
+
+        market.admin_as(authority, crate::instruction::SetCrankBounty { crank_bounty: 5_000 }).unwrap();
+        assert_eq!(market.config().crank_bounty, 5_000);
+
+        let stranger = market.svm.wallet();
+        assert_eq!(
+            market.admin_as(stranger, crate::instruction::SetCrankBounty { crank_bounty: 0 }),
+            Err(anchor_error(EscrowError::Unauthorized))
+        );
+        assert_eq!(market.config().crank_bounty, 5_000);
+    }
+
+    #[test]
+    fn authority_sets_the_insurance_premium() {
+        let mut market = Market::new(500, 1_000);
+        let authority = market.config().authority;
+
+        market.admin_as(authority, crate::instruction::SetInsurancePremium { insurance_premium: 25_000_000 }).unwrap();
+        assert_eq!(market.config().insurance_premium, 25_000_000);
+
+        let stranger = market.svm.wallet();
+        assert_eq!(
+            market.admin_as(stranger, crate::instruction::SetInsurancePremium { insurance_premium: 0 }),
+            Err(anchor_error(EscrowError::Unauthorized))
+        );
+        assert_eq!(market.config().insurance_premium, 25_000_000);
+    }
+}
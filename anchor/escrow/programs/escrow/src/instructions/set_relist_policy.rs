@@ -0,0 +1,30 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Escrow, RelistPolicy};
+use crate::errors::EscrowError;
+
+#[derive(Accounts)]
+pub struct SetRelistPolicy<'info> {
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        constraint = !escrow.locked @ EscrowError::EscrowLocked,
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+ 
+pub fn handler(ctx: Context<SetRelistPolicy>, relist: Option<RelistPolicy>) -> Result<()> {
+    if let Some(policy) = relist {
+        // Relisting is only meaningful for escrows that expire
+        require!(ctx.accounts.escrow.deadline != 0, EscrowError::InvalidRelistPolicy);
+        require!(policy.adjust_bps < 10_000 && policy.period > 0, EscrowError::InvalidRelistPolicy);
+    }
+
+    // None turns relisting off again
+    ctx.accounts.escrow.relist = relist;
+ 
+    Ok(())
+}
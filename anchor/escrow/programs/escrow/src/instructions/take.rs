@@ -1,16 +1,20 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
 
 use anchor_spl::token::
 {
-    transfer_checked, close_account, 
-    CloseAccount, TransferChecked
+    transfer_checked, close_account, burn,
+    CloseAccount, TransferChecked, Burn
 };
 
 use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
 use anchor_spl::associated_token::AssociatedToken;
 
-use crate::state::Escrow;
+use crate::state::{Escrow, EscrowConfig, PriceHistory, Stats};
 use crate::errors::EscrowError;
+use crate::{EscrowRefunded, EscrowTaken};
+use crate::condition;
+use crate::oracle::OraclePrice;
 
 
 #[derive(Accounts)]
@@ -21,12 +25,12 @@ pub struct Take<'info> {
     pub maker: SystemAccount<'info>,
     #[account(
         mut,
-        close = maker,
-        seeds = ["escrow".as_bytes(), maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump,
         has_one = maker @ EscrowError::InvalidMaker,
         has_one = mint_a @ EscrowError::InvalidMintA,
         has_one = mint_b @ EscrowError::InvalidMintB,
+        constraint = !escrow.is_priced() @ EscrowError::PricedEscrow,
     )]
     pub escrow: Box<Account<'info, Escrow>>,
  
@@ -63,15 +67,91 @@ pub struct Take<'info> {
         associated_token::token_program = token_program
     )]
     pub maker_ata_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: taker-chosen reference price, parsed by `OraclePrice::read`
+    pub oracle: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: must match `escrow.condition_program`, checked in `check_condition`
+    pub condition_program: Option<UncheckedAccount<'info>>,
+
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+
+    /// Leave out to skip recording the settlement price for the pair's TWAP
+    #[account(
+        mut,
+        seeds = [PriceHistory::SEED, mint_a.key().as_ref(), mint_b.key().as_ref()],
+        bump = price_history.bump,
+    )]
+    pub price_history: Option<Box<Account<'info, PriceHistory>>>,
+
+    /// Leave out to take without a treasury fee
+    #[account(
+        seeds = [EscrowConfig::SEED],
+        bump = escrow_config.bump,
+    )]
+    pub escrow_config: Option<Box<Account<'info, EscrowConfig>>>,
+    /// Only needed when the config charges a fee, owned by `escrow_config.treasury`
+    #[account(
+        mut,
+        token::mint = mint_b,
+        token::token_program = token_program
+    )]
+    pub treasury_ata_b: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
  
     /// Programs
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
+
+    /// Only needed by `take_and_close` to refund the unfilled Token A
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// Only needed when the escrow burns, `mint_a` again but writable. Takes
+    /// that don't burn leave it out and don't write-lock the mint.
+    #[account(
+        mut,
+        address = escrow.mint_a @ EscrowError::InvalidMintA,
+    )]
+    pub burn_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
 }
 
 impl<'info> Take<'info> {
-    fn transfer_to_maker(&mut self) -> Result<()> {
+    fn check_price_impact(&self, max_deviation_bps: u16) -> Result<()> {
+        let oracle = self.oracle.as_ref().ok_or(EscrowError::MissingOracle)?;
+        let price = OraclePrice::read(&oracle.to_account_info(), &self.escrow.mint_a, &self.escrow.mint_b)?;
+
+        let deviation = price.deviation_bps(self.escrow.receive, self.escrow.amount)?;
+        require!(deviation <= max_deviation_bps as u128, EscrowError::PriceDeviationTooHigh);
+
+        Ok(())
+    }
+
+    fn check_condition(&self, fill_amount: u64, extra_accounts: &[AccountInfo<'info>]) -> Result<()> {
+        condition::check_escrow(&self.escrow, self.condition_program.as_ref(), &self.taker, extra_accounts, fill_amount)
+    }
+ 
+    fn check_treasury(&self) -> Result<()> {
+        let (Some(escrow_config), Some(treasury_ata_b)) = (&self.escrow_config, &self.treasury_ata_b) else {
+            return err!(EscrowError::InvalidTreasury);
+        };
+        require_keys_eq!(treasury_ata_b.owner, escrow_config.treasury, EscrowError::InvalidTreasury);
+
+        Ok(())
+    }
+
+    fn transfer_to_maker(&mut self, fill_amount: u64) -> Result<()> {
         transfer_checked(
             CpiContext::new(
                 self.token_program.to_account_info(),
@@ -82,17 +162,38 @@ impl<'info> Take<'info> {
                     authority: self.taker.to_account_info(),
                 },
             ),
-            self.escrow.receive,
+            fill_amount,
+            self.mint_b.decimals
+        )?;
+ 
+        Ok(())
+    }
+ 
+    fn pay_fee(&mut self, fee: u64) -> Result<()> {
+        let treasury_ata_b = self.treasury_ata_b.as_ref().ok_or(EscrowError::InvalidTreasury)?;
+
+        // Taker-funded, on top of what the maker receives
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.taker_ata_b.to_account_info(),
+                    to: treasury_ata_b.to_account_info(),
+                    mint: self.mint_b.to_account_info(),
+                    authority: self.taker.to_account_info(),
+                },
+            ),
+            fee,
             self.mint_b.decimals
         )?;
  
         Ok(())
     }
  
-    fn withdraw_and_close_vault(&mut self) -> Result<()> {
+    fn withdraw(&mut self, amount: u64) -> Result<()> {
         // Create the signer seeds for the Vault
         let signer_seeds: [&[&[u8]]; 1] = [&[
-            b"escrow",
+            Escrow::SEED,
             self.maker.to_account_info().key.as_ref(),
             &self.escrow.seed.to_le_bytes()[..],
             &[self.escrow.bump],
@@ -110,10 +211,103 @@ impl<'info> Take<'info> {
                 },
                 &signer_seeds
             ),
+            amount,
+            self.mint_a.decimals
+        )?;
+ 
+        Ok(())
+    }
+ 
+    fn unwrap_sol(&mut self) -> Result<()> {
+        // Closing the taker's wSOL account hands its lamports back as native SOL,
+        // `should_unwrap` made sure it only holds what this take delivered
+        close_account(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                CloseAccount {
+                    account: self.taker_ata_a.to_account_info(),
+                    authority: self.taker.to_account_info(),
+                    destination: self.taker.to_account_info(),
+                },
+            ),
+        )?;
+ 
+        Ok(())
+    }
+ 
+    fn burn(&mut self, amount: u64) -> Result<()> {
+        // Create the signer seeds for the Vault
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            Escrow::SEED,
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+ 
+        let burn_mint = self.burn_mint.as_ref().ok_or(EscrowError::MissingBurnMint)?;
+
+        // Burn Token A straight out of the Vault
+        burn(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                Burn {
+                    mint: burn_mint.to_account_info(),
+                    from: self.vault.to_account_info(),
+                    authority: self.escrow.to_account_info(),
+                },
+                &signer_seeds
+            ),
+            amount,
+        )?;
+ 
+        Ok(())
+    }
+ 
+    fn refund_remainder(&mut self) -> Result<()> {
+        // Create the signer seeds for the Vault
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            Escrow::SEED,
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        // Unwrapped wSOL reaches the maker as lamports when the Vault is closed
+        self.vault.reload()?;
+        if self.escrow.unwrap_sol || self.vault.amount == 0 {
+            return Ok(());
+        }
+
+        let maker_ata_a = self.maker_ata_a.as_ref().ok_or(EscrowError::MissingMakerAta)?;
+
+        // Transfer the unfilled Token A (Vault -> Maker)
+        transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.vault.to_account_info(),
+                    to: maker_ata_a.to_account_info(),
+                    mint: self.mint_a.to_account_info(),
+                    authority: self.escrow.to_account_info(),
+                },
+                &signer_seeds
+            ),
             self.vault.amount,
             self.mint_a.decimals
         )?;
  
+        Ok(())
+    }
+ 
+    fn close_vault_and_escrow(&mut self) -> Result<()> {
+        // Create the signer seeds for the Vault
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            Escrow::SEED,
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+ 
         // Close the Vault
         close_account(
             CpiContext::new_with_signer(
@@ -126,17 +320,472 @@ impl<'info> Take<'info> {
                 &signer_seeds
             ),
         )?;
+
+        // Close the Escrow
+        self.escrow.close(self.maker.to_account_info())?;
  
         Ok(())
     }
 }
  
-pub fn handler(ctx: Context<Take>) -> Result<()> {
-    // Transfer Token B to Maker
-    ctx.accounts.transfer_to_maker()?;
+/// Partial fill without an allowlist proof or oracle check. `min_amount_a_out`
+/// bounds the Token A delivered after any burn, `max_amount_b_in` the fill
+/// plus the treasury fee paid on top.
+pub fn handler_partial<'info>(ctx: Context<'_, '_, 'info, 'info, Take<'info>>, fill_amount_b: u64, min_amount_a_out: u64, max_amount_b_in: u64) -> Result<()> {
+    handler(ctx, Vec::new(), fill_amount_b, min_amount_a_out, max_amount_b_in, None)
+}
+ 
+/// Fill part of the escrow, then refund the unfilled Token A to the maker
+/// and close the escrow in the same instruction. Cancels the maker's order,
+/// so the maker has to sign it too.
+pub fn handler_and_close<'info>(ctx: Context<'_, '_, 'info, 'info, Take<'info>>, fill_amount: u64, min_amount_a_out: u64, max_amount_b_in: u64) -> Result<()> {
+    settle(ctx, Vec::new(), fill_amount, min_amount_a_out, max_amount_b_in, None, true)
+}
+ 
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, Take<'info>>, proof: Vec<[u8; 32]>, fill_amount: u64, min_amount_a_out: u64, max_amount_b_in: u64, max_deviation_bps: Option<u16>) -> Result<()> {
+    settle(ctx, proof, fill_amount, min_amount_a_out, max_amount_b_in, max_deviation_bps, false)
+}
+ 
+/// What a take moves, worked out before any transfer
+#[derive(Debug, PartialEq, Eq)]
+pub struct Fill {
+    /// Share of `escrow.amount` the fill is worth
+    pub amount_out: u64,
+    /// Token A leaving the Vault, a full fill drains it
+    pub amount_a_out: u64,
+    /// Part of `amount_a_out` burned instead of delivered
+    pub burned: u64,
+    /// Token A the taker receives
+    pub delivered: u64,
+    /// Treasury fee the taker pays on top of the fill
+    pub fee: u64,
+    /// Token B the taker pays, the fill plus the fee
+    pub amount_b_in: u64,
+}
+
+impl Fill {
+    pub fn new(escrow: &Escrow, vault_amount: u64, fill_amount: u64, escrow_config: Option<&EscrowConfig>) -> Result<Self> {
+        require!(fill_amount > 0 && fill_amount <= escrow.receive, EscrowError::InvalidAmount);
+
+        // Proportional share of Token A, rounded down in the maker's favour.
+        // A full fill drains whatever is left.
+        let full_fill = fill_amount == escrow.receive;
+        let amount_out = if full_fill {
+            escrow.amount
+        } else {
+            ((escrow.amount as u128)
+                .checked_mul(fill_amount as u128)
+                .ok_or(EscrowError::Overflow)?
+                / escrow.receive as u128) as u64
+        };
+        require!(amount_out > 0, EscrowError::FillTooSmall);
+
+        // Deflationary escrows burn part of what leaves the vault, the taker
+        // gets the rest
+        let amount_a_out = if full_fill { vault_amount } else { amount_out };
+        let burned = escrow.burn_amount(amount_a_out);
+
+        // The maker gets the full fill, the treasury fee comes on top. Without
+        // a config there is no fee.
+        let fee = escrow_config.map_or(0, |config| config.take_fee(fill_amount));
+        let amount_b_in = fill_amount.checked_add(fee).ok_or(EscrowError::Overflow)?;
+
+        Ok(Self { amount_out, amount_a_out, burned, delivered: amount_a_out - burned, fee, amount_b_in })
+    }
+}
  
-    // Withdraw and close the Vault
-    ctx.accounts.withdraw_and_close_vault()?;
+/// wSOL escrows hand the taker native SOL by closing their wSOL account. A
+/// taker who already held wSOL there keeps the account and gets wSOL instead,
+/// so the take never unwraps more than it delivered.
+pub fn should_unwrap(escrow: &Escrow, taker_held_a: u64) -> bool {
+    escrow.unwrap_sol && taker_held_a == 0
+}
+ 
+fn settle<'info>(ctx: Context<'_, '_, 'info, 'info, Take<'info>>, proof: Vec<[u8; 32]>, fill_amount: u64, min_amount_a_out: u64, max_amount_b_in: u64, max_deviation_bps: Option<u16>, close_remainder: bool) -> Result<()> {
+    // Only the maker can cancel what's left of their order
+    require!(!close_remainder || ctx.accounts.maker.is_signer, EscrowError::MakerMustSign);
+
+    // Don't let the taker pay for an empty escrow
+    require!(ctx.accounts.vault.amount > 0, EscrowError::EmptyVault);
+
+    // Deadline, private counterparty and allowlist
+    ctx.accounts.escrow.check_taker(&ctx.accounts.taker.key(), &proof, Clock::get()?.unix_timestamp)?;
+
+    // Opt-in check of the escrow price against a reference oracle
+    if let Some(max_deviation_bps) = max_deviation_bps {
+        ctx.accounts.check_price_impact(max_deviation_bps)?;
+    }
+
+    // Validate the fill and work out what it moves
+    let Fill { amount_out, amount_a_out, burned, delivered, fee, amount_b_in } =
+        Fill::new(&ctx.accounts.escrow, ctx.accounts.vault.amount, fill_amount, ctx.accounts.escrow_config.as_deref().map(|config| &**config))?;
+    let full_fill = fill_amount == ctx.accounts.escrow.receive;
+
+    // Conditional escrows settle only if their checker agrees, it gets the
+    // remaining accounts for whatever state it needs to look at
+    ctx.accounts.check_condition(fill_amount, ctx.remaining_accounts)?;
+
+    // Protect the taker against terms changing under them
+    require!(delivered >= min_amount_a_out, EscrowError::SlippageExceeded);
+    require!(amount_b_in <= max_amount_b_in, EscrowError::SlippageExceeded);
+
+    // A fee needs somewhere to go
+    if fee > 0 {
+        ctx.accounts.check_treasury()?;
+    }
+
+    // Burning changes the supply, so only then does the mint come writable
+    if burned > 0 {
+        require!(ctx.accounts.burn_mint.is_some(), EscrowError::MissingBurnMint);
+    }
+ 
+    // Transfer Token B to Maker, and the fee to the treasury
+    ctx.accounts.transfer_to_maker(fill_amount)?;
+    if fee > 0 {
+        ctx.accounts.pay_fee(fee)?;
+    }
+
+    if burned > 0 {
+        ctx.accounts.burn(burned)?;
+    }
+
+    // Withdraw what's left of the share for the taker
+    let taker_held_a = ctx.accounts.taker_ata_a.amount;
+    ctx.accounts.withdraw(delivered)?;
+    if should_unwrap(&ctx.accounts.escrow, taker_held_a) {
+        ctx.accounts.unwrap_sol()?;
+    }
+
+    if full_fill {
+        // The Vault is empty now, close it and the Escrow
+        ctx.accounts.close_vault_and_escrow()?;
+    } else if close_remainder {
+        // The maker takes the rest back right away
+        ctx.accounts.refund_remainder()?;
+        ctx.accounts.close_vault_and_escrow()?;
+    } else {
+        // The escrow stays open for the rest
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.amount -= amount_out;
+        escrow.receive -= fill_amount;
+    }
+
+    let (mint_a, mint_b) = (ctx.accounts.mint_a.key(), ctx.accounts.mint_b.key());
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_take(mint_a, amount_a_out, mint_b, fill_amount, full_fill);
+        if close_remainder && !full_fill {
+            // Not filled to completion, the unfilled part went back to the maker
+            stats.record_refund();
+        }
+    }
+
+    // Settlement price of Token A in Token B, for the pair's TWAP
+    if let Some(price_history) = ctx.accounts.price_history.as_mut() {
+        price_history.record(PriceHistory::price(fill_amount, amount_a_out), Clock::get()?.unix_timestamp);
+    }
+
+    emit!(EscrowTaken {
+        escrow: ctx.accounts.escrow.key(),
+        taker: ctx.accounts.taker.key(),
+    });
+    if close_remainder && !full_fill {
+        emit!(EscrowRefunded {
+            escrow: ctx.accounts.escrow.key(),
+            maker: ctx.accounts.maker.key(),
+        });
+    }
+
+    // Tell the taker how much Token A actually arrived after the burn
+    set_return_data(&delivered.to_le_bytes());
  
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn config(fee_bps: u16) -> EscrowConfig {
+        EscrowConfig { authority: Pubkey::new_unique(), treasury: Pubkey::new_unique(), fee_bps, bump: 255, crank_bounty: 0, insurance_premium: 0 }
+    }
+
+    #[test]
+    fn fee_comes_on_top_of_the_fill() {
+        let escrow = Market::new(500, 1_000).escrow();
+
+        // 1% of the Token B filled, the maker still gets the whole fill
+        let fill = Fill::new(&escrow, 500, 400, Some(&config(100))).unwrap();
+        assert_eq!(fill, Fill { amount_out: 200, amount_a_out: 200, burned: 0, delivered: 200, fee: 4, amount_b_in: 404 });
+
+        // Rounded down in the taker's favour
+        assert_eq!(Fill::new(&escrow, 500, 150, Some(&config(100))).unwrap().fee, 1);
+        assert_eq!(Fill::new(&escrow, 500, 99, Some(&config(100))).unwrap().fee, 0);
+    }
+
+    #[test]
+    fn no_config_means_no_fee() {
+        let escrow = Market::new(500, 1_000).escrow();
+
+        let fill = Fill::new(&escrow, 500, 1_000, None).unwrap();
+        assert_eq!(fill, Fill { amount_out: 500, amount_a_out: 500, burned: 0, delivered: 500, fee: 0, amount_b_in: 1_000 });
+        assert_eq!(Fill::new(&escrow, 500, 1_000, Some(&config(0))).unwrap().fee, 0);
+    }
+
+    #[test]
+    fn fills_are_validated() {
+        let escrow = Market::new(500, 1_000).escrow();
+
+        assert_eq!(Fill::new(&escrow, 500, 0, None), Err(EscrowError::InvalidAmount.into()));
+        assert_eq!(Fill::new(&escrow, 500, 1_001, None), Err(EscrowError::InvalidAmount.into()));
+        // 1 Token B is worth half a base unit of Token A
+        assert_eq!(Fill::new(&escrow, 500, 1, None), Err(EscrowError::FillTooSmall.into()));
+    }
+
+    #[test]
+    fn transfer_fees_only_promise_what_reached_the_vault() {
+        // 500 sent at a 2% transfer fee, make records the 490 that arrived
+        let mut escrow = Market::new(500, 1_000).escrow();
+        escrow.amount = 490;
+
+        assert_eq!(Fill::new(&escrow, 490, 500, None).unwrap().amount_a_out, 245);
+        assert_eq!(Fill::new(&escrow, 490, 1_000, None).unwrap().amount_a_out, 490);
+
+        // A merge into it can't outrun the vault either
+        let source = Escrow { amount: 500, ..escrow.clone() };
+        escrow.absorb(&source, 980).unwrap();
+        assert_eq!(escrow.amount, 980);
+    }
+
+    #[test]
+    fn take_and_close_splits_the_fill_from_the_refund() {
+        let escrow = Market::new(100, 1_000).escrow();
+
+        // 60% filled goes to the taker, the other 40% back to the maker
+        let fill = Fill::new(&escrow, 100, 600, None).unwrap();
+        assert_eq!(fill.delivered, 60);
+        assert_eq!(100 - fill.amount_a_out, 40);
+    }
+
+    #[test]
+    fn take_and_close_needs_the_maker() {
+        let mut market = Market::new(100, 1_000);
+        let close = ix(
+            crate::accounts::Take { maker_ata_a: Some(market.ata(&market.maker, &market.mint_a)), ..market.take_accounts() },
+            crate::instruction::TakeAndClose { fill_amount: 600, min_amount_a_out: 0, max_amount_b_in: u64::MAX },
+        );
+
+        assert_eq!(market.svm.process(&[close]), Err(anchor_error(EscrowError::MakerMustSign)));
+        assert_eq!(market.escrow().receive, 1_000);
+    }
+
+    #[test]
+    fn partial_takes_bound_what_arrives_after_the_burn() {
+        let mut market = Market::new(500, 1_000);
+        market.edit_escrow(|escrow| escrow.burn_bps = 1_000);
+        let take = |min_amount_a_out| ix(
+            market.take_accounts(),
+            crate::instruction::TakePartial { fill_amount_b: 400, min_amount_a_out, max_amount_b_in: u64::MAX },
+        );
+        // 200 Token A leave the vault, 20 of them burned
+        let too_much = take(181);
+
+        assert_eq!(market.svm.process(&[too_much]), Err(anchor_error(EscrowError::SlippageExceeded)));
+        assert_eq!(Fill::new(&market.escrow(), 500, 400, None).unwrap().delivered, 180);
+    }
+
+    #[test]
+    fn burns_take_their_share_off_what_the_taker_gets() {
+        let mut escrow = Market::new(500, 1_000).escrow();
+        escrow.burn_bps = 1_000;
+
+        // 10% of what leaves the vault comes off the supply, the taker gets the rest
+        let fill = Fill::new(&escrow, 500, 1_000, None).unwrap();
+        assert_eq!(fill, Fill { amount_out: 500, amount_a_out: 500, burned: 50, delivered: 450, fee: 0, amount_b_in: 1_000 });
+        // Rounded down in the taker's favour
+        let fill = Fill::new(&escrow, 500, 18, None).unwrap();
+        assert_eq!((fill.amount_a_out, fill.burned, fill.delivered), (9, 0, 9));
+    }
+
+    #[test]
+    fn burning_escrows_need_the_writable_mint() {
+        let mut market = Market::new(500, 1_000);
+        market.edit_escrow(|escrow| escrow.burn_bps = 1_000);
+        let take = |burn_mint| ix(
+            crate::accounts::Take { burn_mint, ..market.take_accounts() },
+            crate::instruction::TakePartial { fill_amount_b: 400, min_amount_a_out: 0, max_amount_b_in: u64::MAX },
+        );
+        let without = take(None);
+        let other_mint = take(Some(market.mint_b));
+
+        assert_eq!(market.svm.process(&[without]), Err(anchor_error(EscrowError::MissingBurnMint)));
+        assert_eq!(market.svm.process(&[other_mint]), Err(anchor_error(EscrowError::InvalidMintA)));
+        assert_eq!(market.escrow().receive, 1_000);
+    }
+
+    #[test]
+    fn only_the_delivered_wsol_is_unwrapped() {
+        let mut escrow = Market::new(500, 1_000).escrow();
+        assert!(!should_unwrap(&escrow, 0));
+
+        escrow.unwrap_sol = true;
+        assert!(should_unwrap(&escrow, 0));
+        // The taker's own wSOL stays wrapped, along with what the take delivered
+        assert!(!should_unwrap(&escrow, 1));
+    }
+
+    #[test]
+    fn empty_vaults_cant_be_taken() {
+        let mut market = Market::new(500, 1_000);
+        market.svm.set_token_account(market.vault, market.mint_a, market.escrow, 0);
+
+        assert_eq!(market.svm.process(&[market.take_ix(1_000)]), Err(anchor_error(EscrowError::EmptyVault)));
+        assert_eq!(market.escrow().receive, 1_000);
+    }
+
+    #[test]
+    fn escrows_stay_takeable_up_to_their_deadline() {
+        let mut market = Market::new(500, 1_000);
+        let deadline = market.svm.now + 60;
+        market.edit_escrow(|escrow| escrow.deadline = deadline);
+        market.svm.now = deadline;
+
+        // Gets past the deadline check and fails on the fill instead
+        assert_eq!(market.svm.process(&[market.take_ix(0)]), Err(anchor_error(EscrowError::InvalidAmount)));
+        market.svm.now = deadline + 1;
+        assert_eq!(market.svm.process(&[market.take_ix(0)]), Err(anchor_error(EscrowError::Expired)));
+    }
+
+    #[test]
+    fn partial_fills_add_up_to_the_deposit() {
+        let mut escrow = Market::new(333, 1_000).escrow();
+        let mut delivered = 0;
+
+        // Each partial fill rounds down, the last one drains the dust
+        for fill_amount in [100, 250, 301, 349] {
+            let fill = Fill::new(&escrow, escrow.amount, fill_amount, None).unwrap();
+            delivered += fill.delivered;
+            escrow.amount -= fill.amount_out;
+            escrow.receive -= fill_amount;
+        }
+
+        assert_eq!(escrow.receive, 0);
+        assert_eq!(delivered, 333);
+    }
+
+    #[test]
+    fn fills_are_bounded_by_what_is_left() {
+        let mut market = Market::new(500, 1_000);
+
+        // Too little for a whole base unit of Token A
+        assert_eq!(market.svm.process(&[market.take_ix(1)]), Err(anchor_error(EscrowError::FillTooSmall)));
+        // More than is left
+        assert_eq!(market.svm.process(&[market.take_ix(1_001)]), Err(anchor_error(EscrowError::InvalidAmount)));
+        assert_eq!((market.escrow().amount, market.escrow().receive), (500, 1_000));
+    }
+
+    #[test]
+    fn max_amount_b_in_bounds_the_fee_too() {
+        let mut market = Market::new(500, 1_000);
+        market.set_config(EscrowConfig { fee_bps: 100, ..market.config() });
+
+        let take = ix(market.take_accounts(), crate::instruction::TakePartial { fill_amount_b: 400, min_amount_a_out: 0, max_amount_b_in: 403 });
+        assert_eq!(market.svm.process(&[take]), Err(anchor_error(EscrowError::SlippageExceeded)));
+    }
+
+    #[test]
+    fn fee_needs_the_treasury_account() {
+        let mut market = Market::new(500, 1_000);
+        market.set_config(EscrowConfig { fee_bps: 100, ..market.config() });
+
+        let take = |treasury_ata_b| ix(
+            crate::accounts::Take { treasury_ata_b, ..market.take_accounts() },
+            crate::instruction::TakePartial { fill_amount_b: 400, min_amount_a_out: 0, max_amount_b_in: u64::MAX },
+        );
+        // Right mint, wrong owner
+        let elsewhere = take(Some(market.ata(&market.taker, &market.mint_b)));
+        let missing = take(None);
+
+        assert_eq!(market.svm.process(&[missing]), Err(anchor_error(EscrowError::InvalidTreasury)));
+        assert_eq!(market.svm.process(&[elsewhere]), Err(anchor_error(EscrowError::InvalidTreasury)));
+    }
+
+    /// Market at 2 Token B per Token A with `price` posted for its mints
+    fn quoted_market(price: u64) -> (Market, Pubkey) {
+        let mut market = Market::new(500, 1_000);
+        let quote = OraclePrice { mint_a: market.mint_a, mint_b: market.mint_b, price };
+        let mut data = Vec::new();
+        quote.serialize(&mut data).unwrap();
+
+        let oracle = Pubkey::new_unique();
+        market.svm.set_account(oracle, TestAccount { lamports: 1_000_000, data, owner: Pubkey::new_unique() });
+        (market, oracle)
+    }
+
+    fn take_checked(market: &Market, oracle: Option<Pubkey>, fill_amount: u64, max_deviation_bps: u16) -> anchor_lang::solana_program::instruction::Instruction {
+        ix(
+            crate::accounts::Take { oracle, ..market.take_accounts() },
+            crate::instruction::Take { proof: Vec::new(), fill_amount, min_amount_a_out: 0, max_amount_b_in: u64::MAX, max_deviation_bps: Some(max_deviation_bps) },
+        )
+    }
+
+    #[test]
+    fn price_checks_need_an_oracle() {
+        let (mut market, _) = quoted_market(2_000_000_000);
+
+        assert_eq!(market.svm.process(&[take_checked(&market, None, 1_000, 100)]), Err(anchor_error(EscrowError::MissingOracle)));
+    }
+
+    #[test]
+    fn price_checks_stop_deviating_takes() {
+        // 10% under the escrow's price
+        let (mut market, oracle) = quoted_market(1_800_000_000);
+
+        assert_eq!(market.svm.process(&[take_checked(&market, Some(oracle), 1_000, 1_000)]), Err(anchor_error(EscrowError::PriceDeviationTooHigh)));
+        // Within tolerance it gets past the check and fails on the fill instead
+        assert_eq!(market.svm.process(&[take_checked(&market, Some(oracle), 0, 1_200)]), Err(anchor_error(EscrowError::InvalidAmount)));
+    }
+
+    #[test]
+    fn oracles_have_to_quote_the_escrow_mints() {
+        let (mut market, oracle) = quoted_market(2_000_000_000);
+        let swapped = OraclePrice { mint_a: market.mint_b, mint_b: market.mint_a, price: 2_000_000_000 };
+        let mut data = Vec::new();
+        swapped.serialize(&mut data).unwrap();
+        market.svm.set_account(oracle, TestAccount { lamports: 1_000_000, data, owner: Pubkey::new_unique() });
+
+        assert_eq!(market.svm.process(&[take_checked(&market, Some(oracle), 1_000, 10_000)]), Err(anchor_error(EscrowError::InvalidOracle)));
+    }
+
+    #[test]
+    fn private_escrows_reject_other_takers() {
+        let mut market = Market::new(500, 1_000);
+        market.edit_escrow(|escrow| escrow.allowed_taker = Pubkey::new_unique());
+
+        assert_eq!(market.svm.process(&[market.take_ix(1_000)]), Err(anchor_error(EscrowError::UnauthorizedTaker)));
+
+        // The counterparty gets past the check and fails on the fill instead
+        let taker = market.taker;
+        market.edit_escrow(|escrow| escrow.allowed_taker = taker);
+        assert_eq!(market.svm.process(&[market.take_ix(0)]), Err(anchor_error(EscrowError::InvalidAmount)));
+    }
+
+    #[test]
+    fn the_price_history_is_optional_but_has_to_be_the_pairs() {
+        let mut market = Market::new(500, 1_000);
+        let elsewhere = Pubkey::new_unique();
+        let history: PriceHistory = market.svm.state(&market.price_history_key());
+        market.svm.set_state(elsewhere, &history, PriceHistory::INIT_SPACE + PriceHistory::DISCRIMINATOR.len());
+
+        let take = |price_history| ix(
+            crate::accounts::Take { price_history, ..market.take_accounts() },
+            crate::instruction::TakePartial { fill_amount_b: 0, min_amount_a_out: 0, max_amount_b_in: u64::MAX },
+        );
+        let without = take(None);
+        let other_pair = take(Some(elsewhere));
+
+        // Gets past the accounts and fails on the fill instead
+        assert_eq!(market.svm.process(&[without]), Err(anchor_error(EscrowError::InvalidAmount)));
+        assert_eq!(market.svm.process(&[other_pair]), Err(anchor_error(ErrorCode::ConstraintSeeds)));
+    }
+}
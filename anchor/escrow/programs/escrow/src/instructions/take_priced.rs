@@ -0,0 +1,263 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token::
+{
+    transfer_checked, close_account, 
+    CloseAccount, TransferChecked
+};
+
+use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Escrow, Stats};
+use crate::errors::EscrowError;
+
+
+#[derive(Accounts)]
+pub struct TakePriced<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        has_one = mint_a @ EscrowError::InvalidMintA,
+        has_one = mint_b @ EscrowError::InvalidMintB,
+        constraint = escrow.is_priced() @ EscrowError::NotPricedEscrow,
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+ 
+    /// Token Accounts
+    pub mint_a: Box<InterfaceAccount<'info, Mint>>,
+    pub mint_b: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
+    )]
+    pub taker_ata_a: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
+    )]
+    pub taker_ata_b: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_b: Box<InterfaceAccount<'info, TokenAccount>>,
+ 
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+ 
+    /// Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> TakePriced<'info> {
+    fn payment_for(&self, quantity: u64) -> Result<u64> {
+        let payment = (quantity as u128)
+            .checked_mul(self.escrow.price_per_unit as u128)
+            .ok_or(EscrowError::Overflow)?;
+
+        Ok(u64::try_from(payment).map_err(|_| EscrowError::Overflow)?)
+    }
+
+    fn transfer_to_maker(&mut self, payment: u64) -> Result<()> {
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.taker_ata_b.to_account_info(),
+                    to: self.maker_ata_b.to_account_info(),
+                    mint: self.mint_b.to_account_info(),
+                    authority: self.taker.to_account_info(),
+                },
+            ),
+            payment,
+            self.mint_b.decimals
+        )?;
+ 
+        Ok(())
+    }
+ 
+    fn withdraw(&mut self, quantity: u64) -> Result<()> {
+        // Create the signer seeds for the Vault
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            Escrow::SEED,
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+ 
+        // Transfer Token A (Vault -> Taker)
+        transfer_checked(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.vault.to_account_info(),
+                    to: self.taker_ata_a.to_account_info(),
+                    mint: self.mint_a.to_account_info(),
+                    authority: self.escrow.to_account_info(),
+                },
+                &signer_seeds
+            ),
+            quantity,
+            self.mint_a.decimals
+        )?;
+ 
+        Ok(())
+    }
+ 
+    fn close_vault_and_escrow(&mut self) -> Result<()> {
+        // Create the signer seeds for the Vault
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            Escrow::SEED,
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+ 
+        // Close the Vault
+        close_account(
+            CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                CloseAccount {
+                    account: self.vault.to_account_info(),
+                    authority: self.escrow.to_account_info(),
+                    destination: self.maker.to_account_info(),
+                },
+                &signer_seeds
+            ),
+        )?;
+
+        // Close the Escrow
+        self.escrow.close(self.maker.to_account_info())?;
+ 
+        Ok(())
+    }
+}
+ 
+pub fn handler(ctx: Context<TakePriced>, quantity: u64) -> Result<()> {
+    // Validate the quantity
+    require!(quantity > 0, EscrowError::InvalidAmount);
+    require!(
+        quantity <= ctx.accounts.vault.amount,
+        EscrowError::InsufficientVaultForQuantity
+    );
+
+    let payment = ctx.accounts.payment_for(quantity)?;
+ 
+    // Transfer Token B to Maker
+    ctx.accounts.transfer_to_maker(payment)?;
+ 
+    // Withdraw the purchased Token A
+    ctx.accounts.withdraw(quantity)?;
+
+    ctx.accounts.escrow.amount = ctx.accounts.escrow.amount.saturating_sub(quantity);
+
+    // The escrow stays open until the vault is sold out
+    ctx.accounts.vault.reload()?;
+    let sold_out = ctx.accounts.vault.amount == 0;
+    if sold_out {
+        ctx.accounts.close_vault_and_escrow()?;
+    }
+
+    let (mint_a, mint_b) = (ctx.accounts.mint_a.key(), ctx.accounts.mint_b.key());
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_take(mint_a, quantity, mint_b, payment, sold_out);
+    }
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    /// Market whose escrow sells its 500 Token A at `price_per_unit` each
+    fn priced_market(price_per_unit: u64) -> Market {
+        let mut market = Market::new(500, 1_000);
+        market.edit_escrow(|escrow| {
+            escrow.receive = 0;
+            escrow.price_per_unit = price_per_unit;
+        });
+        market
+    }
+
+    fn take_ix(market: &Market, quantity: u64) -> anchor_lang::solana_program::instruction::Instruction {
+        ix(
+            crate::accounts::TakePriced {
+                taker: market.taker,
+                maker: market.maker,
+                escrow: market.escrow,
+                mint_a: market.mint_a,
+                mint_b: market.mint_b,
+                vault: market.vault,
+                taker_ata_a: market.ata(&market.taker, &market.mint_a),
+                taker_ata_b: market.ata(&market.taker, &market.mint_b),
+                maker_ata_b: market.ata(&market.maker, &market.mint_b),
+                stats: None,
+                associated_token_program: anchor_spl::associated_token::ID,
+                token_program: anchor_spl::token::ID,
+                system_program: system_program::ID,
+            },
+            crate::instruction::TakePriced { quantity },
+        )
+    }
+
+    #[test]
+    fn quantity_has_to_fit_the_vault() {
+        let mut market = priced_market(3);
+
+        assert_eq!(market.svm.process(&[take_ix(&market, 0)]), Err(anchor_error(EscrowError::InvalidAmount)));
+        assert_eq!(market.svm.process(&[take_ix(&market, 501)]), Err(anchor_error(EscrowError::InsufficientVaultForQuantity)));
+    }
+
+    #[test]
+    fn payment_overflow_is_rejected() {
+        let mut market = priced_market(u64::MAX);
+
+        assert_eq!(market.svm.process(&[take_ix(&market, 2)]), Err(anchor_error(EscrowError::Overflow)));
+    }
+
+    #[test]
+    fn fixed_receive_escrows_arent_priced() {
+        let mut market = Market::new(500, 1_000);
+
+        assert_eq!(market.svm.process(&[take_ix(&market, 1)]), Err(anchor_error(EscrowError::NotPricedEscrow)));
+    }
+
+    #[test]
+    fn priced_escrows_cant_be_repriced_through_update() {
+        let mut market = priced_market(3);
+        let update = ix(market.update_accounts(), crate::instruction::UpdateReceive { new_receive: 10 });
+
+        assert_eq!(market.svm.process(&[update]), Err(anchor_error(EscrowError::PricedEscrow)));
+    }
+}
@@ -0,0 +1,108 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::state::{Escrow, Stats};
+use crate::errors::EscrowError;
+use crate::condition;
+use crate::EscrowTaken;
+
+
+#[derive(Accounts)]
+pub struct TakeSol<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = maker,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        constraint = escrow.is_native() @ EscrowError::NotNativeEscrow,
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    /// CHECK: must match `escrow.condition_program`, only needed for conditional escrows
+    pub condition_program: Option<UncheckedAccount<'info>>,
+
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+ 
+    /// Programs
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> TakeSol<'info> {
+    fn transfer_to_maker(&mut self) -> Result<()> {
+        transfer(
+            CpiContext::new(
+                self.system_program.to_account_info(),
+                Transfer {
+                    from: self.taker.to_account_info(),
+                    to: self.maker.to_account_info(),
+                },
+            ),
+            self.escrow.receive,
+        )?;
+
+        Ok(())
+    }
+
+    fn deposit(&self) -> Result<u64> {
+        // Everything above the rent-exempt minimum is the maker's deposit,
+        // the rent itself goes back to the maker when the escrow is closed
+        let rent = Rent::get()?.minimum_balance(self.escrow.to_account_info().data_len());
+
+        Ok(Escrow::lamport_deposit(self.escrow.get_lamports(), rent))
+    }
+
+    fn withdraw_deposit(&mut self) -> Result<()> {
+        let deposit = self.deposit()?;
+
+        // Transfer the deposit (Escrow -> Taker)
+        self.escrow.sub_lamports(deposit)?;
+        self.taker.add_lamports(deposit)?;
+
+        Ok(())
+    }
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, TakeSol<'info>>) -> Result<()> {
+    // Don't let the taker pay for an empty escrow
+    let deposit = ctx.accounts.deposit()?;
+    require!(deposit > 0, EscrowError::EmptyVault);
+
+    // Same checks as every other take, SOL escrows have no allowlist
+    ctx.accounts.escrow.check_taker(&ctx.accounts.taker.key(), &[], Clock::get()?.unix_timestamp)?;
+    condition::check_escrow(
+        &ctx.accounts.escrow,
+        ctx.accounts.condition_program.as_ref(),
+        &ctx.accounts.taker,
+        ctx.remaining_accounts,
+        ctx.accounts.escrow.receive,
+    )?;
+
+    // Transfer Lamports to Maker
+    ctx.accounts.transfer_to_maker()?;
+
+    // Withdraw the deposit
+    ctx.accounts.withdraw_deposit()?;
+
+    let receive = ctx.accounts.escrow.receive;
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_take(system_program::ID, deposit, system_program::ID, receive, true);
+    }
+
+    emit!(EscrowTaken {
+        escrow: ctx.accounts.escrow.key(),
+        taker: ctx.accounts.taker.key(),
+    });
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{transfer_checked, TransferChecked};
+use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Escrow, Stats};
+use crate::errors::EscrowError;
+use crate::condition;
+use crate::EscrowTaken;
+
+
+#[derive(Accounts)]
+pub struct TakeSolForToken<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = maker,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        has_one = mint_b @ EscrowError::InvalidMintB,
+        constraint = escrow.holds_sol() @ EscrowError::NotNativeEscrow,
+    )]
+    pub escrow: Account<'info, Escrow>,
+ 
+    /// Token Accounts
+    pub mint_b: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program
+    )]
+    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
+ 
+    /// CHECK: must match `escrow.condition_program`, only needed for conditional escrows
+    pub condition_program: Option<UncheckedAccount<'info>>,
+
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+ 
+    /// Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> TakeSolForToken<'info> {
+    fn transfer_to_maker(&mut self) -> Result<()> {
+        transfer_checked(
+            CpiContext::new(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.taker_ata_b.to_account_info(),
+                    to: self.maker_ata_b.to_account_info(),
+                    mint: self.mint_b.to_account_info(),
+                    authority: self.taker.to_account_info(),
+                },
+            ),
+            self.escrow.receive,
+            self.mint_b.decimals
+        )?;
+ 
+        Ok(())
+    }
+
+    fn deposit(&self) -> Result<u64> {
+        // Everything above the rent-exempt minimum is the maker's deposit,
+        // the rent itself goes back to the maker when the escrow is closed
+        let rent = Rent::get()?.minimum_balance(self.escrow.to_account_info().data_len());
+
+        Ok(Escrow::lamport_deposit(self.escrow.get_lamports(), rent))
+    }
+
+    fn withdraw_deposit(&mut self) -> Result<()> {
+        let deposit = self.deposit()?;
+
+        // Transfer the deposit (Escrow -> Taker)
+        self.escrow.sub_lamports(deposit)?;
+        self.taker.add_lamports(deposit)?;
+
+        Ok(())
+    }
+}
+ 
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, TakeSolForToken<'info>>) -> Result<()> {
+    // Don't let the taker pay for an empty escrow
+    let deposit = ctx.accounts.deposit()?;
+    require!(deposit > 0, EscrowError::EmptyVault);
+
+    // Same checks as every other take, SOL escrows have no allowlist
+    ctx.accounts.escrow.check_taker(&ctx.accounts.taker.key(), &[], Clock::get()?.unix_timestamp)?;
+    condition::check_escrow(
+        &ctx.accounts.escrow,
+        ctx.accounts.condition_program.as_ref(),
+        &ctx.accounts.taker,
+        ctx.remaining_accounts,
+        ctx.accounts.escrow.receive,
+    )?;
+
+    // Transfer Token B to Maker
+    ctx.accounts.transfer_to_maker()?;
+
+    // Withdraw the deposit
+    ctx.accounts.withdraw_deposit()?;
+
+    let (mint_b, receive) = (ctx.accounts.mint_b.key(), ctx.accounts.escrow.receive);
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_take(system_program::ID, deposit, mint_b, receive, true);
+    }
+
+    emit!(EscrowTaken {
+        escrow: ctx.accounts.escrow.key(),
+        taker: ctx.accounts.taker.key(),
+    });
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    /// Market whose escrow holds lamports and asks for its Token B, with
+    /// `change` applied to the escrow before the deposit goes in
+    fn sol_market(change: impl FnOnce(&mut Escrow)) -> Market {
+        let mut market = Market::new(500, 1_000);
+        market.edit_escrow(|escrow| {
+            escrow.mint_a = system_program::ID;
+            change(escrow);
+        });
+        market.svm.airdrop(&market.escrow, 2_000_000);
+        market
+    }
+
+    fn take_ix(market: &Market, condition_program: Option<Pubkey>) -> anchor_lang::solana_program::instruction::Instruction {
+        ix(
+            crate::accounts::TakeSolForToken {
+                taker: market.taker,
+                maker: market.maker,
+                escrow: market.escrow,
+                mint_b: market.mint_b,
+                taker_ata_b: market.ata(&market.taker, &market.mint_b),
+                maker_ata_b: market.ata(&market.maker, &market.mint_b),
+                condition_program,
+                stats: Some(market.stats_key()),
+                associated_token_program: anchor_spl::associated_token::ID,
+                token_program: anchor_spl::token::ID,
+                system_program: system_program::ID,
+            },
+            crate::instruction::TakeSolForToken {},
+        )
+    }
+
+    #[test]
+    fn expired_sol_escrows_cant_be_taken() {
+        let mut market = sol_market(|escrow| escrow.deadline = escrow.created_at - 1);
+
+        assert_eq!(market.svm.process(&[take_ix(&market, None)]), Err(anchor_error(EscrowError::Expired)));
+    }
+
+    #[test]
+    fn private_sol_escrows_only_fill_for_their_taker() {
+        let mut market = sol_market(|escrow| escrow.allowed_taker = Pubkey::new_unique());
+
+        assert_eq!(market.svm.process(&[take_ix(&market, None)]), Err(anchor_error(EscrowError::UnauthorizedTaker)));
+    }
+
+    #[test]
+    fn conditional_sol_escrows_need_their_checker() {
+        let mut market = sol_market(|escrow| escrow.condition_program = Some(Pubkey::new_unique()));
+
+        assert_eq!(market.svm.process(&[take_ix(&market, None)]), Err(anchor_error(EscrowError::InvalidConditionProgram)));
+        let wrong = Pubkey::new_unique();
+        assert_eq!(market.svm.process(&[take_ix(&market, Some(wrong))]), Err(anchor_error(EscrowError::InvalidConditionProgram)));
+    }
+}
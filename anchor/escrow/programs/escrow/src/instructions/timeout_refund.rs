@@ -0,0 +1,124 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::token_interface::{TokenInterface, Mint, TokenAccount};
+use anchor_spl::associated_token::AssociatedToken;
+
+use crate::state::{Escrow, Stats};
+use crate::errors::EscrowError;
+use crate::EscrowRefunded;
+use super::refund::withdraw_and_close_vault;
+
+/// Permissionless once the deadline has passed, funds still go to the maker
+#[derive(Accounts)]
+pub struct TimeoutRefund<'info> {
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mut,
+        close = maker,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        has_one = mint_a @ EscrowError::InvalidMintA,
+    )]
+    pub escrow: Account<'info, Escrow>,
+ 
+    /// Token Accounts
+    pub mint_a: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = caller,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+ 
+    /// Leave out to skip the protocol counters
+    #[account(
+        mut,
+        seeds = [Stats::SEED],
+        bump = stats.bump,
+    )]
+    pub stats: Option<Box<Account<'info, Stats>>>,
+ 
+    /// Programs
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> TimeoutRefund<'info> {
+    fn withdraw_and_close_vault(&mut self) -> Result<()> {
+        withdraw_and_close_vault(
+            &self.escrow,
+            &self.maker.to_account_info(),
+            &self.mint_a,
+            &self.vault,
+            &self.maker_ata_a,
+            &self.token_program,
+        )
+    }
+}
+ 
+pub fn handler(ctx: Context<TimeoutRefund>) -> Result<()> {
+    // Only escrows with a deadline can time out
+    require!(ctx.accounts.escrow.is_expired(Clock::get()?.unix_timestamp), EscrowError::NotExpired);
+
+    // Withdraw and close the Vault (Vault -> Maker)
+    ctx.accounts.withdraw_and_close_vault()?;
+
+    if let Some(stats) = ctx.accounts.stats.as_mut() {
+        stats.record_refund();
+    }
+
+    emit!(EscrowRefunded {
+        escrow: ctx.accounts.escrow.key(),
+        maker: ctx.accounts.maker.key(),
+    });
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn only_expired_escrows_time_out() {
+        let mut market = Market::new(500, 1_000);
+        let caller = market.svm.wallet();
+        let refund = ix(market.timeout_refund_accounts(caller), crate::instruction::TimeoutRefund {});
+
+        // No deadline, it never times out
+        assert_eq!(market.svm.process(std::slice::from_ref(&refund)), Err(anchor_error(EscrowError::NotExpired)));
+
+        // Open up to and including the deadline
+        let deadline = market.svm.now + 60;
+        market.edit_escrow(|escrow| escrow.deadline = deadline);
+        market.svm.now = deadline;
+        assert_eq!(market.svm.process(&[refund]), Err(anchor_error(EscrowError::NotExpired)));
+    }
+
+    #[test]
+    fn past_the_deadline_takes_stop_and_the_escrow_is_expired() {
+        let mut market = Market::new(500, 1_000);
+        let deadline = market.svm.now + 60;
+        market.edit_escrow(|escrow| escrow.deadline = deadline);
+
+        market.svm.now = deadline + 1;
+        assert_eq!(market.svm.process(&[market.take_ix(1_000)]), Err(anchor_error(EscrowError::Expired)));
+        assert!(market.escrow().is_expired(market.svm.now));
+        assert!(!market.escrow().is_expired(deadline));
+    }
+}
@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+
+use crate::state::PriceHistory;
+use crate::errors::EscrowError;
+
+/// Read-only, the average comes back as return data
+#[derive(Accounts)]
+pub struct Twap<'info> {
+    #[account(
+        seeds = [PriceHistory::SEED, price_history.mint_a.as_ref(), price_history.mint_b.as_ref()],
+        bump = price_history.bump,
+    )]
+    pub price_history: Account<'info, PriceHistory>,
+}
+ 
+pub fn handler(ctx: Context<Twap>, window: i64) -> Result<u64> {
+    let now = Clock::get()?.unix_timestamp;
+    let twap = ctx.accounts.price_history.twap(now, window).ok_or(EscrowError::NoPriceHistory)?;
+ 
+    Ok(twap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn twap_needs_a_settlement() {
+        let mut market = Market::new(500, 1_000);
+        let twap = ix(crate::accounts::Twap { price_history: market.price_history_key() }, crate::instruction::Twap { window: 0 });
+        assert_eq!(market.svm.process(std::slice::from_ref(&twap)), Err(anchor_error(EscrowError::NoPriceHistory)));
+
+        let mut history: PriceHistory = market.svm.state(&market.price_history_key());
+        history.record(PriceHistory::price(1_000, 500), market.svm.now - 60);
+        market.svm.set_state(market.price_history_key(), &history, PriceHistory::INIT_SPACE + PriceHistory::DISCRIMINATOR.len());
+        market.svm.process(&[twap]).unwrap();
+    }
+}
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Escrow;
+use crate::errors::EscrowError;
+use crate::EscrowUpdated;
+
+/// Reprices an open escrow in place. A take racing an update fills at the
+/// new terms, so takers should pass `min_amount_a_out` / `max_amount_b_in`.
+#[derive(Accounts)]
+pub struct Update<'info> {
+    pub maker: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [Escrow::SEED, maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker,
+        constraint = !escrow.locked @ EscrowError::EscrowLocked,
+        constraint = !escrow.is_priced() @ EscrowError::PricedEscrow,
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+ 
+pub fn handler(ctx: Context<Update>, receive: u64, deadline: i64) -> Result<()> {
+    // Validate the new terms
+    require!(receive > 0, EscrowError::InvalidAmount);
+    require!(deadline == 0 || deadline > Clock::get()?.unix_timestamp, EscrowError::InvalidDeadline);
+
+    let escrow = &mut ctx.accounts.escrow;
+
+    // A relist policy needs a deadline to count from
+    require!(deadline != 0 || escrow.relist.is_none(), EscrowError::InvalidDeadline);
+
+    escrow.receive = receive;
+    escrow.deadline = deadline;
+
+    emit!(EscrowUpdated {
+        escrow: escrow.key(),
+        receive,
+        deadline,
+    });
+ 
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn update_receive_keeps_the_deadline() {
+        let mut market = Market::new(500, 1_000);
+        let deadline = market.svm.now + 60;
+        market.edit_escrow(|escrow| escrow.deadline = deadline);
+
+        let update_receive = ix(market.update_accounts(), crate::instruction::UpdateReceive { new_receive: 2_000 });
+        market.svm.process(&[update_receive]).unwrap();
+        assert_eq!((market.escrow().receive, market.escrow().deadline), (2_000, deadline));
+
+        let update = ix(market.update_accounts(), crate::instruction::Update { receive: 1_500, deadline: 0 });
+        market.svm.process(&[update]).unwrap();
+        assert_eq!((market.escrow().receive, market.escrow().deadline), (1_500, 0));
+
+        let zero = ix(market.update_accounts(), crate::instruction::UpdateReceive { new_receive: 0 });
+        assert_eq!(market.svm.process(&[zero]), Err(anchor_error(EscrowError::InvalidAmount)));
+    }
+}
@@ -2,8 +2,17 @@ use anchor_lang::prelude::*;
  
 mod state;
 mod errors;
+mod merkle;
+pub mod condition;
+pub mod pricing;
+pub mod oracle;
+#[cfg(feature = "cpi")]
+pub mod settle;
 mod instructions;
+#[cfg(test)]
+mod test_utils;
 use instructions::*;
+use state::RelistPolicy;
 
 declare_id!("22222222222222222222222222222222222222222222");
  
@@ -12,16 +21,210 @@ pub mod blueshift_anchor_escrow {
     use super::*;
  
     #[instruction(discriminator = 0)]
-    pub fn make(ctx: Context<Make>, seed: u64, receive: u64, amount: u64) -> Result<()> {
-        instructions::make::handler(ctx, seed, receive, amount)
+    #[allow(clippy::too_many_arguments)]
+    pub fn make<'info>(ctx: Context<'_, '_, 'info, 'info, Make<'info>>, seed: u64, receive: u64, amount: u64, insured: bool, allowlist_root: Option<[u8; 32]>, deadline: i64, allowed_taker: Pubkey, wrap_sol: bool) -> Result<()> {
+        instructions::make::handler(ctx, seed, receive, amount, insured, allowlist_root, deadline, allowed_taker, wrap_sol)
     }
  
     #[instruction(discriminator = 1)]
-    pub fn take(ctx: Context<Take>) -> Result<()> {
-        instructions::take::handler(ctx)
+    pub fn take<'info>(ctx: Context<'_, '_, 'info, 'info, Take<'info>>, proof: Vec<[u8; 32]>, fill_amount: u64, min_amount_a_out: u64, max_amount_b_in: u64, max_deviation_bps: Option<u16>) -> Result<()> {
+        instructions::take::handler(ctx, proof, fill_amount, min_amount_a_out, max_amount_b_in, max_deviation_bps)
     }
  
     #[instruction(discriminator = 2)]    pub fn refund(ctx: Context<Refund>) -> Result<()> {
         instructions::refund::handler(ctx)
     }
+
+    #[instruction(discriminator = 3)]
+    pub fn make_sol(ctx: Context<MakeSol>, seed: u64, receive: u64, amount: u64) -> Result<()> {
+        instructions::make_sol::handler(ctx, seed, receive, amount)
+    }
+
+    #[instruction(discriminator = 4)]
+    pub fn take_sol<'info>(ctx: Context<'_, '_, 'info, 'info, TakeSol<'info>>) -> Result<()> {
+        instructions::take_sol::handler(ctx)
+    }
+
+    #[instruction(discriminator = 5)]
+    pub fn refund_sol(ctx: Context<RefundSol>) -> Result<()> {
+        instructions::refund_sol::handler(ctx)
+    }
+ 
+    #[instruction(discriminator = 6)]
+    pub fn make_with_bump(ctx: Context<MakeWithBump>, seed: u64, receive: u64, amount: u64, bump: u8) -> Result<()> {
+        instructions::make_with_bump::handler(ctx, seed, receive, amount, bump)
+    }
+ 
+    #[instruction(discriminator = 7)]
+    pub fn make_priced(ctx: Context<MakePriced>, seed: u64, price_per_unit: u64, amount: u64) -> Result<()> {
+        instructions::make_priced::handler(ctx, seed, price_per_unit, amount)
+    }
+ 
+    #[instruction(discriminator = 8)]
+    pub fn take_priced(ctx: Context<TakePriced>, quantity: u64) -> Result<()> {
+        instructions::take_priced::handler(ctx, quantity)
+    }
+ 
+    #[instruction(discriminator = 9)]
+    pub fn make_otc(ctx: Context<MakeOtc>, seed: u64, taker: Pubkey, amount_a: u64, amount_b: u64) -> Result<()> {
+        instructions::make_otc::handler(ctx, seed, taker, amount_a, amount_b)
+    }
+ 
+    #[instruction(discriminator = 10)]
+    pub fn fund_otc(ctx: Context<FundOtc>) -> Result<()> {
+        instructions::fund_otc::handler(ctx)
+    }
+ 
+    #[instruction(discriminator = 11)]
+    pub fn finalize_otc(ctx: Context<FinalizeOtc>) -> Result<()> {
+        instructions::finalize_otc::handler(ctx)
+    }
+ 
+    #[instruction(discriminator = 12)]
+    pub fn abort_otc(ctx: Context<AbortOtc>) -> Result<()> {
+        instructions::abort_otc::handler(ctx)
+    }
+ 
+    #[instruction(discriminator = 13)]
+    pub fn set_relist_policy(ctx: Context<SetRelistPolicy>, relist: Option<RelistPolicy>) -> Result<()> {
+        instructions::set_relist_policy::handler(ctx, relist)
+    }
+ 
+    #[instruction(discriminator = 14)]
+    pub fn relist_expired(ctx: Context<RelistExpired>) -> Result<()> {
+        instructions::relist_expired::handler(ctx)
+    }
+ 
+    #[instruction(discriminator = 15)]
+    pub fn timeout_refund(ctx: Context<TimeoutRefund>) -> Result<()> {
+        instructions::timeout_refund::handler(ctx)
+    }
+ 
+    #[instruction(discriminator = 16)]
+    pub fn take_partial<'info>(ctx: Context<'_, '_, 'info, 'info, Take<'info>>, fill_amount_b: u64, min_amount_a_out: u64, max_amount_b_in: u64) -> Result<()> {
+        instructions::take::handler_partial(ctx, fill_amount_b, min_amount_a_out, max_amount_b_in)
+    }
+ 
+    /// Reprice an open escrow and reset its deadline, 0 for none
+    #[instruction(discriminator = 17)]
+    pub fn update(ctx: Context<Update>, receive: u64, deadline: i64) -> Result<()> {
+        instructions::update::handler(ctx, receive, deadline)
+    }
+ 
+    #[instruction(discriminator = 18)]
+    pub fn set_condition(ctx: Context<SetCondition>, condition_program: Option<Pubkey>) -> Result<()> {
+        instructions::set_condition::handler(ctx, condition_program)
+    }
+ 
+    #[instruction(discriminator = 19)]
+    pub fn take_sol_for_token<'info>(ctx: Context<'_, '_, 'info, 'info, TakeSolForToken<'info>>) -> Result<()> {
+        instructions::take_sol_for_token::handler(ctx)
+    }
+ 
+    /// `update` with the escrow's current deadline. Kept as its own
+    /// instruction for clients that only reprice, the same call as the
+    /// pinocchio program's `Update`, without reading the deadline first.
+    #[instruction(discriminator = 20)]
+    pub fn update_receive(ctx: Context<Update>, new_receive: u64) -> Result<()> {
+        let deadline = ctx.accounts.escrow.deadline;
+        update(ctx, new_receive, deadline)
+    }
+ 
+    #[instruction(discriminator = 21)]
+    pub fn finalize(ctx: Context<Finalize>) -> Result<()> {
+        instructions::finalize::handler(ctx)
+    }
+ 
+    #[instruction(discriminator = 22)]
+    pub fn set_burn(ctx: Context<SetBurn>, burn_bps: u16) -> Result<()> {
+        instructions::set_burn::handler(ctx, burn_bps)
+    }
+ 
+    #[instruction(discriminator = 23)]
+    pub fn initialize_config(ctx: Context<InitializeConfig>, fee_bps: u16, treasury: Pubkey) -> Result<()> {
+        instructions::initialize_config::handler(ctx, fee_bps, treasury)
+    }
+ 
+    #[instruction(discriminator = 24)]
+    pub fn set_fee(ctx: Context<SetFee>, fee_bps: u16, treasury: Pubkey) -> Result<()> {
+        instructions::set_fee::handler(ctx, fee_bps, treasury)
+    }
+ 
+    #[instruction(discriminator = 25)]
+    pub fn twap(ctx: Context<Twap>, window: i64) -> Result<u64> {
+        instructions::twap::handler(ctx, window)
+    }
+ 
+    #[instruction(discriminator = 26)]
+    pub fn merge(ctx: Context<Merge>) -> Result<()> {
+        instructions::merge::handler(ctx)
+    }
+ 
+    #[instruction(discriminator = 27)]
+    pub fn take_and_close<'info>(ctx: Context<'_, '_, 'info, 'info, Take<'info>>, fill_amount: u64, min_amount_a_out: u64, max_amount_b_in: u64) -> Result<()> {
+        instructions::take::handler_and_close(ctx, fill_amount, min_amount_a_out, max_amount_b_in)
+    }
+ 
+    #[instruction(discriminator = 28)]
+    pub fn crank_refund(ctx: Context<CrankRefund>) -> Result<()> {
+        instructions::crank_refund::handler(ctx)
+    }
+ 
+    #[instruction(discriminator = 29)]
+    pub fn set_crank_bounty(ctx: Context<SetFee>, crank_bounty: u64) -> Result<()> {
+        instructions::set_fee::handler_crank_bounty(ctx, crank_bounty)
+    }
+ 
+    #[instruction(discriminator = 30)]
+    pub fn set_insurance_premium(ctx: Context<SetFee>, insurance_premium: u64) -> Result<()> {
+        instructions::set_fee::handler_insurance_premium(ctx, insurance_premium)
+    }
+ 
+    #[instruction(discriminator = 31)]
+    pub fn initialize_stats(ctx: Context<InitializeStats>) -> Result<()> {
+        instructions::initialize_stats::handler(ctx)
+    }
+ 
+    #[instruction(discriminator = 32)]
+    pub fn initialize_price_history(ctx: Context<InitializePriceHistory>) -> Result<()> {
+        instructions::initialize_price_history::handler(ctx)
+    }
+}
+
+#[event]
+pub struct EscrowMade {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount: u64,
+    pub receive: u64,
+}
+
+#[event]
+pub struct EscrowTaken {
+    pub escrow: Pubkey,
+    pub taker: Pubkey,
+}
+
+#[event]
+pub struct EscrowUpdated {
+    pub escrow: Pubkey,
+    pub receive: u64,
+    pub deadline: i64,
+}
+
+#[event]
+pub struct EscrowRefunded {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+}
+
+#[event]
+pub struct EscrowMerged {
+    pub escrow: Pubkey,
+    /// Closed, its Token A and terms now belong to `escrow`
+    pub source: Pubkey,
+    pub amount: u64,
+    pub receive: u64,
 }
\ No newline at end of file
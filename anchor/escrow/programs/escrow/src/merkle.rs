@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::spl_associated_token_account::solana_program::hash::hashv;
+
+/// Leaf committed to the allowlist tree for a taker
+pub fn leaf(taker: &Pubkey) -> [u8; 32] {
+    hashv(&[taker.as_ref()]).to_bytes()
+}
+
+/// Verify `leaf` is in the tree with `root`.
+///
+/// Sibling pairs are hashed in sorted order, so proofs don't need to carry
+/// left/right flags.
+pub fn verify(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            hashv(&[&node, sibling]).to_bytes()
+        } else {
+            hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+
+    computed == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
+        if left <= right {
+            hashv(&[&left, &right]).to_bytes()
+        } else {
+            hashv(&[&right, &left]).to_bytes()
+        }
+    }
+
+    #[test]
+    fn single_taker_tree_needs_no_proof() {
+        let taker = Pubkey::new_unique();
+        assert!(verify(&[], &leaf(&taker), leaf(&taker)));
+        assert!(!verify(&[], &leaf(&taker), leaf(&Pubkey::new_unique())));
+    }
+
+    #[test]
+    fn proofs_verify_from_either_side() {
+        let takers: Vec<[u8; 32]> = (0..4).map(|_| leaf(&Pubkey::new_unique())).collect();
+        let (left, right) = (node(takers[0], takers[1]), node(takers[2], takers[3]));
+        let root = node(left, right);
+
+        assert!(verify(&[takers[1], right], &root, takers[0]));
+        assert!(verify(&[takers[0], right], &root, takers[1]));
+        assert!(verify(&[takers[3], left], &root, takers[2]));
+        assert!(verify(&[takers[2], left], &root, takers[3]));
+    }
+
+    #[test]
+    fn outsiders_and_bad_proofs_fail() {
+        let takers: Vec<[u8; 32]> = (0..2).map(|_| leaf(&Pubkey::new_unique())).collect();
+        let root = node(takers[0], takers[1]);
+
+        assert!(!verify(&[takers[1]], &root, leaf(&Pubkey::new_unique())));
+        assert!(!verify(&[], &root, takers[0]));
+        assert!(!verify(&[takers[0]], &root, takers[0]));
+    }
+}
@@ -0,0 +1,84 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::EscrowError;
+
+/// Decimals of `OraclePrice::price`
+pub const ORACLE_PRICE_DECIMALS: u32 = 9;
+
+/// Layout a reference price account must have to be read by `take`.
+///
+/// The taker picks which account to trust, so nothing is assumed about its
+/// owner beyond the data matching this layout and the escrow's mints.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct OraclePrice {
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    /// Token B base units per token A base unit, with `ORACLE_PRICE_DECIMALS` decimals
+    pub price: u64,
+}
+
+impl OraclePrice {
+    pub fn read(account: &AccountInfo, mint_a: &Pubkey, mint_b: &Pubkey) -> Result<Self> {
+        let data = account.try_borrow_data()?;
+        let oracle = Self::deserialize(&mut &data[..]).map_err(|_| EscrowError::InvalidOracle)?;
+
+        require_keys_eq!(oracle.mint_a, *mint_a, EscrowError::InvalidOracle);
+        require_keys_eq!(oracle.mint_b, *mint_b, EscrowError::InvalidOracle);
+        require!(oracle.price > 0, EscrowError::InvalidOracle);
+
+        Ok(oracle)
+    }
+
+    /// How far `receive` for `amount` strays from this price, in basis points
+    pub fn deviation_bps(&self, receive: u64, amount: u64) -> Result<u128> {
+        require!(amount > 0, EscrowError::InvalidAmount);
+
+        let implied = (receive as u128)
+            .checked_mul(10u128.pow(ORACLE_PRICE_DECIMALS))
+            .ok_or(EscrowError::Overflow)?
+            / amount as u128;
+        let reference = self.price as u128;
+
+        Ok(implied.abs_diff(reference) * 10_000 / reference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    fn quote(price: u64) -> OraclePrice {
+        OraclePrice { mint_a: Pubkey::new_unique(), mint_b: Pubkey::new_unique(), price }
+    }
+
+    #[test]
+    fn deviation_is_relative_to_the_oracle() {
+        // 1_000 Token B for 500 Token A is 2 per unit
+        assert_eq!(quote(2_000_000_000).deviation_bps(1_000, 500).unwrap(), 0);
+        assert_eq!(quote(1_000_000_000).deviation_bps(1_000, 500).unwrap(), 10_000);
+        assert_eq!(quote(4_000_000_000).deviation_bps(1_000, 500).unwrap(), 5_000);
+    }
+
+    #[test]
+    fn deviation_needs_an_amount() {
+        assert_eq!(err(quote(1).deviation_bps(1_000, 0)), anchor_error(EscrowError::InvalidAmount));
+    }
+
+    #[test]
+    fn reading_checks_the_mints_and_price() {
+        let oracle = quote(2_000_000_000);
+        let mut data = Vec::new();
+        oracle.serialize(&mut data).unwrap();
+        let account = account_info(Pubkey::new_unique(), TestAccount { lamports: 1, data, owner: Pubkey::new_unique() });
+
+        assert_eq!(OraclePrice::read(&account, &oracle.mint_a, &oracle.mint_b).unwrap().price, 2_000_000_000);
+        assert_eq!(err(OraclePrice::read(&account, &oracle.mint_b, &oracle.mint_a).map(|oracle| oracle.price)), anchor_error(EscrowError::InvalidOracle));
+
+        let zero = quote(0);
+        let mut data = Vec::new();
+        zero.serialize(&mut data).unwrap();
+        let account = account_info(Pubkey::new_unique(), TestAccount { lamports: 1, data, owner: Pubkey::new_unique() });
+        assert_eq!(err(OraclePrice::read(&account, &zero.mint_a, &zero.mint_b).map(|oracle| oracle.price)), anchor_error(EscrowError::InvalidOracle));
+    }
+}
@@ -0,0 +1,67 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::EscrowError;
+
+/// Decimals of the fixed-point prices taken by `receive_for_rate`
+pub const PRICE_DECIMALS: u32 = 6;
+
+/// Token B base units to ask for `amount_a` base units of token A.
+///
+/// `price_a_in_b` is the price of one whole token A in whole token B, with
+/// `PRICE_DECIMALS` decimals. The result is rounded down.
+pub fn receive_for_rate(amount_a: u64, decimals_a: u8, decimals_b: u8, price_a_in_b: u64) -> Result<u64> {
+    let scale_a = 10u128.checked_pow(decimals_a as u32).ok_or(EscrowError::Overflow)?;
+    let scale_b = 10u128.checked_pow(decimals_b as u32).ok_or(EscrowError::Overflow)?;
+    let price_scale = 10u128.pow(PRICE_DECIMALS);
+
+    let numerator = (amount_a as u128)
+        .checked_mul(price_a_in_b as u128)
+        .and_then(|value| value.checked_mul(scale_b))
+        .ok_or(EscrowError::Overflow)?;
+    let denominator = scale_a.checked_mul(price_scale).ok_or(EscrowError::Overflow)?;
+
+    u64::try_from(numerator / denominator).map_err(|_| EscrowError::Overflow.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1.5 whole token B per whole token A
+    const PRICE: u64 = 1_500_000;
+
+    #[test]
+    fn same_decimals() {
+        // 2 whole token A at 6 decimals
+        assert_eq!(receive_for_rate(2_000_000, 6, 6, PRICE).unwrap(), 3_000_000);
+        assert_eq!(receive_for_rate(0, 6, 6, PRICE).unwrap(), 0);
+    }
+
+    #[test]
+    fn more_decimals_on_b() {
+        // 2 whole token A at 6 decimals, token B at 9
+        assert_eq!(receive_for_rate(2_000_000, 6, 9, PRICE).unwrap(), 3_000_000_000);
+        // Token A without decimals
+        assert_eq!(receive_for_rate(2, 0, 9, PRICE).unwrap(), 3_000_000_000);
+    }
+
+    #[test]
+    fn fewer_decimals_on_b_rounds_down() {
+        // 2 whole token A at 9 decimals, token B at 2
+        assert_eq!(receive_for_rate(2_000_000_000, 9, 2, PRICE).unwrap(), 300);
+        // 0.019 token A is worth 0.0285 token B, which is 2 base units at 2 decimals
+        assert_eq!(receive_for_rate(19_000_000, 9, 2, PRICE).unwrap(), 2);
+        // Less than a base unit of token B
+        assert_eq!(receive_for_rate(1, 9, 2, PRICE).unwrap(), 0);
+    }
+
+    #[test]
+    fn overflow_is_an_error() {
+        // The result doesn't fit a u64
+        assert_eq!(receive_for_rate(u64::MAX, 0, 6, PRICE), Err(EscrowError::Overflow.into()));
+        // The intermediate product doesn't fit a u128
+        assert_eq!(receive_for_rate(u64::MAX, 6, 38, u64::MAX), Err(EscrowError::Overflow.into()));
+        // No mint has that many decimals, 10^39 doesn't fit a u128
+        assert_eq!(receive_for_rate(1, 39, 6, PRICE), Err(EscrowError::Overflow.into()));
+    }
+}
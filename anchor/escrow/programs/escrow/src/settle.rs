@@ -0,0 +1,57 @@
+//! Thin wrappers for programs settling escrows through CPI.
+//!
+//! Build against this crate with the `cpi` feature and fill in
+//! `cpi::accounts::Take`. Its fields map onto the account metas in this order:
+//!
+//! 0. `taker`, signer, writable
+//! 1. `maker`, writable, signer for `take_and_close`
+//! 2. `escrow`, writable
+//! 3. `mint_a`
+//! 4. `mint_b`
+//! 5. `vault`, writable
+//! 6. `taker_ata_a`, writable
+//! 7. `taker_ata_b`, writable
+//! 8. `maker_ata_b`, writable
+//! 9. `oracle`, optional, pass the escrow program id for none
+//! 10. `condition_program`, optional, pass the escrow program id for none
+//! 11. `stats`, writable, optional
+//! 12. `price_history`, writable, optional
+//! 13. `escrow_config`, optional, no treasury fee without it
+//! 14. `treasury_ata_b`, writable, optional, only needed when a fee is charged
+//! 15. `associated_token_program`
+//! 16. `token_program`
+//! 17. `system_program`
+//! 18. `maker_ata_a`, optional, only read by `take_and_close`
+//! 19. `burn_mint`, writable, optional, `mint_a` again, only needed when the escrow burns
+//!
+//! The taker still has to sign: either the user signs the outer transaction,
+//! or the calling program uses a PDA as the taker and signs for it with
+//! `CpiContext::new_with_signer`. Conditional escrows get whatever remaining
+//! accounts are set on the context.
+
+use anchor_lang::prelude::*;
+
+use crate::cpi;
+
+/// Fill `fill_amount` of Token B with slippage bounds, no allowlist proof or oracle check.
+/// `max_amount_b_in` has to leave room for the treasury fee paid on top.
+pub fn take_cpi<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, cpi::accounts::Take<'info>>,
+    fill_amount: u64,
+    min_amount_a_out: u64,
+    max_amount_b_in: u64,
+) -> Result<()> {
+    cpi::take(ctx, Vec::new(), fill_amount, min_amount_a_out, max_amount_b_in, None)
+}
+
+/// Fill with an allowlist proof and an optional oracle deviation check
+pub fn take_with_proof_cpi<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, cpi::accounts::Take<'info>>,
+    proof: Vec<[u8; 32]>,
+    fill_amount: u64,
+    min_amount_a_out: u64,
+    max_amount_b_in: u64,
+    max_deviation_bps: Option<u16>,
+) -> Result<()> {
+    cpi::take(ctx, proof, fill_amount, min_amount_a_out, max_amount_b_in, max_deviation_bps)
+}
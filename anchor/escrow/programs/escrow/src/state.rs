@@ -1,4 +1,18 @@
 use anchor_lang::prelude::*;
+
+use anchor_spl::token::spl_token;
+
+use crate::errors::EscrowError;
+use crate::merkle;
+use crate::oracle::ORACLE_PRICE_DECIMALS;
+
+/// Lamports an insured make pays into the insurance fund, until the config
+/// authority sets another premium
+pub const INSURANCE_PREMIUM: u64 = 10_000_000;
+
+/// Lamports `crank_refund` pays its caller out of the escrow's rent, until
+/// the config authority sets another bounty
+pub const CRANK_BOUNTY: u64 = 1_000_000;
  
 #[derive(InitSpace)]
 #[account(discriminator = 1)]
@@ -8,5 +22,494 @@ pub struct Escrow {
     pub mint_a: Pubkey,
     pub mint_b: Pubkey,
     pub receive: u64,
+    /// Token A (or lamports) still held for takers
+    pub amount: u64,
     pub bump: u8,
-}
\ No newline at end of file
+    pub insured: bool,
+    /// Mint B base units per base unit of mint A, 0 for fixed-receive escrows
+    pub price_per_unit: u64,
+    /// Merkle root of allowed takers, None means anyone can take
+    pub allowlist_root: Option<[u8; 32]>,
+    /// Unix timestamp after which the escrow can't be taken, 0 means never
+    pub deadline: i64,
+    /// What to do once the deadline passes, None means wait for a refund
+    pub relist: Option<RelistPolicy>,
+    /// Only this taker can fill the escrow, the default key means anyone
+    pub allowed_taker: Pubkey,
+    /// Program that has to approve every take, None means unconditional
+    pub condition_program: Option<Pubkey>,
+    /// Set once by `finalize`, the terms can't change anymore
+    pub locked: bool,
+    /// Unix timestamp of the make
+    pub created_at: i64,
+    /// Share of the Token A going out on every take that gets burned, in basis points
+    pub burn_bps: u16,
+    /// wSOL escrow funded from lamports, takers and the maker get native SOL back
+    pub unwrap_sol: bool,
+}
+
+/// Lets a keeper re-post an expired escrow at a lower price
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct RelistPolicy {
+    /// Price cut applied to `receive` on every relist, in basis points
+    pub adjust_bps: u16,
+    /// Seconds the relisted escrow stays open
+    pub period: i64,
+}
+ 
+impl Escrow {
+    /// First PDA seed, followed by the maker and the LE seed. The Pinocchio
+    /// escrow derives the same address, so this has to stay in step with it.
+    pub const SEED: &'static [u8] = b"escrow";
+
+    /// SOL-for-SOL escrows store the system program in place of both mints
+    pub fn is_native(&self) -> bool {
+        self.mint_a == system_program::ID && self.mint_b == system_program::ID
+    }
+
+    /// The maker deposited lamports, held directly by the escrow account
+    pub fn holds_sol(&self) -> bool {
+        self.mint_a == system_program::ID
+    }
+
+    /// Lamports a SOL escrow holds for takers, everything above its rent-exempt
+    /// minimum. The rent goes back to the maker when the escrow is closed.
+    pub fn lamport_deposit(lamports: u64, rent_exempt: u64) -> u64 {
+        lamports.saturating_sub(rent_exempt)
+    }
+
+    /// Priced escrows let the taker choose how much of mint A to buy
+    pub fn is_priced(&self) -> bool {
+        self.price_per_unit > 0
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.deadline != 0 && now > self.deadline
+    }
+
+    /// Checks every take variant runs on the taker before anything moves
+    pub fn check_taker(&self, taker: &Pubkey, proof: &[[u8; 32]], now: i64) -> Result<()> {
+        // Expired escrows can only be refunded
+        require!(!self.is_expired(now), EscrowError::Expired);
+
+        // Private escrows can only be filled by their counterparty
+        if self.allowed_taker != Pubkey::default() {
+            require_keys_eq!(*taker, self.allowed_taker, EscrowError::UnauthorizedTaker);
+        }
+
+        // Allowlisted escrows need a membership proof for the taker
+        if let Some(root) = self.allowlist_root {
+            require!(merkle::verify(proof, &root, merkle::leaf(taker)), EscrowError::NotAllowlisted);
+        }
+
+        Ok(())
+    }
+
+    /// Same pair and same rules for takers, only the size may differ
+    pub fn can_merge(&self, other: &Escrow) -> bool {
+        self.mint_a == other.mint_a
+            && self.mint_b == other.mint_b
+            && !self.is_priced()
+            && !other.is_priced()
+            && self.insured == other.insured
+            && self.allowlist_root == other.allowlist_root
+            && self.deadline == other.deadline
+            && self.relist == other.relist
+            && self.allowed_taker == other.allowed_taker
+            && self.condition_program == other.condition_program
+            && self.burn_bps == other.burn_bps
+            && self.unwrap_sol == other.unwrap_sol
+    }
+
+    /// Adds the terms of `source`, merged in, on top of these. Both sides add
+    /// up, so the price stays a blend of the two. Transfer-fee mints deliver
+    /// less than was sent, so `amount` never promises more than `vault_amount`.
+    pub fn absorb(&mut self, source: &Escrow, vault_amount: u64) -> Result<()> {
+        self.amount = self.amount.checked_add(source.amount).ok_or(EscrowError::Overflow)?.min(vault_amount);
+        self.receive = self.receive.checked_add(source.receive).ok_or(EscrowError::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Lamports and wSOL can't be burned, the native mint rejects it
+    pub fn can_burn(&self) -> bool {
+        !self.holds_sol() && !self.unwrap_sol && self.mint_a != spl_token::native_mint::ID
+    }
+
+    /// Part of `amount_out` burned on a take, rounded down in the taker's favour
+    pub fn burn_amount(&self, amount_out: u64) -> u64 {
+        (amount_out as u128 * self.burn_bps as u128 / 10_000) as u64
+    }
+}
+ 
+/// Negotiated two-sided swap, both parties fund their side before settlement
+#[derive(InitSpace)]
+#[account(discriminator = 2)]
+pub struct OtcDeal {
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    pub funded_a: bool,
+    pub funded_b: bool,
+    pub bump: u8,
+}
+ 
+impl OtcDeal {
+    pub fn is_funded(&self) -> bool {
+        self.funded_a && self.funded_b
+    }
+}
+ 
+/// Largest treasury fee, in basis points
+pub const MAX_FEE_BPS: u16 = 10_000;
+ 
+/// Protocol settings for the escrow frontend.
+///
+/// The take fee is taker-funded: the maker always gets the full fill amount
+/// of Token B and the taker pays `fee_bps` of it on top, to the treasury.
+#[derive(InitSpace)]
+#[account(discriminator = 4)]
+pub struct EscrowConfig {
+    pub authority: Pubkey,
+    /// Owner of the treasury ATAs the fee is paid to
+    pub treasury: Pubkey,
+    /// Fee on every take in basis points of the Token B filled, 0 disables it
+    pub fee_bps: u16,
+    pub bump: u8,
+    /// Lamports `crank_refund` pays its caller, taken from the escrow's rent
+    pub crank_bounty: u64,
+    /// Lamports an insured make pays into the insurance fund
+    pub insurance_premium: u64,
+}
+ 
+impl EscrowConfig {
+    pub const SEED: &'static [u8] = b"escrow_config";
+
+    /// Fee owed on top of `fill_amount`, rounded down in the taker's favour
+    pub fn take_fee(&self, fill_amount: u64) -> u64 {
+        (fill_amount as u128 * self.fee_bps as u128 / 10_000) as u64
+    }
+}
+ 
+/// Settlements a `PriceHistory` keeps, older ones get overwritten
+pub const MAX_PRICE_POINTS: usize = 32;
+ 
+/// Ring of settlement prices for one mint pair, written by every take that
+/// passes it.
+///
+/// Not a manipulation-resistant oracle: anyone can make an escrow and fill it
+/// themselves, so a handful of dust self-fills rewrites every slot at any
+/// price. Only read it as an indication of where the pair has been trading.
+#[derive(InitSpace)]
+#[account(discriminator = 5)]
+pub struct PriceHistory {
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    #[max_len(MAX_PRICE_POINTS)]
+    pub points: Vec<PricePoint>,
+    /// Slot the next point goes to once the ring is full
+    pub next: u8,
+    pub bump: u8,
+}
+
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PricePoint {
+    /// Token B base units per token A base unit, with `ORACLE_PRICE_DECIMALS` decimals
+    pub price: u64,
+    pub timestamp: i64,
+}
+ 
+impl PriceHistory {
+    pub const SEED: &'static [u8] = b"prices";
+
+    /// Price of a settlement that paid `amount_b` for `amount_a`, in the
+    /// `PricePoint` format. Saturates instead of failing the take on pairs
+    /// where a single token A is worth more than a u64 can express.
+    pub fn price(amount_b: u64, amount_a: u64) -> u64 {
+        let price = (amount_b as u128 * 10u128.pow(ORACLE_PRICE_DECIMALS)) / amount_a.max(1) as u128;
+        u64::try_from(price).unwrap_or(u64::MAX)
+    }
+
+    pub fn record(&mut self, price: u64, timestamp: i64) {
+        let point = PricePoint { price, timestamp };
+        if self.points.len() < MAX_PRICE_POINTS {
+            self.points.push(point);
+        } else {
+            self.points[self.next as usize] = point;
+            self.next = ((self.next as usize + 1) % MAX_PRICE_POINTS) as u8;
+        }
+    }
+
+    /// Time-weighted average over the last `window` seconds, 0 means everything
+    /// stored. Each price holds until the next settlement, the last one until `now`.
+    pub fn twap(&self, now: i64, window: i64) -> Option<u64> {
+        // Oldest first, the ring starts at `next` once it has wrapped
+        let (newer, older) = self.points.split_at(self.next as usize);
+        let ordered: Vec<&PricePoint> = older.iter().chain(newer).collect();
+
+        let since = if window > 0 { now.saturating_sub(window) } else { i64::MIN };
+        let mut weighted: u128 = 0;
+        let mut elapsed: u128 = 0;
+        for (index, point) in ordered.iter().enumerate() {
+            let end = ordered.get(index + 1).map_or(now, |next| next.timestamp);
+            let start = point.timestamp.max(since);
+            if end > start {
+                let duration = (end - start) as u128;
+                weighted = weighted.checked_add((point.price as u128).checked_mul(duration)?)?;
+                elapsed += duration;
+            }
+        }
+
+        // Everything settled this very second, fall back to the latest price
+        if elapsed == 0 {
+            return ordered.last().map(|point| point.price);
+        }
+        u64::try_from(weighted / elapsed).ok()
+    }
+}
+ 
+/// Mints a `Stats` account keeps volume for, later mints aren't tracked
+pub const MAX_TRACKED_MINTS: usize = 8;
+ 
+/// Lifetime protocol counters, every counter saturates instead of overflowing.
+///
+/// One account for the whole program, so every instruction takes it as an
+/// optional account: clients that don't care about the counters leave it out
+/// and don't serialise their transactions behind everyone else's.
+#[derive(InitSpace)]
+#[account(discriminator = 3)]
+pub struct Stats {
+    pub made: u64,
+    /// Escrows filled to completion
+    pub taken: u64,
+    pub refunded: u64,
+    /// Amount swapped through takes, per mint
+    #[max_len(MAX_TRACKED_MINTS)]
+    pub volumes: Vec<MintVolume>,
+    pub bump: u8,
+    /// Escrows folded into another one by `merge`
+    pub merged: u64,
+}
+
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct MintVolume {
+    pub mint: Pubkey,
+    pub volume: u64,
+}
+ 
+impl Stats {
+    pub const SEED: &'static [u8] = b"stats";
+
+    pub fn record_make(&mut self) {
+        self.made = self.made.saturating_add(1);
+    }
+
+    /// `completed` once the escrow is filled to the end
+    pub fn record_take(&mut self, mint_a: Pubkey, amount_a: u64, mint_b: Pubkey, amount_b: u64, completed: bool) {
+        if completed {
+            self.taken = self.taken.saturating_add(1);
+        }
+        self.record_volume(mint_a, amount_a);
+        self.record_volume(mint_b, amount_b);
+    }
+
+    pub fn record_refund(&mut self) {
+        self.refunded = self.refunded.saturating_add(1);
+    }
+
+    pub fn record_merge(&mut self) {
+        self.merged = self.merged.saturating_add(1);
+    }
+
+    pub fn record_volume(&mut self, mint: Pubkey, amount: u64) {
+        if let Some(entry) = self.volumes.iter_mut().find(|entry| entry.mint == mint) {
+            entry.volume = entry.volume.saturating_add(amount);
+        } else if self.volumes.len() < MAX_TRACKED_MINTS {
+            self.volumes.push(MintVolume { mint, volume: amount });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{anchor_error, err};
+
+    fn escrow() -> Escrow {
+        Escrow {
+            seed: 1,
+            maker: Pubkey::new_unique(),
+            mint_a: Pubkey::new_unique(),
+            mint_b: Pubkey::new_unique(),
+            receive: 1_000,
+            amount: 500,
+            bump: 255,
+            insured: false,
+            price_per_unit: 0,
+            allowlist_root: None,
+            deadline: 0,
+            relist: None,
+            allowed_taker: Pubkey::default(),
+            condition_program: None,
+            locked: false,
+            created_at: 0,
+            burn_bps: 0,
+            unwrap_sol: false,
+        }
+    }
+
+    fn sol_escrow() -> Escrow {
+        Escrow {
+            mint_a: system_program::ID,
+            mint_b: system_program::ID,
+            ..escrow()
+        }
+    }
+
+    /// Escrow PDA for maker `[7; 32]` and seed 42. The Pinocchio escrow's
+    /// state tests derive the same address and compare it to these bytes.
+    const SHARED_ESCROW_PDA: [u8; 32] = [
+        0x0a, 0xa2, 0x7e, 0x63, 0x88, 0x09, 0xb0, 0x41,
+        0x8d, 0x53, 0x2a, 0x8f, 0x4e, 0xfa, 0x2e, 0x4d,
+        0x99, 0xa8, 0x42, 0xef, 0x68, 0x0c, 0xfe, 0x9b,
+        0x57, 0x24, 0x48, 0x62, 0xda, 0x93, 0xac, 0xce,
+    ];
+
+    #[test]
+    fn escrow_pda_matches_the_pinocchio_escrow() {
+        let maker = Pubkey::new_from_array([7; 32]);
+        let (escrow, _) = Pubkey::find_program_address(&[Escrow::SEED, maker.as_ref(), &42u64.to_le_bytes()], &crate::ID);
+
+        assert_eq!(escrow.to_bytes(), SHARED_ESCROW_PDA);
+    }
+
+    fn history() -> PriceHistory {
+        PriceHistory { mint_a: Pubkey::new_unique(), mint_b: Pubkey::new_unique(), points: Vec::new(), next: 0, bump: 255 }
+    }
+
+    #[test]
+    fn settlement_price_saturates() {
+        // 2 Token B per Token A, with 9 decimals
+        assert_eq!(PriceHistory::price(1_000, 500), 2_000_000_000);
+        // A single base unit of Token A worth more than ~1.8e10 base units of B
+        assert_eq!(PriceHistory::price(u64::MAX, 1), u64::MAX);
+        assert_eq!(PriceHistory::price(20_000_000_000, 1), u64::MAX);
+    }
+
+    #[test]
+    fn price_history_overwrites_the_oldest_point() {
+        let mut history = history();
+        for index in 0..MAX_PRICE_POINTS as i64 + 2 {
+            history.record(index as u64, index);
+        }
+
+        assert_eq!(history.points.len(), MAX_PRICE_POINTS);
+        assert_eq!(history.next, 2);
+        assert_eq!(history.points[0].price, MAX_PRICE_POINTS as u64);
+        assert_eq!(history.points[1].price, MAX_PRICE_POINTS as u64 + 1);
+        assert_eq!(history.points[2].price, 2);
+    }
+
+    #[test]
+    fn twap_weights_prices_by_how_long_they_held() {
+        let mut history = history();
+        assert_eq!(history.twap(100, 0), None);
+
+        // 100 for 30s, then 200 for 10s
+        history.record(100, 0);
+        history.record(200, 30);
+        assert_eq!(history.twap(40, 0), Some(125));
+
+        // Only the last 20s: 100 for 10s, 200 for 10s
+        assert_eq!(history.twap(40, 20), Some(150));
+
+        // Everything settled this second, the latest price stands
+        let mut same_second = self::history();
+        same_second.record(100, 50);
+        same_second.record(300, 50);
+        assert_eq!(same_second.twap(50, 0), Some(300));
+    }
+
+    #[test]
+    fn twap_reads_a_wrapped_ring_oldest_first() {
+        let mut history = history();
+        for index in 0..MAX_PRICE_POINTS as i64 + 1 {
+            // Every point holds for 10s, only the newest one differs
+            let price = if index == MAX_PRICE_POINTS as i64 { 1_100 } else { 100 };
+            history.record(price, index * 10);
+        }
+
+        // The newest point is at slot 0 but still counts last, up to `now`
+        let now = MAX_PRICE_POINTS as i64 * 10 + 10;
+        assert_eq!(history.twap(now, 20), Some(600));
+    }
+
+    #[test]
+    fn private_escrows_only_take_their_counterparty() {
+        let taker = Pubkey::new_unique();
+        let private = Escrow { allowed_taker: taker, ..escrow() };
+
+        private.check_taker(&taker, &[], 0).unwrap();
+        assert_eq!(err(private.check_taker(&Pubkey::new_unique(), &[], 0)), anchor_error(EscrowError::UnauthorizedTaker));
+
+        // Anyone can take a public one
+        escrow().check_taker(&Pubkey::new_unique(), &[], 0).unwrap();
+    }
+
+    #[test]
+    fn deadline_is_inclusive_and_zero_never_expires() {
+        let open_ended = escrow();
+        assert!(!open_ended.is_expired(i64::MAX));
+
+        let expiring = Escrow { deadline: 100, ..escrow() };
+        assert!(!expiring.is_expired(100));
+        assert!(expiring.is_expired(101));
+        assert_eq!(err(expiring.check_taker(&Pubkey::new_unique(), &[], 101)), anchor_error(EscrowError::Expired));
+    }
+
+    #[test]
+    fn stats_count_completed_takes_and_volume() {
+        let mut stats = Stats { made: 0, taken: 0, refunded: 0, volumes: Vec::new(), bump: 255, merged: 0 };
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        stats.record_take(mint_a, 100, mint_b, 200, false);
+        stats.record_take(mint_a, 50, mint_b, 100, true);
+        stats.record_refund();
+
+        assert_eq!((stats.taken, stats.refunded), (1, 1));
+        assert_eq!(stats.volumes.iter().map(|entry| (entry.mint, entry.volume)).collect::<Vec<_>>(), [(mint_a, 150), (mint_b, 300)]);
+    }
+
+    #[test]
+    fn sol_escrow_deposit_sits_on_top_of_rent() {
+        let rent = Rent::default().minimum_balance(Escrow::INIT_SPACE + Escrow::DISCRIMINATOR.len());
+
+        // make: rent paid by init, the deposit transferred on top
+        let lamports = rent + 2_000_000;
+        assert_eq!(Escrow::lamport_deposit(lamports, rent), 2_000_000);
+
+        // take: after the deposit left, only the rent is left for the maker
+        assert_eq!(Escrow::lamport_deposit(rent, rent), 0);
+        assert_eq!(Escrow::lamport_deposit(rent - 1, rent), 0);
+    }
+
+    #[test]
+    fn sol_for_sol_escrows_are_native() {
+        let native = sol_escrow();
+        assert!(native.is_native());
+        assert!(native.holds_sol());
+
+        // SOL for a token can be refunded as SOL but not taken through take_sol
+        let for_token = Escrow { mint_b: Pubkey::new_unique(), ..sol_escrow() };
+        assert!(!for_token.is_native());
+        assert!(for_token.holds_sol());
+
+        let tokens = escrow();
+        assert!(!tokens.is_native());
+        assert!(!tokens.holds_sol());
+    }
+}
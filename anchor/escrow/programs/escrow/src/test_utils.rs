@@ -0,0 +1,469 @@
+//! In-process runtime for instruction tests.
+//!
+//! Instructions run natively against accounts serialized the way the loader
+//! does it, so Anchor's constraints and `exit` behave as on-chain. CPIs can't
+//! run off-chain, so instructions that make any are only driven through here
+//! up to the checks in front of their first CPI, against accounts set up directly.
+
+#![allow(dead_code)]
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Once;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    entrypoint::{deserialize, MAX_PERMITTED_DATA_INCREASE},
+    instruction::Instruction,
+    program_pack::Pack,
+};
+use anchor_lang::InstructionData;
+use anchor_spl::associated_token::{get_associated_token_address, spl_associated_token_account::solana_program};
+use anchor_spl::token::spl_token;
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+
+use crate::state::{Escrow, EscrowConfig, OtcDeal, PriceHistory, Stats};
+
+thread_local! {
+    static NOW: Cell<i64> = const { Cell::new(0) };
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock { unix_timestamp: NOW.with(Cell::get), ..Clock::default() };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TestAccount {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+}
+
+/// Accounts and a clock, transactions either apply as a whole or not at all
+pub struct Svm {
+    accounts: HashMap<Pubkey, TestAccount>,
+    pub now: i64,
+}
+
+impl Svm {
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(Stubs));
+        });
+
+        Self { accounts: HashMap::new(), now: 1_700_000_000 }
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: TestAccount) {
+        self.accounts.insert(key, account);
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.accounts.get(key).map_or(0, |account| account.lamports)
+    }
+
+    pub fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        self.accounts.entry(*key).or_default().lamports += lamports;
+    }
+
+    /// Funded system account
+    pub fn wallet(&mut self) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.set_account(key, TestAccount { lamports: 10_000_000_000, ..TestAccount::default() });
+        key
+    }
+
+    fn set_packed<T: Pack>(&mut self, key: Pubkey, state: T) {
+        let mut data = vec![0; T::LEN];
+        state.pack_into_slice(&mut data);
+        let lamports = Rent::default().minimum_balance(T::LEN);
+        self.set_account(key, TestAccount { lamports, data, owner: spl_token::ID });
+    }
+
+    pub fn add_mint(&mut self, decimals: u8) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.set_packed(
+            key,
+            spl_token::state::Mint {
+                mint_authority: Some(Pubkey::new_unique()).into(),
+                supply: u64::MAX / 2,
+                decimals,
+                is_initialized: true,
+                freeze_authority: None.into(),
+            },
+        );
+        key
+    }
+
+    pub fn set_token_account(&mut self, key: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) {
+        self.set_packed(
+            key,
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                state: spl_token::state::AccountState::Initialized,
+                ..spl_token::state::Account::default()
+            },
+        );
+    }
+
+    /// Program account holding `state`, with `space` bytes of data
+    pub fn set_state<T: AccountSerialize>(&mut self, key: Pubkey, state: &T, space: usize) {
+        let mut data = Vec::with_capacity(space);
+        state.try_serialize(&mut data).unwrap();
+        data.resize(space, 0);
+        let lamports = Rent::default().minimum_balance(space);
+        self.set_account(key, TestAccount { lamports, data, owner: crate::ID });
+    }
+
+    pub fn state<T: AccountDeserialize>(&self, key: &Pubkey) -> T {
+        let account = self.accounts.get(key).expect("program account");
+        T::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    /// Runs the instructions as one transaction. Nothing is written back
+    /// unless all of them succeed.
+    pub fn process(&mut self, instructions: &[Instruction]) -> std::result::Result<(), ProgramError> {
+        NOW.with(|now| now.set(self.now));
+        let mut accounts = self.accounts.clone();
+        for instruction in instructions {
+            execute(&mut accounts, instruction)?;
+        }
+
+        accounts.retain(|_, account| account.lamports > 0);
+        self.accounts = accounts;
+        Ok(())
+    }
+}
+
+/// Programs an instruction can name, passed as executable accounts
+fn is_program(key: &Pubkey) -> bool {
+    [crate::ID, system_program::ID, spl_token::ID, anchor_spl::associated_token::ID].contains(key)
+}
+
+/// Serializes the instruction's accounts like the loader, runs it and reads
+/// the writable accounts back
+fn execute(accounts: &mut HashMap<Pubkey, TestAccount>, instruction: &Instruction) -> std::result::Result<(), ProgramError> {
+    let mut keys: Vec<Pubkey> = Vec::new();
+    let mut input: Vec<u8> = Vec::new();
+    input.extend_from_slice(&(instruction.accounts.len() as u64).to_le_bytes());
+    for meta in &instruction.accounts {
+        if let Some(position) = keys.iter().position(|key| *key == meta.pubkey) {
+            input.push(position as u8);
+            input.extend_from_slice(&[0; 7]);
+            continue;
+        }
+        keys.push(meta.pubkey);
+
+        let account = accounts.get(&meta.pubkey).cloned().unwrap_or_default();
+        let (is_signer, is_writable) = instruction
+            .accounts
+            .iter()
+            .filter(|other| other.pubkey == meta.pubkey)
+            .fold((false, false), |(signer, writable), other| (signer || other.is_signer, writable || other.is_writable));
+        let executable = is_program(&meta.pubkey);
+        input.extend_from_slice(&[u8::MAX, is_signer as u8, is_writable as u8, executable as u8, 0, 0, 0, 0]);
+        input.extend_from_slice(meta.pubkey.as_ref());
+        input.extend_from_slice(account.owner.as_ref());
+        input.extend_from_slice(&account.lamports.to_le_bytes());
+        input.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        input.extend_from_slice(&account.data);
+        input.resize(input.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        input.resize(input.len().next_multiple_of(8), 0);
+        input.extend_from_slice(&u64::MAX.to_le_bytes());
+    }
+    input.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+    input.extend_from_slice(&instruction.data);
+    input.extend_from_slice(instruction.program_id.as_ref());
+
+    // u64 words keep the buffer aligned like the loader's
+    let buffer = Box::leak(vec![0u64; input.len().div_ceil(8)].into_boxed_slice());
+    unsafe { std::ptr::copy_nonoverlapping(input.as_ptr(), buffer.as_mut_ptr() as *mut u8, input.len()) };
+    let (program_id, infos, data) = unsafe { deserialize(buffer.as_mut_ptr() as *mut u8) };
+    let infos = Box::leak(infos.into_boxed_slice());
+
+    crate::entry(program_id, infos, data)?;
+
+    for info in infos.iter().filter(|info| info.is_writable) {
+        accounts.insert(
+            *info.key,
+            TestAccount { lamports: info.lamports(), data: info.data.borrow().to_vec(), owner: *info.owner },
+        );
+    }
+    Ok(())
+}
+
+pub fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &crate::ID).0
+}
+
+pub fn pda_bump(seeds: &[&[u8]]) -> u8 {
+    Pubkey::find_program_address(seeds, &crate::ID).1
+}
+
+pub fn ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction { program_id: crate::ID, accounts: accounts.to_account_metas(None), data: data.data() }
+}
+
+/// Anchor error code as the runtime reports it
+pub fn anchor_error(code: impl Into<u32>) -> ProgramError {
+    ProgramError::Custom(code.into())
+}
+
+/// Error of a helper call, in the form the runtime reports it
+pub fn err<T: std::fmt::Debug>(result: Result<T>) -> ProgramError {
+    result.unwrap_err().into()
+}
+
+/// Account handed to a helper directly, outside of any instruction
+pub fn account_info(key: Pubkey, account: TestAccount) -> AccountInfo<'static> {
+    AccountInfo::new(
+        Box::leak(Box::new(key)),
+        false,
+        true,
+        Box::leak(Box::new(account.lamports)),
+        Box::leak(account.data.into_boxed_slice()),
+        Box::leak(Box::new(account.owner)),
+        false,
+        0,
+    )
+}
+
+/// Escrow of `amount` Token A for `receive` Token B as `make` leaves it,
+/// with the taker holding enough Token B and every account a take needs
+pub struct Market {
+    pub svm: Svm,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub escrow: Pubkey,
+    pub vault: Pubkey,
+    pub config: Pubkey,
+    pub treasury: Pubkey,
+}
+
+impl Market {
+    pub fn new(amount: u64, receive: u64) -> Self {
+        let mut svm = Svm::new();
+        let maker = svm.wallet();
+        let taker = svm.wallet();
+        let mint_a = svm.add_mint(6);
+        let mint_b = svm.add_mint(6);
+        let (escrow, bump) = Pubkey::find_program_address(&[Escrow::SEED, maker.as_ref(), &1u64.to_le_bytes()], &crate::ID);
+        let mut market = Self {
+            maker,
+            taker,
+            mint_a,
+            mint_b,
+            escrow,
+            vault: get_associated_token_address(&escrow, &mint_a),
+            config: pda(&[EscrowConfig::SEED]),
+            treasury: Pubkey::new_unique(),
+            svm,
+        };
+
+        market.set_escrow(Escrow {
+            seed: 1,
+            maker,
+            mint_a,
+            mint_b,
+            receive,
+            amount,
+            bump,
+            insured: false,
+            price_per_unit: 0,
+            allowlist_root: None,
+            deadline: 0,
+            relist: None,
+            allowed_taker: Pubkey::default(),
+            condition_program: None,
+            locked: false,
+            created_at: market.svm.now,
+            burn_bps: 0,
+            unwrap_sol: false,
+        });
+        market.svm.set_token_account(market.vault, mint_a, escrow, amount);
+        for (owner, mint, balance) in [(taker, mint_a, 0), (taker, mint_b, u64::MAX / 4), (maker, mint_a, 0), (maker, mint_b, 0)] {
+            market.svm.set_token_account(get_associated_token_address(&owner, &mint), mint, owner, balance);
+        }
+        market.set_config(EscrowConfig {
+            authority: Pubkey::new_unique(),
+            treasury: market.treasury,
+            fee_bps: 0,
+            bump: pda_bump(&[EscrowConfig::SEED]),
+            crank_bounty: 0,
+            insurance_premium: 0,
+        });
+        market.svm.set_token_account(get_associated_token_address(&market.treasury, &mint_b), mint_b, market.treasury, 0);
+        market.svm.set_state(
+            market.stats_key(),
+            &Stats { made: 1, taken: 0, refunded: 0, volumes: Vec::new(), bump: pda_bump(&[Stats::SEED]), merged: 0 },
+            Stats::INIT_SPACE + Stats::DISCRIMINATOR.len(),
+        );
+        market.svm.set_state(
+            market.price_history_key(),
+            &PriceHistory { mint_a, mint_b, points: Vec::new(), next: 0, bump: pda_bump(&[PriceHistory::SEED, mint_a.as_ref(), mint_b.as_ref()]) },
+            PriceHistory::INIT_SPACE + PriceHistory::DISCRIMINATOR.len(),
+        );
+        market
+    }
+
+    pub fn escrow(&self) -> Escrow {
+        self.svm.state(&self.escrow)
+    }
+
+    pub fn set_escrow(&mut self, escrow: Escrow) {
+        self.svm.set_state(self.escrow, &escrow, Escrow::INIT_SPACE + Escrow::DISCRIMINATOR.len());
+    }
+
+    /// Applies `change` to the stored escrow, like a maker instruction would
+    pub fn edit_escrow(&mut self, change: impl FnOnce(&mut Escrow)) {
+        let mut escrow = self.escrow();
+        change(&mut escrow);
+        self.set_escrow(escrow);
+    }
+
+    pub fn config(&self) -> EscrowConfig {
+        self.svm.state(&self.config)
+    }
+
+    pub fn set_config(&mut self, config: EscrowConfig) {
+        self.svm.set_state(self.config, &config, EscrowConfig::INIT_SPACE + EscrowConfig::DISCRIMINATOR.len());
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.svm.state(&self.stats_key())
+    }
+
+    pub fn stats_key(&self) -> Pubkey {
+        pda(&[Stats::SEED])
+    }
+
+    pub fn price_history_key(&self) -> Pubkey {
+        pda(&[PriceHistory::SEED, self.mint_a.as_ref(), self.mint_b.as_ref()])
+    }
+
+    pub fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address(owner, mint)
+    }
+
+    pub fn take_accounts(&self) -> crate::accounts::Take {
+        crate::accounts::Take {
+            taker: self.taker,
+            maker: self.maker,
+            escrow: self.escrow,
+            mint_a: self.mint_a,
+            mint_b: self.mint_b,
+            vault: self.vault,
+            taker_ata_a: self.ata(&self.taker, &self.mint_a),
+            taker_ata_b: self.ata(&self.taker, &self.mint_b),
+            maker_ata_b: self.ata(&self.maker, &self.mint_b),
+            oracle: None,
+            condition_program: None,
+            stats: Some(self.stats_key()),
+            price_history: Some(self.price_history_key()),
+            escrow_config: Some(self.config),
+            treasury_ata_b: Some(self.ata(&self.treasury, &self.mint_b)),
+            associated_token_program: anchor_spl::associated_token::ID,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            maker_ata_a: None,
+            burn_mint: None,
+        }
+    }
+
+    pub fn take_ix(&self, fill_amount: u64) -> Instruction {
+        ix(
+            self.take_accounts(),
+            crate::instruction::Take { proof: Vec::new(), fill_amount, min_amount_a_out: 0, max_amount_b_in: u64::MAX, max_deviation_bps: None },
+        )
+    }
+
+    pub fn update_accounts(&self) -> crate::accounts::Update {
+        crate::accounts::Update { maker: self.maker, escrow: self.escrow }
+    }
+
+    /// Accounts of `timeout_refund`, `crank_refund` takes the same plus the config
+    pub fn timeout_refund_accounts(&self, caller: Pubkey) -> crate::accounts::TimeoutRefund {
+        crate::accounts::TimeoutRefund {
+            caller,
+            maker: self.maker,
+            escrow: self.escrow,
+            mint_a: self.mint_a,
+            vault: self.vault,
+            maker_ata_a: self.ata(&self.maker, &self.mint_a),
+            stats: Some(self.stats_key()),
+            associated_token_program: anchor_spl::associated_token::ID,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+        }
+    }
+
+    /// Runs a config setter signed by `authority`
+    pub fn admin_as(&mut self, authority: Pubkey, data: impl InstructionData) -> std::result::Result<(), ProgramError> {
+        let set = ix(crate::accounts::SetFee { authority, escrow_config: self.config }, data);
+        self.svm.process(&[set])
+    }
+}
+
+/// OTC deal between a market's maker and taker, with only the maker's side
+/// funded and both vaults in place
+pub struct Otc {
+    pub market: Market,
+    pub deal: Pubkey,
+}
+
+impl Otc {
+    pub fn new(amount_a: u64, amount_b: u64) -> Self {
+        let market = Market::new(0, 1);
+        let (deal, bump) = Pubkey::find_program_address(&[b"otc", market.maker.as_ref(), &1u64.to_le_bytes()], &crate::ID);
+        let mut otc = Self { market, deal };
+
+        otc.set_deal(OtcDeal {
+            seed: 1,
+            maker: otc.market.maker,
+            taker: otc.market.taker,
+            mint_a: otc.market.mint_a,
+            mint_b: otc.market.mint_b,
+            amount_a,
+            amount_b,
+            funded_a: true,
+            funded_b: false,
+            bump,
+        });
+        let (mint_a, mint_b) = (otc.market.mint_a, otc.market.mint_b);
+        otc.market.svm.set_token_account(otc.vault(&mint_a), mint_a, deal, amount_a);
+        otc.market.svm.set_token_account(otc.vault(&mint_b), mint_b, deal, 0);
+        otc
+    }
+
+    pub fn deal(&self) -> OtcDeal {
+        self.market.svm.state(&self.deal)
+    }
+
+    pub fn set_deal(&mut self, deal: OtcDeal) {
+        self.market.svm.set_state(self.deal, &deal, OtcDeal::INIT_SPACE + OtcDeal::DISCRIMINATOR.len());
+    }
+
+    pub fn vault(&self, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address(&self.deal, mint)
+    }
+}
@@ -17,6 +17,10 @@ pub enum ProtocolError {
     InvalidProgram,
     #[msg("Invalid borrower ATA")]
     InvalidBorrowerAta,
+    #[msg("Borrower ATA is not owned by the borrower")]
+    InvalidBorrowerAtaOwner,
+    #[msg("Borrower ATA mint does not match the loan mint")]
+    InvalidBorrowerAtaMint,
     #[msg("Invalid protocol ATA")]
     InvalidProtocolAta,
     #[msg("Missing repay instruction")]
@@ -25,4 +29,64 @@ pub enum ProtocolError {
     MissingBorrowIx,
     #[msg("Overflow")]
     Overflow,
+    #[msg("Invalid protocol config")]
+    InvalidConfig,
+    #[msg("Too many loans in this transaction")]
+    TooManyLoans,
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Protocol is paused")]
+    Paused,
+    #[msg("Withdrawal would leave the vault below the minimum liquidity")]
+    BelowMinLiquidity,
+    #[msg("Borrow amount exceeds the protocol maximum")]
+    MaxBorrowExceeded,
+    #[msg("Borrow amount too large")]
+    AmountTooLarge,
+    #[msg("Duplicate borrow from the same protocol ATA")]
+    DuplicateBorrow,
+    #[msg("Borrow would exceed the global exposure cap")]
+    GlobalExposureExceeded,
+    #[msg("A bond is required to borrow")]
+    BondRequired,
+    #[msg("Bond is below the protocol minimum")]
+    InsufficientBond,
+    #[msg("Bond can't be slashed")]
+    NotSlashable,
+    #[msg("Bond is locked while it can be slashed")]
+    BondLocked,
+    #[msg("Signer is not the pending authority")]
+    NotPendingAuthority,
+    #[msg("No rebate to claim yet")]
+    NoRebate,
+    #[msg("Fee waiver list is full")]
+    WaiverListFull,
+    #[msg("Borrower is not on the fee waiver list")]
+    WaiverNotFound,
+    #[msg("Not the configured DEX program")]
+    InvalidDexProgram,
+    #[msg("Swap route is missing accounts")]
+    InvalidRoute,
+    #[msg("Swap route didn't cover the loan fee")]
+    UnprofitableRoute,
+    #[msg("Not enough liquidity shares")]
+    InsufficientShares,
+    #[msg("A price feed is required for the USD fee")]
+    MissingOracle,
+    #[msg("Invalid price feed")]
+    InvalidOracle,
+    #[msg("Price feed is too old")]
+    StaleOracle,
+    #[msg("Fee tiers must be at most four, ascending, and at most 10000 bps")]
+    InvalidFeeTiers,
+    #[msg("Invalid loan record account")]
+    InvalidLoanRecord,
+    #[msg("Unsupported loan record version")]
+    UnsupportedLoanVersion,
+    #[msg("Account already has the current layout")]
+    AlreadyMigrated,
+    #[msg("Bond unlock wasn't requested or is still cooling down")]
+    BondCoolingDown,
+    #[msg("Bond is being unlocked and can't back loans")]
+    BondUnlocking,
 }
\ No newline at end of file
@@ -0,0 +1,214 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::ProtocolError;
+use crate::oracle::PriceFeed;
+use crate::state::Config;
+
+/// Fee charged on every loan, in basis points, unless a fee tier applies
+pub const FEE_BPS: u64 = 500;
+
+/// Largest principal for which principal + fee still fits in a u64
+pub const MAX_BORROW: u64 = ((u64::MAX as u128 * 10_000) / (10_000 + FEE_BPS as u128)) as u64;
+
+/// Breakdown of what a borrower owes, returned by `repay`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RepayResult {
+    pub principal: u64,
+    pub fee: u64,
+    pub total: u64,
+}
+
+/// Repayment for a fee-waived borrower, just the principal
+pub fn waived_repayment(principal: u64) -> RepayResult {
+    RepayResult {
+        principal,
+        fee: 0,
+        total: principal,
+    }
+}
+
+/// Repayment with a fee fixed up front, e.g. converted from USD, still floored at `min_fee`
+pub fn flat_repayment(principal: u64, fee: u64, min_fee: u64) -> Result<RepayResult> {
+    let fee = fee.max(min_fee);
+    let total = principal.checked_add(fee).ok_or(ProtocolError::Overflow)?;
+
+    Ok(RepayResult {
+        principal,
+        fee,
+        total,
+    })
+}
+
+/// Compute the fee and total repayment for a borrowed amount at `fee_bps`.
+///
+/// The fee rounds up so small loans can't dodge it, and never drops below `min_fee`.
+pub fn compute_repayment(principal: u64, fee_bps: u64, min_fee: u64) -> Result<RepayResult> {
+    let fee = (principal as u128).checked_mul(fee_bps as u128).ok_or(ProtocolError::Overflow)?.div_ceil(10_000);
+    let fee = u64::try_from(fee).map_err(|_| ProtocolError::Overflow)?.max(min_fee);
+    let total = principal.checked_add(fee).ok_or(ProtocolError::Overflow)?;
+
+    Ok(RepayResult {
+        principal,
+        fee,
+        total,
+    })
+}
+
+/// Repayment at the bps tiers of the current config
+pub fn bps_repayment(config: &Config, borrow_amount: u64) -> Result<RepayResult> {
+    compute_repayment(borrow_amount, config.fee_bps(borrow_amount), config.min_fee)
+}
+
+/// Repayment for a loan at the current config, the one fee schedule `borrow`,
+/// `arb` and `quote_fee` all go through.
+///
+/// Waived borrowers pay no fee, a USD fee is converted at the price in
+/// `price_feed`, otherwise the bps tiers apply.
+pub fn loan_repayment(
+    config: &Config,
+    principal: u64,
+    waived: bool,
+    price_feed: Option<&AccountInfo>,
+    mint: &Pubkey,
+    decimals: u8,
+    now: i64,
+) -> Result<RepayResult> {
+    if waived {
+        return Ok(waived_repayment(principal));
+    }
+
+    if config.usd_fee > 0 {
+        // flat USD fee, converted at the current price of the mint
+        let feed = PriceFeed::read(price_feed.ok_or(ProtocolError::MissingOracle)?, &config.fee_oracle, mint)?;
+        feed.check_fresh(now, config.max_oracle_age)?;
+        let fee = feed.usd_to_tokens(config.usd_fee, decimals)?;
+        return flat_repayment(principal, fee, config.min_fee);
+    }
+
+    bps_repayment(config, principal)
+}
+
+/// Total a borrower without a waiver owes on `borrow_amount` at the current
+/// config, for front-ends to show before the user signs.
+///
+/// Goes through `loan_repayment` like `borrow`, which fixes the fee `repay`
+/// then charges. A USD fee needs the price feed, quote it with `quote_fee`.
+pub fn quote_repay(config: &Config, borrow_amount: u64) -> Result<u64> {
+    // the mint, its decimals and the clock are only read along with a feed
+    let result = loan_repayment(config, borrow_amount, false, None, &Pubkey::default(), 0, 0)?;
+
+    Ok(result.total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::LoanRecord;
+    use crate::test_utils::*;
+
+    #[test]
+    fn repay_result_breaks_the_total_down() {
+        let result = compute_repayment(10_000, FEE_BPS, 0).unwrap();
+        assert_eq!(result, RepayResult { principal: 10_000, fee: 500, total: 10_500 });
+
+        // what clients decode from the return data
+        let encoded = result.try_to_vec().unwrap();
+        assert_eq!(RepayResult::try_from_slice(&encoded).unwrap(), result);
+
+        assert_eq!(waived_repayment(10_000), RepayResult { principal: 10_000, fee: 0, total: 10_000 });
+    }
+
+    #[test]
+    fn repay_charges_the_fee_fixed_at_borrow() {
+        let mut config = default_config(Pubkey::new_unique());
+        let mint = Pubkey::new_unique();
+
+        // borrow fixes the fee at the config of the time
+        let at_borrow = loan_repayment(&config, 10_000, false, None, &mint, 6, 0).unwrap();
+        let record = LoanRecord {
+            version: LoanRecord::VERSION,
+            borrower: Pubkey::new_unique(),
+            mint,
+            amount: at_borrow.principal,
+            fee: at_borrow.fee,
+            open: true,
+            bump: 255,
+        };
+
+        // a fee change in between doesn't reach the open loan
+        config.min_fee = 5_000;
+        assert_eq!(record.repayment().unwrap(), RepayResult { principal: 10_000, fee: 500, total: 10_500 });
+        assert_eq!(loan_repayment(&config, 10_000, false, None, &mint, 6, 0).unwrap().fee, 5_000);
+
+        // waived borrowers owe the principal only
+        assert_eq!(loan_repayment(&config, 10_000, true, None, &mint, 6, 0).unwrap(), waived_repayment(10_000));
+    }
+
+    #[test]
+    fn fee_rounds_up_on_small_loans() {
+        // 5% of 1..=20 is at most one base unit, but never zero
+        assert_eq!(compute_repayment(1, FEE_BPS, 0).unwrap().fee, 1);
+        assert_eq!(compute_repayment(19, FEE_BPS, 0).unwrap().fee, 1);
+        assert_eq!(compute_repayment(20, FEE_BPS, 0).unwrap().fee, 1);
+        assert_eq!(compute_repayment(21, FEE_BPS, 0).unwrap().fee, 2);
+
+        // exact multiples don't round
+        assert_eq!(compute_repayment(40, FEE_BPS, 0).unwrap().fee, 2);
+
+        // the floor applies on top of the rounding
+        assert_eq!(compute_repayment(1, FEE_BPS, 3).unwrap().fee, 3);
+        assert_eq!(compute_repayment(1_000, FEE_BPS, 3).unwrap().fee, 50);
+    }
+
+    #[test]
+    fn quote_repay_rounds_small_loans_up() {
+        let mut config = default_config(Pubkey::new_unique());
+
+        // principal plus a fee of at least one base unit
+        assert_eq!(quote_repay(&config, 0).unwrap(), 0);
+        assert_eq!(quote_repay(&config, 1).unwrap(), 2);
+        assert_eq!(quote_repay(&config, 20).unwrap(), 21);
+        assert_eq!(quote_repay(&config, 21).unwrap(), 23);
+        assert_eq!(quote_repay(&config, 40).unwrap(), 42);
+
+        // the same total borrow fixes for repay
+        let mint = Pubkey::new_unique();
+        for amount in 1..=100 {
+            assert_eq!(quote_repay(&config, amount).unwrap(), loan_repayment(&config, amount, false, None, &mint, 6, 0).unwrap().total);
+        }
+
+        // the floor applies on top of the rounding
+        config.min_fee = 3;
+        assert_eq!(quote_repay(&config, 1).unwrap(), 4);
+
+        // and the checked math still refuses to wrap
+        assert_eq!(err(quote_repay(&config, u64::MAX)), anchor_error(ProtocolError::Overflow));
+    }
+
+    #[test]
+    fn quote_repay_needs_a_feed_for_a_usd_fee() {
+        let mut config = default_config(Pubkey::new_unique());
+        config.usd_fee = 1_000_000;
+
+        assert_eq!(err(quote_repay(&config, 10_000)), anchor_error(ProtocolError::MissingOracle));
+    }
+
+    #[test]
+    fn max_borrow_repays_without_overflow() {
+        let result = compute_repayment(MAX_BORROW, FEE_BPS, 0).unwrap();
+        assert_eq!(result.total, MAX_BORROW + result.fee);
+
+        let config = default_config(Pubkey::new_unique());
+        assert!(config.check_borrow(MAX_BORROW).is_ok());
+        assert_eq!(err(config.check_borrow(MAX_BORROW + 1)), anchor_error(ProtocolError::AmountTooLarge));
+    }
+
+    #[test]
+    fn amounts_near_u64_max_fail_instead_of_wrapping() {
+        assert_eq!(err(compute_repayment(u64::MAX, FEE_BPS, 0)), anchor_error(ProtocolError::Overflow));
+
+        // a tier above FEE_BPS can still push MAX_BORROW past u64::MAX
+        assert_eq!(err(compute_repayment(MAX_BORROW, 10_000, 0)), anchor_error(ProtocolError::Overflow));
+        assert_eq!(err(flat_repayment(u64::MAX, 1, 0)), anchor_error(ProtocolError::Overflow));
+    }
+}
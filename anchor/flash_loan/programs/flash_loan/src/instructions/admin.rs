@@ -0,0 +1,203 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Config, FeeTier, MAX_FEE_TIERS};
+use crate::errors::ProtocolError;
+
+/// Accounts for the authority-gated config setters
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+
+    pub authority: Signer<'info>, // protocol admin
+
+    #[account(
+        mut,
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = authority @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>, // protocol config
+}
+
+/// Second step of an authority rotation, signed by the proposed key
+#[derive(Accounts)]
+pub struct AcceptAuthority<'info> {
+
+    pub pending_authority: Signer<'info>, // proposed admin
+
+    #[account(
+        mut,
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        constraint = config.pending_authority == pending_authority.key() @ ProtocolError::NotPendingAuthority,
+    )]
+    pub config: Account<'info, Config>, // protocol config
+}
+
+pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+
+    // only new borrows are halted, repay stays open so outstanding loans can settle
+    ctx.accounts.config.paused = paused;
+
+    Ok(())
+}
+
+pub fn set_max_borrow(ctx: Context<UpdateConfig>, max_borrow: u64) -> Result<()> {
+
+    // 0 lifts the cap
+    ctx.accounts.config.max_borrow = max_borrow;
+
+    Ok(())
+}
+
+pub fn set_min_liquidity(ctx: Context<UpdateConfig>, min_liquidity: u64) -> Result<()> {
+
+    // reserve floor that withdraw_fees can't go below
+    ctx.accounts.config.min_liquidity = min_liquidity;
+
+    Ok(())
+}
+
+pub fn set_min_fee(ctx: Context<UpdateConfig>, min_fee: u64) -> Result<()> {
+
+    // floor applied on top of the bps fee
+    ctx.accounts.config.min_fee = min_fee;
+
+    Ok(())
+}
+
+pub fn set_max_global_exposure(ctx: Context<UpdateConfig>, max_global_exposure: u64) -> Result<()> {
+
+    // 0 lifts the cap
+    ctx.accounts.config.max_global_exposure = max_global_exposure;
+
+    Ok(())
+}
+
+pub fn set_min_bond(ctx: Context<UpdateConfig>, min_bond: u64) -> Result<()> {
+
+    // 0 turns bonds off, plain borrow works again
+    ctx.accounts.config.min_bond = min_bond;
+
+    Ok(())
+}
+
+pub fn set_rebate_every(ctx: Context<UpdateConfig>, rebate_every: u64) -> Result<()> {
+
+    // 0 turns rebates off, already accrued loans are kept
+    ctx.accounts.config.rebate_every = rebate_every;
+
+    Ok(())
+}
+
+pub fn set_dex_program(ctx: Context<UpdateConfig>, dex_program: Pubkey) -> Result<()> {
+
+    // default turns arb off
+    ctx.accounts.config.dex_program = dex_program;
+
+    Ok(())
+}
+
+pub fn set_usd_fee(ctx: Context<UpdateConfig>, usd_fee: u64, fee_oracle: Pubkey, max_oracle_age: i64) -> Result<()> {
+
+    // 0 goes back to the bps fee, the feed settings are kept either way
+    require!(usd_fee == 0 || (fee_oracle != Pubkey::default() && max_oracle_age > 0), ProtocolError::InvalidConfig);
+
+    let config = &mut ctx.accounts.config;
+    config.usd_fee = usd_fee;
+    config.fee_oracle = fee_oracle;
+    config.max_oracle_age = max_oracle_age;
+
+    Ok(())
+}
+
+pub fn set_fee_tiers(ctx: Context<UpdateConfig>, fee_tiers: Vec<FeeTier>) -> Result<()> {
+
+    // an empty schedule goes back to FEE_BPS for every loan
+    require!(fee_tiers.len() <= MAX_FEE_TIERS, ProtocolError::InvalidFeeTiers);
+    require!(fee_tiers.windows(2).all(|pair| pair[0].threshold < pair[1].threshold), ProtocolError::InvalidFeeTiers);
+    require!(fee_tiers.iter().all(|tier| tier.fee_bps <= 10_000), ProtocolError::InvalidFeeTiers);
+
+    ctx.accounts.config.fee_tiers = fee_tiers;
+
+    Ok(())
+}
+
+pub fn propose_authority(ctx: Context<UpdateConfig>, new_authority: Pubkey) -> Result<()> {
+
+    // nothing changes until the new key accepts, default cancels a proposal
+    ctx.accounts.config.pending_authority = new_authority;
+
+    Ok(())
+}
+
+pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+
+    // the constraint already proved the signer is the pending key
+    let config = &mut ctx.accounts.config;
+    config.authority = config.pending_authority;
+    config.pending_authority = Pubkey::default();
+
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::prelude::Pubkey;
+    use crate::errors::ProtocolError;
+    use crate::instruction;
+    use crate::test_utils::*;
+
+    #[test]
+    fn only_the_authority_sets_the_borrow_cap() {
+        let mut pool = Pool::new(0);
+        let stranger = pool.svm.wallet();
+
+        let update = instruction::SetMaxBorrow { max_borrow: 1_000 };
+        assert_eq!(pool.admin_as(stranger, update), Err(anchor_error(ProtocolError::Unauthorized)));
+        assert_eq!(pool.config().max_borrow, 0);
+
+        pool.admin(instruction::SetMaxBorrow { max_borrow: 1_000 }).unwrap();
+        assert_eq!(pool.config().max_borrow, 1_000);
+    }
+
+    #[test]
+    fn authority_rotates_once_the_proposed_key_accepts() {
+        let mut pool = Pool::new(0);
+        let next = pool.svm.wallet();
+        let config = pool.config;
+        let accept = |pending_authority| ix(crate::accounts::AcceptAuthority { pending_authority, config }, instruction::AcceptAuthority {});
+
+        // nothing to accept before a proposal, and only the authority proposes
+        assert_eq!(pool.svm.process(&[accept(next)]), Err(anchor_error(ProtocolError::NotPendingAuthority)));
+        let propose = instruction::ProposeAuthority { new_authority: next };
+        assert_eq!(pool.admin_as(next, propose), Err(anchor_error(ProtocolError::Unauthorized)));
+
+        pool.admin(instruction::ProposeAuthority { new_authority: next }).unwrap();
+        assert_eq!(pool.config().authority, pool.authority);
+
+        // a third key can't take the proposal over
+        let stranger = pool.svm.wallet();
+        assert_eq!(pool.svm.process(&[accept(stranger)]), Err(anchor_error(ProtocolError::NotPendingAuthority)));
+
+        pool.svm.process(&[accept(next)]).unwrap();
+        let config = pool.config();
+        assert_eq!((config.authority, config.pending_authority), (next, Pubkey::default()));
+
+        // the old authority is locked out
+        assert_eq!(pool.admin(instruction::SetPaused { paused: true }), Err(anchor_error(ProtocolError::Unauthorized)));
+        pool.admin_as(next, instruction::SetPaused { paused: true }).unwrap();
+    }
+
+    #[test]
+    fn proposing_the_default_key_cancels() {
+        let mut pool = Pool::new(0);
+        let next = pool.svm.wallet();
+
+        pool.admin(instruction::ProposeAuthority { new_authority: next }).unwrap();
+        pool.admin(instruction::ProposeAuthority { new_authority: Pubkey::default() }).unwrap();
+
+        let accept = ix(crate::accounts::AcceptAuthority { pending_authority: next, config: pool.config }, instruction::AcceptAuthority {});
+        assert_eq!(pool.svm.process(&[accept]), Err(anchor_error(ProtocolError::NotPendingAuthority)));
+    }
+}
@@ -0,0 +1,288 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+    sysvar::instructions::{load_current_index_checked, ID as INSTRUCTIONS_SYSVAR_ID},
+};
+use anchor_spl::{
+    token::{Token, TokenAccount, Mint, Transfer, transfer},
+    associated_token::AssociatedToken,
+};
+
+use crate::{BorrowEvent, RepayEvent};
+use crate::errors::ProtocolError;
+use crate::fees::{loan_repayment, RepayResult};
+use crate::state::{pool_seed, Config, FeeWaiver, Reserve};
+use super::borrow::{check_loan_count, open_exposure};
+
+/// One swap on the configured DEX.
+///
+/// `data` is passed through untouched, the leg takes the next `accounts`
+/// remaining accounts as its route, in order.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct SwapLeg {
+    pub data: Vec<u8>,
+    pub accounts: u8,
+}
+
+/// Borrow, swap through the DEX and repay, all inside one instruction
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct Arb<'info> {
+
+    #[account(mut)]
+    pub borrower: Signer<'info>, // arbitrageur, signs the swaps
+
+    #[account(
+        seeds = [b"protocol".as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub protocol: SystemAccount<'info>, // pda account for the pool
+
+    pub mint: Account<'info, Mint>, // mint borrowed and repaid
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = borrower,
+    )]
+    pub borrower_ata: Account<'info, TokenAccount>, // receives the loan, pays it back
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = protocol,
+    )]
+    pub protocol_ata: Account<'info, TokenAccount>, // pool liquidity
+
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    /// CHECK: InstructionSysvar account
+    pub instructions: UncheckedAccount<'info>, // counts the arb with the other loans of the transaction
+
+    #[account(
+        mut,
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>, // protocol config, arb updates the lifetime totals
+
+    #[account(
+        init_if_needed, // created by the first loan of this mint
+        payer = borrower,
+        space = Reserve::INIT_SPACE + Reserve::DISCRIMINATOR.len(),
+        seeds = [b"reserve".as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub reserve: Account<'info, Reserve>, // fee bookkeeping for this mint in the pool
+
+    #[account(
+        executable,
+        address = config.dex_program @ ProtocolError::InvalidDexProgram,
+        constraint = config.dex_program != Pubkey::default() @ ProtocolError::InvalidDexProgram,
+    )]
+    /// CHECK: pinned to the configured DEX program
+    pub dex_program: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        seeds = [b"fee_waiver".as_ref()],
+        bump = fee_waiver.bump,
+    )]
+    pub fee_waiver: Option<Account<'info, FeeWaiver>>, // waived borrowers arb without a fee
+
+    /// CHECK: owner and layout checked by `PriceFeed::read`
+    pub price_feed: Option<UncheckedAccount<'info>>, // USD price of the mint, required while a USD fee is set
+}
+
+pub fn handler<'info>(ctx: Context<'_, '_, 'info, 'info, Arb<'info>>, pool_id: u64, borrow_amount: u64, legs: Vec<SwapLeg>) -> Result<RepayResult> {
+
+    // same gates as a plain borrow, on top of the loans already open in this transaction
+    let config = &ctx.accounts.config;
+    require!(config.min_bond == 0, ProtocolError::BondRequired);
+    config.check_borrow(borrow_amount)?;
+
+    // an arb is a loan too: it counts against the per-transaction limit, can't
+    // share a vault with a borrow, and adds to what the open borrows around it lend out
+    let ixs = ctx.accounts.instructions.to_account_info();
+    let current_index = load_current_index_checked(&ixs)?;
+    check_loan_count(&ixs, config.max_loans_per_tx, &ctx.accounts.protocol_ata.key())?;
+    config.check_exposure(open_exposure(&ixs, current_index, borrow_amount)?)?;
+
+    // fees waiting to be withdrawn or compounded are not lendable
+    let reserve = &mut ctx.accounts.reserve;
+    reserve.mint = ctx.accounts.mint.key();
+    reserve.bump = ctx.bumps.reserve;
+    let available = reserve.lendable(ctx.accounts.protocol_ata.amount);
+    require!(borrow_amount <= available, ProtocolError::NotEnoughFunds);
+
+    // same fee as a borrow of the same amount
+    let waived = ctx.accounts.fee_waiver.as_ref().is_some_and(|list| list.is_waived(&ctx.accounts.borrower.key()));
+    let result = loan_repayment(
+        &ctx.accounts.config,
+        borrow_amount,
+        waived,
+        ctx.accounts.price_feed.as_deref(),
+        &ctx.accounts.mint.key(),
+        ctx.accounts.mint.decimals,
+        Clock::get()?.unix_timestamp,
+    )?;
+    let balance_before = ctx.accounts.borrower_ata.amount;
+
+    // lend the funds out of the pool
+    let pool = pool_seed(pool_id);
+    let seeds = &[
+        b"protocol".as_ref(),
+        pool.as_ref(),
+        &[ctx.bumps.protocol]
+    ];
+
+    let signer_seeds = &[&seeds[..]];
+
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.protocol_ata.to_account_info(),
+                to: ctx.accounts.borrower_ata.to_account_info(),
+                authority: ctx.accounts.protocol.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        borrow_amount,
+    )?;
+
+    // run the route, each leg is a plain CPI into the DEX with the borrower's signature
+    let dex_program = ctx.accounts.dex_program.to_account_info();
+    let mut route = ctx.remaining_accounts;
+    for leg in legs {
+        let (leg_accounts, rest) = route.split_at_checked(leg.accounts as usize).ok_or(ProtocolError::InvalidRoute)?;
+        route = rest;
+
+        let ix = Instruction {
+            program_id: dex_program.key(),
+            accounts: leg_accounts.iter().map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer,
+                is_writable: account.is_writable,
+            }).collect(),
+            data: leg.data,
+        };
+
+        let mut account_infos = leg_accounts.to_vec();
+        account_infos.push(dex_program.clone());
+        invoke(&ix, &account_infos)?;
+    }
+
+    // the route has to pay for the fee, a losing trade reverts the whole loan
+    ctx.accounts.borrower_ata.reload()?;
+    check_profit(balance_before, ctx.accounts.borrower_ata.amount, &result)?;
+
+    // repay principal and fee
+    transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.borrower_ata.to_account_info(),
+                to: ctx.accounts.protocol_ata.to_account_info(),
+                authority: ctx.accounts.borrower.to_account_info(),
+            }
+        ),
+        result.total
+    )?;
+
+    // keep the fee out of the lendable liquidity until it's withdrawn or compounded
+    let reserve = &mut ctx.accounts.reserve;
+    reserve.accrued_fees = reserve.accrued_fees.checked_add(result.fee).ok_or(ProtocolError::Overflow)?;
+
+    // lifetime volume and revenue
+    ctx.accounts.config.settle_loan(result.principal, result.fee)?;
+
+    emit!(BorrowEvent {
+        borrower: ctx.accounts.borrower.key(),
+        mint: ctx.accounts.mint.key(),
+        amount: borrow_amount,
+    });
+
+    emit!(RepayEvent {
+        borrower: ctx.accounts.borrower.key(),
+        mint: ctx.accounts.mint.key(),
+        amount: result.principal,
+        fee: result.fee,
+    });
+
+    // handed back to the caller as return data
+    Ok(result)
+}
+
+/// The borrower has to end up with at least `balance_before` once the
+/// principal and the fee are paid back out of `balance_after`
+fn check_profit(balance_before: u64, balance_after: u64, result: &RepayResult) -> Result<()> {
+
+    let required = balance_before.checked_add(result.total).ok_or(ProtocolError::Overflow)?;
+    require!(balance_after >= required, ProtocolError::UnprofitableRoute);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::compute_repayment;
+    use crate::test_utils::*;
+
+    /// Mock DEX paying `out_bps` of what it's given back, borrower balance after the route
+    fn route(balance_before: u64, borrow_amount: u64, out_bps: u64) -> u64 {
+        balance_before + borrow_amount * out_bps / 10_000
+    }
+
+    #[test]
+    fn route_has_to_cover_principal_and_fee() {
+        let result = compute_repayment(1_000_000, 500, 0).unwrap();
+        let before = 200_000;
+
+        // 6% up on the route covers the 5% fee
+        assert!(check_profit(before, route(before, 1_000_000, 10_600), &result).is_ok());
+        assert!(check_profit(before, route(before, 1_000_000, 10_500), &result).is_ok());
+
+        // 4% up still leaves the borrower short once the fee is paid
+        let after = route(before, 1_000_000, 10_400);
+        assert!(after >= before + result.fee);
+        assert_eq!(err(check_profit(before, after, &result)), anchor_error(ProtocolError::UnprofitableRoute));
+
+        // a route that loses money can't dip into the borrower's own balance
+        assert_eq!(err(check_profit(before, route(before, 1_000_000, 9_000), &result)), anchor_error(ProtocolError::UnprofitableRoute));
+    }
+
+    #[test]
+    fn an_arb_counts_with_the_borrows_around_it() {
+        let mut pool = Pool::new(10_000);
+        // Nothing to lend, so the arb stops right before its transfer
+        let empty = pool.add_vault(0);
+        let borrower = pool.borrower(&[pool.mint, empty]);
+        pool.set_config(Config { dex_program: anchor_spl::token::ID, ..pool.config() });
+
+        // Gets past the loan count and the cap, and fails on the liquidity instead
+        let instructions = [pool.borrow_ix(borrower, 600), pool.arb_ix(borrower, empty, 400), pool.repay_ix(borrower)];
+        assert_eq!(pool.svm.process_at(&instructions, 1), Err(anchor_error(ProtocolError::NotEnoughFunds)));
+
+        // the borrow still open adds to the arb's exposure
+        pool.set_config(Config { max_global_exposure: 999, ..pool.config() });
+        assert_eq!(pool.svm.process_at(&instructions, 1), Err(anchor_error(ProtocolError::GlobalExposureExceeded)));
+
+        // and the arb takes a loan of the per-transaction limit
+        pool.set_config(Config { max_global_exposure: 0, max_loans_per_tx: 1, ..pool.config() });
+        assert_eq!(pool.svm.process_at(&instructions, 1), Err(anchor_error(ProtocolError::TooManyLoans)));
+
+        // it can't draw from the vault of an open borrow either
+        pool.set_config(Config { max_loans_per_tx: 2, ..pool.config() });
+        let same_vault = [pool.borrow_ix(borrower, 600), pool.arb_ix(borrower, pool.mint, 400), pool.repay_ix(borrower)];
+        assert_eq!(pool.svm.process_at(&same_vault, 1), Err(anchor_error(ProtocolError::DuplicateBorrow)));
+
+        // nor can a borrow next to an arb on its vault
+        let ixs = account_info(INSTRUCTIONS_SYSVAR_ID, instructions_sysvar(&same_vault, 0));
+        assert_eq!(err(check_loan_count(&ixs, 2, &pool.protocol_ata)), anchor_error(ProtocolError::DuplicateBorrow));
+    }
+}
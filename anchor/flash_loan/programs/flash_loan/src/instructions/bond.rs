@@ -0,0 +1,273 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+
+use crate::state::{Bond, Config};
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+pub struct PostBond<'info> {
+
+    #[account(mut)]
+    pub borrower: Signer<'info>, // borrower posting the bond
+
+    #[account(
+        init_if_needed, // first deposit creates the bond
+        payer = borrower,
+        space = Bond::INIT_SPACE + Bond::DISCRIMINATOR.len(),
+        seeds = [b"bond".as_ref(), borrower.key().as_ref()],
+        bump,
+    )]
+    pub bond: Account<'info, Bond>, // pda account holding the bonded lamports
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawBond<'info> {
+
+    #[account(mut)]
+    pub borrower: Signer<'info>, // bond owner
+
+    #[account(
+        mut,
+        seeds = [b"bond".as_ref(), borrower.key().as_ref()],
+        bump = bond.bump,
+        has_one = borrower @ ProtocolError::Unauthorized,
+    )]
+    pub bond: Account<'info, Bond>, // pda account holding the bonded lamports
+}
+
+/// The protocol authority acts as keeper
+#[derive(Accounts)]
+pub struct KeeperBond<'info> {
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // protocol admin, receives slashed bonds
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = authority @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>, // protocol config
+
+    #[account(
+        mut,
+        seeds = [b"bond".as_ref(), bond.borrower.as_ref()],
+        bump = bond.bump,
+    )]
+    pub bond: Account<'info, Bond>, // bond being reported on
+}
+
+pub fn post_bond(ctx: Context<PostBond>, amount: u64) -> Result<()> {
+
+    require!(amount > 0, ProtocolError::InvalidAmount);
+
+    transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.borrower.to_account_info(),
+                to: ctx.accounts.bond.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+
+    let bond = &mut ctx.accounts.bond;
+    bond.borrower = ctx.accounts.borrower.key();
+    bond.bump = ctx.bumps.bond;
+    bond.amount = bond.amount.checked_add(amount).ok_or(ProtocolError::Overflow)?;
+
+    Ok(())
+}
+
+pub fn request_bond_unlock(ctx: Context<WithdrawBond>) -> Result<()> {
+
+    // starts the cooldown, the bond stops backing loans right away
+    let bond = &mut ctx.accounts.bond;
+    bond.unlock_requested_at = Clock::get()?.unix_timestamp;
+
+    Ok(())
+}
+
+pub fn withdraw_bond(ctx: Context<WithdrawBond>, amount: u64) -> Result<()> {
+
+    require!(amount > 0, ProtocolError::InvalidAmount);
+
+    // the keeper gets the cooldown to report the last loans, so a borrower can't
+    // pull the bond out between misbehaving and the report
+    let bond = &mut ctx.accounts.bond;
+    require!(bond.is_unlocked(Clock::get()?.unix_timestamp), ProtocolError::BondCoolingDown);
+
+    // nor from under a pending slash
+    require!(!bond.is_slashable(), ProtocolError::BondLocked);
+    bond.amount = bond.amount.checked_sub(amount).ok_or(ProtocolError::NotEnoughFunds)?;
+    bond.unlock_requested_at = 0;
+
+    // rent stays in the account, only bonded lamports move
+    bond.sub_lamports(amount)?;
+    ctx.accounts.borrower.add_lamports(amount)?;
+
+    Ok(())
+}
+
+pub fn report_failures(ctx: Context<KeeperBond>, count: u64) -> Result<()> {
+
+    require!(count > 0, ProtocolError::InvalidAmount);
+
+    // failed loans revert, so the keeper reports what it saw off-chain
+    let bond = &mut ctx.accounts.bond;
+    bond.failures = bond.failures.checked_add(count).ok_or(ProtocolError::Overflow)?;
+
+    Ok(())
+}
+
+pub fn slash_bond(ctx: Context<KeeperBond>) -> Result<()> {
+
+    let bond = &mut ctx.accounts.bond;
+    require!(bond.is_slashable(), ProtocolError::NotSlashable);
+
+    // the whole bond goes to the protocol and the record starts over
+    let amount = bond.amount;
+    bond.amount = 0;
+    bond.successes = 0;
+    bond.failures = 0;
+
+    bond.sub_lamports(amount)?;
+    ctx.accounts.authority.add_lamports(amount)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::instruction::Instruction;
+    use anchor_lang::InstructionData;
+    use crate::instruction;
+    use crate::state::BOND_UNLOCK_DELAY;
+    use crate::test_utils::*;
+
+    const BONDED: u64 = 1_000_000;
+
+    /// Bond of a fresh borrower with `successes` repaid loans and `BONDED` lamports posted
+    fn post(pool: &mut Pool, successes: u64) -> (Pubkey, Pubkey) {
+        let borrower = pool.borrower(&[pool.mint]);
+        let (bond, bump) = Pubkey::find_program_address(&[b"bond", borrower.as_ref()], &crate::ID);
+        let state = Bond { borrower, amount: BONDED, successes, failures: 0, unlock_requested_at: 0, bump };
+        pool.svm.set_state(bond, &state, Bond::INIT_SPACE + Bond::DISCRIMINATOR.len());
+        pool.svm.airdrop(&bond, BONDED);
+        (borrower, bond)
+    }
+
+    fn keeper(pool: &Pool, authority: Pubkey, bond: Pubkey, data: impl InstructionData) -> Instruction {
+        ix(crate::accounts::KeeperBond { authority, config: pool.config, bond }, data)
+    }
+
+    fn owner(borrower: Pubkey, bond: Pubkey, data: impl InstructionData) -> Instruction {
+        ix(crate::accounts::WithdrawBond { borrower, bond }, data)
+    }
+
+    #[test]
+    fn bond_is_slashable_once_failures_pass_half_of_the_attempts() {
+        let bond = |successes, failures| Bond { borrower: Pubkey::default(), amount: 1, successes, failures, unlock_requested_at: 0, bump: 0 };
+
+        assert!(!bond(0, 0).is_slashable());
+        assert!(!bond(1, 1).is_slashable());
+        assert!(bond(1, 2).is_slashable());
+        assert!(bond(0, 1).is_slashable());
+    }
+
+    #[test]
+    fn only_the_authority_reports_failures() {
+        let mut pool = Pool::new(0);
+        let (_, bond) = post(&mut pool, 1);
+        let stranger = pool.svm.wallet();
+
+        let report = keeper(&pool, stranger, bond, instruction::ReportFailures { count: 5 });
+        assert_eq!(pool.svm.process(&[report]), Err(anchor_error(ProtocolError::Unauthorized)));
+
+        let report = keeper(&pool, pool.authority, bond, instruction::ReportFailures { count: 5 });
+        pool.svm.process(&[report]).unwrap();
+        assert_eq!(pool.svm.state::<Bond>(&bond).failures, 5);
+    }
+
+    #[test]
+    fn griefing_borrower_gets_slashed_and_cant_withdraw_first() {
+        let mut pool = Pool::new(0);
+        let (borrower, bond) = post(&mut pool, 1);
+
+        // one success against one failure isn't enough
+        let report = keeper(&pool, pool.authority, bond, instruction::ReportFailures { count: 1 });
+        pool.svm.process(&[report]).unwrap();
+        let slash = keeper(&pool, pool.authority, bond, instruction::SlashBond {});
+        assert_eq!(pool.svm.process(std::slice::from_ref(&slash)), Err(anchor_error(ProtocolError::NotSlashable)));
+
+        let report = keeper(&pool, pool.authority, bond, instruction::ReportFailures { count: 1 });
+        pool.svm.process(&[report]).unwrap();
+
+        // the bond is locked while it can be slashed, even once unlocked
+        pool.svm.process(&[owner(borrower, bond, instruction::RequestBondUnlock {})]).unwrap();
+        pool.svm.now += BOND_UNLOCK_DELAY;
+        let withdraw = owner(borrower, bond, instruction::WithdrawBond { amount: BONDED });
+        assert_eq!(pool.svm.process(&[withdraw]), Err(anchor_error(ProtocolError::BondLocked)));
+
+        let authority_before = pool.svm.lamports(&pool.authority);
+        pool.svm.process(&[slash]).unwrap();
+        assert_eq!(pool.svm.lamports(&pool.authority), authority_before + BONDED);
+
+        let slashed = pool.svm.state::<Bond>(&bond);
+        assert_eq!((slashed.amount, slashed.successes, slashed.failures), (0, 0, 0));
+    }
+
+    #[test]
+    fn clean_borrower_withdraws_the_bond() {
+        let mut pool = Pool::new(0);
+        let (borrower, bond) = post(&mut pool, 3);
+        let before = pool.svm.lamports(&borrower);
+
+        let withdraw = owner(borrower, bond, instruction::WithdrawBond { amount: BONDED });
+        assert_eq!(pool.svm.process(std::slice::from_ref(&withdraw)), Err(anchor_error(ProtocolError::BondCoolingDown)));
+
+        pool.svm.process(&[owner(borrower, bond, instruction::RequestBondUnlock {})]).unwrap();
+        pool.svm.now += BOND_UNLOCK_DELAY;
+        pool.svm.process(&[withdraw]).unwrap();
+
+        assert_eq!(pool.svm.lamports(&borrower), before + BONDED);
+        let withdrawn = pool.svm.state::<Bond>(&bond);
+        assert_eq!((withdrawn.amount, withdrawn.unlock_requested_at), (0, 0));
+    }
+
+    #[test]
+    fn misbehaving_borrower_cant_withdraw_before_the_report() {
+        let mut pool = Pool::new(0);
+        let (borrower, bond) = post(&mut pool, 1);
+
+        // right after two failed loans, before the keeper has reported them
+        pool.svm.process(&[owner(borrower, bond, instruction::RequestBondUnlock {})]).unwrap();
+        let withdraw = owner(borrower, bond, instruction::WithdrawBond { amount: BONDED });
+        assert_eq!(pool.svm.process(std::slice::from_ref(&withdraw)), Err(anchor_error(ProtocolError::BondCoolingDown)));
+
+        pool.svm.now += BOND_UNLOCK_DELAY - 1;
+        assert_eq!(pool.svm.process(std::slice::from_ref(&withdraw)), Err(anchor_error(ProtocolError::BondCoolingDown)));
+
+        // the report lands within the cooldown and the bond stays for the slash
+        let report = keeper(&pool, pool.authority, bond, instruction::ReportFailures { count: 2 });
+        pool.svm.process(&[report]).unwrap();
+        pool.svm.now += 1;
+        assert_eq!(pool.svm.process(&[withdraw]), Err(anchor_error(ProtocolError::BondLocked)));
+        pool.svm.process(&[keeper(&pool, pool.authority, bond, instruction::SlashBond {})]).unwrap();
+    }
+
+    #[test]
+    fn unlocking_bond_backs_no_loans() {
+        let mut pool = Pool::new(10_000);
+        let (borrower, bond) = post(&mut pool, 1);
+        pool.svm.process(&[owner(borrower, bond, instruction::RequestBondUnlock {})]).unwrap();
+
+        let accounts = crate::accounts::Loan { bond: Some(bond), ..pool.loan_accounts(borrower) };
+        let borrow = ix(accounts, instruction::BorrowWithBond { pool_id: 0, borrow_amount: 1_000 });
+        assert_eq!(pool.svm.process(&[borrow]), Err(anchor_error(ProtocolError::BondUnlocking)));
+    }
+}
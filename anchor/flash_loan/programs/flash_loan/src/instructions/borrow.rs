@@ -0,0 +1,334 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Transfer, transfer},
+    associated_token::get_associated_token_address,    
+};
+
+use anchor_lang::{
+    Discriminator,
+    solana_program::{
+        instruction::Instruction,
+        sysvar::instructions::{
+            load_current_index_checked,
+            load_instruction_at_checked
+        }
+    }
+};
+
+use crate::{instruction, BorrowEvent, ID};
+use crate::errors::ProtocolError;
+use crate::fees::loan_repayment;
+use crate::state::{pool_seed, LoanRecord};
+use super::Loan;
+
+pub fn handler(ctx: Context<Loan>, pool_id: u64, borrow_amount: u64) -> Result<()> {
+
+    // once bonds are required every loan has to go through borrow_with_bond
+    require!(ctx.accounts.config.min_bond == 0, ProtocolError::BondRequired);
+
+    process(ctx, pool_id, borrow_amount)
+}
+
+pub fn handler_with_bond(ctx: Context<Loan>, pool_id: u64, borrow_amount: u64) -> Result<()> {
+
+    // the bond has to cover the protocol minimum, not be up for slashing, and not on its way out
+    let bond = ctx.accounts.bond.as_ref().ok_or(ProtocolError::BondRequired)?;
+    require!(bond.amount > 0 && bond.amount >= ctx.accounts.config.min_bond, ProtocolError::InsufficientBond);
+    require!(!bond.is_slashable(), ProtocolError::BondLocked);
+    require!(!bond.is_unlocking(), ProtocolError::BondUnlocking);
+
+    process(ctx, pool_id, borrow_amount)
+}
+
+fn process(ctx: Context<Loan>, pool_id: u64, borrow_amount: u64) -> Result<()> {
+        
+    // pause switch, amount bounds and the per-loan cap
+    ctx.accounts.config.check_borrow(borrow_amount)?;
+
+    // Instruction Introspection, before any funds move: the loan count and a
+    // repay later in the transaction. Other loans may come before this one.
+    let ixs = ctx.accounts.instructions.to_account_info();
+    let current_index = load_current_index_checked(&ctx.accounts.instructions)?;
+    check_loan_count(&ixs, ctx.accounts.config.max_loans_per_tx, &ctx.accounts.protocol_ata.key())?;
+    let repay_ix = find_repay(&ixs, current_index, &ctx.accounts.protocol_ata.key())?;
+
+    // verify ATA accounts, the repay is already pinned to this pool's vault
+    let expected_borrower_ata = get_associated_token_address(&ctx.accounts.borrower.key(), &ctx.accounts.mint.key());
+    require_keys_eq!(ctx.accounts.borrower_ata.key(), expected_borrower_ata, ProtocolError::InvalidBorrowerAta);
+    require_keys_eq!(repay_ix.accounts.get(3).ok_or(ProtocolError::InvalidBorrowerAta)?.pubkey, expected_borrower_ata, ProtocolError::InvalidBorrowerAta);
+
+    // principal out at once in this transaction, this loan included, against the global cap
+    ctx.accounts.config.check_exposure(open_exposure(&ixs, current_index, borrow_amount)?)?;
+
+    // the fee is fixed when the loan is taken, unless the borrower is on the waiver list
+    let waived = ctx.accounts.fee_waiver.as_ref().is_some_and(|list| list.is_waived(&ctx.accounts.borrower.key()));
+    let result = loan_repayment(
+        &ctx.accounts.config,
+        borrow_amount,
+        waived,
+        ctx.accounts.price_feed.as_deref(),
+        &ctx.accounts.mint.key(),
+        ctx.accounts.mint.decimals,
+        Clock::get()?.unix_timestamp,
+    )?;
+
+    // make sure the borrower ATA really belongs to the borrower for this mint
+    require_keys_eq!(ctx.accounts.borrower_ata.owner, ctx.accounts.borrower.key(), ProtocolError::InvalidBorrowerAtaOwner);
+    require_keys_eq!(ctx.accounts.borrower_ata.mint, ctx.accounts.mint.key(), ProtocolError::InvalidBorrowerAtaMint);
+
+    // fees waiting to be withdrawn or compounded are not lendable
+    let reserve = &mut ctx.accounts.reserve;
+    reserve.mint = ctx.accounts.mint.key();
+    reserve.bump = ctx.bumps.reserve;
+    let available = reserve.lendable(ctx.accounts.protocol_ata.amount);
+    require!(borrow_amount <= available, ProtocolError::NotEnoughFunds);
+
+    // derive signer seeds for the protocol account necessary to sign tranfer transaction
+    let pool = pool_seed(pool_id);
+    let seeds = &[
+        b"protocol".as_ref(),
+        pool.as_ref(),
+        &[ctx.bumps.protocol]
+    ];
+
+    let signer_seeds = &[&seeds[..]];
+
+    // transfer the funds from the protocol to the borrower
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.protocol_ata.to_account_info(),
+                to: ctx.accounts.borrower_ata.to_account_info(),
+                authority: ctx.accounts.protocol.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        borrow_amount,
+    )?;
+
+    // repay charges exactly what's recorded here
+    let loan_record = &mut ctx.accounts.loan_record;
+    require!(loan_record.version == 0 || loan_record.version == LoanRecord::VERSION, ProtocolError::UnsupportedLoanVersion);
+    loan_record.version = LoanRecord::VERSION;
+    loan_record.borrower = ctx.accounts.borrower.key();
+    loan_record.mint = ctx.accounts.mint.key();
+    loan_record.amount = borrow_amount;
+    loan_record.fee = result.fee;
+    loan_record.open = true;
+    loan_record.bump = ctx.bumps.loan_record;
+
+    emit!(BorrowEvent {
+        borrower: ctx.accounts.borrower.key(),
+        mint: ctx.accounts.mint.key(),
+        amount: borrow_amount,
+    });
+
+    Ok(())
+}
+
+fn instruction_count(ixs: &AccountInfo) -> Result<u16> {
+
+    let instruction_sysvar = ixs.try_borrow_data()?;
+    require!(instruction_sysvar.len() >= 2, ProtocolError::InvalidIx);
+
+    Ok(u16::from_le_bytes([instruction_sysvar[0], instruction_sysvar[1]]))
+}
+
+/// Amount of a borrow instruction of this program, `None` for anything else
+fn borrowed(ix: &Instruction) -> Option<u64> {
+
+    let discriminator = ix.data.get(0..8)?;
+    let is_borrow = discriminator == instruction::Borrow::DISCRIMINATOR
+        || discriminator == instruction::BorrowWithBond::DISCRIMINATOR;
+    if ix.program_id != ID || !is_borrow {
+        return None;
+    }
+
+    // pool_id, then borrow_amount
+    Some(u64::from_le_bytes(ix.data.get(16..24)?.try_into().ok()?))
+}
+
+/// Whether `ix` lends out of a vault of this program: a borrow, or an arb
+/// that borrows and repays inside itself
+fn is_loan(ix: &Instruction) -> bool {
+
+    borrowed(ix).is_some() || (ix.program_id == ID && ix.data.get(0..8) == Some(instruction::Arb::DISCRIMINATOR))
+}
+
+/// First repay of this program after `index` paying back into `protocol_ata`, with its index
+fn repay_after(ixs: &AccountInfo, index: usize, protocol_ata: &Pubkey) -> Result<Option<(usize, Instruction)>> {
+
+    for index in index + 1..instruction_count(ixs)? as usize {
+        let ix = load_instruction_at_checked(index, ixs)?;
+        let is_repay = ix.program_id == ID && ix.data.get(0..8) == Some(instruction::Repay::DISCRIMINATOR);
+        if is_repay && ix.accounts.get(4).is_some_and(|meta| meta.pubkey == *protocol_ata) {
+            return Ok(Some((index, ix)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Repay of the loan at `current_index`.
+/// At most one loan per vault is open in a transaction, so the first one after it is this loan's.
+fn find_repay(ixs: &AccountInfo, current_index: u16, protocol_ata: &Pubkey) -> Result<Instruction> {
+
+    match repay_after(ixs, current_index as usize, protocol_ata)? {
+        Some((_, ix)) => Ok(ix),
+        None => err!(ProtocolError::MissingRepayIx),
+    }
+}
+
+/// Principal out at once while the loan at `current_index` is open: its own
+/// amount plus every earlier borrow that's only repaid after it
+pub fn open_exposure(ixs: &AccountInfo, current_index: u16, borrow_amount: u64) -> Result<u64> {
+
+    let mut open_exposure = borrow_amount;
+    for index in 0..current_index as usize {
+        let ix = load_instruction_at_checked(index, ixs)?;
+        let (Some(amount), Some(vault)) = (borrowed(&ix), ix.accounts.get(4)) else {
+            continue;
+        };
+
+        // a borrow without any repay fails on its own, counting it is harmless
+        let repaid = repay_after(ixs, index, &vault.pubkey)?.is_some_and(|(repay, _)| repay < current_index as usize);
+        if !repaid {
+            open_exposure = open_exposure.checked_add(amount).ok_or(ProtocolError::Overflow)?;
+        }
+    }
+
+    Ok(open_exposure)
+}
+
+/// Counts the loans of this program in the whole transaction, arbs included,
+/// and the ones drawing from `protocol_ata`
+pub fn check_loan_count(ixs: &AccountInfo, max_loans_per_tx: u8, protocol_ata: &Pubkey) -> Result<()> {
+
+    let mut loans: u16 = 0;
+    let mut same_vault_loans: u16 = 0;
+    for index in 0..instruction_count(ixs)? as usize {
+        let ix = load_instruction_at_checked(index, ixs)?;
+        if is_loan(&ix) {
+            loans += 1;
+            if ix.accounts.get(4).is_some_and(|meta| meta.pubkey == *protocol_ata) {
+                same_vault_loans += 1;
+            }
+        }
+    }
+    require!(loans <= max_loans_per_tx as u16, ProtocolError::TooManyLoans);
+    require!(same_vault_loans <= 1, ProtocolError::DuplicateBorrow);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use anchor_lang::solana_program::sysvar::instructions::ID as INSTRUCTIONS_SYSVAR_ID;
+
+    use super::*;
+    use crate::state::Config;
+    use crate::test_utils::*;
+
+    /// Borrow from a vault of its own for every borrower, repaid at the end
+    fn sysvar(pools: &[&Pool], borrowers: &[Pubkey]) -> AccountInfo<'static> {
+        let mut instructions: Vec<_> = pools.iter().zip(borrowers).map(|(pool, borrower)| pool.borrow_ix(*borrower, 1_000)).collect();
+        instructions.push(pools[0].repay_ix(borrowers[0]));
+        account_info(INSTRUCTIONS_SYSVAR_ID, instructions_sysvar(&instructions, 0))
+    }
+
+    #[test]
+    fn loans_up_to_the_limit_pass() {
+        let (first, second) = (Pool::new(10_000), Pool::new(10_000));
+        let ixs = sysvar(&[&first, &second], &[Pubkey::new_unique(), Pubkey::new_unique()]);
+
+        assert!(check_loan_count(&ixs, 2, &first.protocol_ata).is_ok());
+    }
+
+    #[test]
+    fn more_loans_than_the_limit_fail() {
+        let pools = [Pool::new(10_000), Pool::new(10_000), Pool::new(10_000)];
+        let borrowers = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let ixs = sysvar(&[&pools[0], &pools[1], &pools[2]], &borrowers);
+
+        assert_eq!(err(check_loan_count(&ixs, 2, &pools[0].protocol_ata)), anchor_error(ProtocolError::TooManyLoans));
+        assert!(check_loan_count(&ixs, 3, &pools[0].protocol_ata).is_ok());
+    }
+
+    #[test]
+    fn nested_borrow_from_the_same_vault_fails() {
+        let pool = Pool::new(10_000);
+        let borrower = Pubkey::new_unique();
+        let ixs = sysvar(&[&pool, &pool], &[borrower, borrower]);
+
+        assert_eq!(err(check_loan_count(&ixs, 2, &pool.protocol_ata)), anchor_error(ProtocolError::DuplicateBorrow));
+    }
+
+    #[test]
+    fn borrow_with_bond_counts_as_a_borrow() {
+        let pool = Pool::new(10_000);
+        let borrower = Pubkey::new_unique();
+        let bonded = ix(pool.loan_accounts(borrower), instruction::BorrowWithBond { pool_id: 0, borrow_amount: 1_000 });
+        let instructions = [pool.borrow_ix(borrower, 1_000), bonded, pool.repay_ix(borrower)];
+        let ixs = account_info(INSTRUCTIONS_SYSVAR_ID, instructions_sysvar(&instructions, 0));
+
+        assert_eq!(err(check_loan_count(&ixs, 2, &pool.protocol_ata)), anchor_error(ProtocolError::DuplicateBorrow));
+    }
+
+    #[test]
+    fn a_second_borrow_runs_through_the_handler() {
+        let mut pool = Pool::new(10_000);
+        // Nothing to lend, so the second borrow stops right before its transfer
+        let empty = pool.add_vault(0);
+        let borrower = pool.borrower(&[pool.mint, empty]);
+        let second = ix(pool.loan_accounts_of(borrower, empty), instruction::Borrow { pool_id: 0, borrow_amount: 1_000 });
+        let repay_second = ix(pool.loan_accounts_of(borrower, empty), instruction::Repay { pool_id: 0 });
+        let instructions = [pool.borrow_ix(borrower, 1_000), second.clone(), repay_second.clone(), pool.repay_ix(borrower)];
+
+        // Gets past the introspection at index 1 and fails on the liquidity instead
+        assert_eq!(pool.svm.process_at(&instructions, 1), Err(anchor_error(ProtocolError::NotEnoughFunds)));
+
+        // Still bounded by the loan count
+        pool.set_config(Config { max_loans_per_tx: 1, ..pool.config() });
+        assert_eq!(pool.svm.process_at(&instructions, 1), Err(anchor_error(ProtocolError::TooManyLoans)));
+        pool.set_config(Config { max_loans_per_tx: 2, ..pool.config() });
+
+        // And each loan needs its own repay after it
+        let repaid_before = [pool.borrow_ix(borrower, 1_000), repay_second, second, pool.repay_ix(borrower)];
+        assert_eq!(pool.svm.process_at(&repaid_before, 2), Err(anchor_error(ProtocolError::MissingRepayIx)));
+    }
+
+    #[test]
+    fn only_loans_still_open_add_to_the_exposure() {
+        let (first, second) = (Pool::new(10_000), Pool::new(10_000));
+        let borrower = Pubkey::new_unique();
+
+        // the first loan is repaid after the second one is taken
+        let nested = [first.borrow_ix(borrower, 600), second.borrow_ix(borrower, 400), second.repay_ix(borrower), first.repay_ix(borrower)];
+        let ixs = account_info(INSTRUCTIONS_SYSVAR_ID, instructions_sysvar(&nested, 1));
+        assert_eq!(open_exposure(&ixs, 1, 400).unwrap(), 1_000);
+
+        // back to back, the first one is already paid back
+        let sequential = [first.borrow_ix(borrower, 600), first.repay_ix(borrower), second.borrow_ix(borrower, 400), second.repay_ix(borrower)];
+        let ixs = account_info(INSTRUCTIONS_SYSVAR_ID, instructions_sysvar(&sequential, 2));
+        assert_eq!(open_exposure(&ixs, 2, 400).unwrap(), 400);
+    }
+
+    #[test]
+    fn open_loans_are_capped_by_the_global_exposure() {
+        let mut pool = Pool::new(10_000);
+        let empty = pool.add_vault(0);
+        let borrower = pool.borrower(&[pool.mint, empty]);
+        let second = ix(pool.loan_accounts_of(borrower, empty), instruction::Borrow { pool_id: 0, borrow_amount: 400 });
+        let repay_second = ix(pool.loan_accounts_of(borrower, empty), instruction::Repay { pool_id: 0 });
+        let instructions = [pool.borrow_ix(borrower, 600), second, repay_second, pool.repay_ix(borrower)];
+
+        // 600 still out when the second loan is taken
+        pool.set_config(Config { max_global_exposure: 999, ..pool.config() });
+        assert_eq!(pool.svm.process_at(&instructions, 1), Err(anchor_error(ProtocolError::GlobalExposureExceeded)));
+
+        // Gets past the cap and fails on the empty vault instead
+        pool.set_config(Config { max_global_exposure: 1_000, ..pool.config() });
+        assert_eq!(pool.svm.process_at(&instructions, 1), Err(anchor_error(ProtocolError::NotEnoughFunds)));
+    }
+}
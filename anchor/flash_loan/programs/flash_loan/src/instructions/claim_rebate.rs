@@ -0,0 +1,103 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount, Mint, Transfer, transfer};
+
+use crate::state::{pool_seed, BorrowerStats, Config, Reserve};
+use crate::RebateEvent;
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct ClaimRebate<'info> {
+
+    pub borrower: Signer<'info>, // borrower claiming back a fee
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>, // protocol config
+
+    #[account(
+        seeds = [b"protocol".as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub protocol: SystemAccount<'info>, // pda account for the pool
+
+    pub mint: Account<'info, Mint>, // mint account
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = borrower,
+    )]
+    pub borrower_ata: Account<'info, TokenAccount>, // ATA account receiving the rebate
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = protocol,
+    )]
+    pub protocol_ata: Account<'info, TokenAccount>, // ATA account holding the pool liquidity and fees
+
+    #[account(
+        mut,
+        seeds = [b"reserve".as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump = reserve.bump,
+    )]
+    pub reserve: Account<'info, Reserve>, // fee bookkeeping for this mint in the pool
+
+    #[account(
+        mut,
+        seeds = [b"borrower_stats".as_ref(), borrower.key().as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump = borrower_stats.bump,
+    )]
+    pub borrower_stats: Account<'info, BorrowerStats>, // loyalty record
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn handler(ctx: Context<ClaimRebate>, pool_id: u64) -> Result<()> {
+
+    // one rebate per claim, the loans past it count towards the next one
+    let rebate_every = ctx.accounts.config.rebate_every;
+    let stats = &mut ctx.accounts.borrower_stats;
+    let owed = stats.owed(rebate_every);
+    let amount = stats.claim(rebate_every)?;
+
+    // rebates are paid out of the accrued fees held back for them, never out of the lending liquidity
+    let reserve = &mut ctx.accounts.reserve;
+    reserve.accrued_fees = reserve.accrued_fees.checked_sub(amount).ok_or(ProtocolError::NotEnoughFunds)?;
+    reserve.rebook_rebates(owed, stats.owed(rebate_every))?;
+
+    // same signer seeds as borrow
+    let pool = pool_seed(pool_id);
+    let seeds = &[
+        b"protocol".as_ref(),
+        pool.as_ref(),
+        &[ctx.bumps.protocol]
+    ];
+
+    let signer_seeds = &[&seeds[..]];
+
+    // transfer the rebate from the protocol to the borrower
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.protocol_ata.to_account_info(),
+                to: ctx.accounts.borrower_ata.to_account_info(),
+                authority: ctx.accounts.protocol.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+    )?;
+
+    emit!(RebateEvent {
+        borrower: ctx.accounts.borrower.key(),
+        mint: ctx.accounts.mint.key(),
+        amount,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::state::{pool_seed, Config, Reserve};
+use crate::CompoundEvent;
+use crate::errors::ProtocolError;
+
+/// Authority only, compounded fees stop being withdrawable or claimable
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct CompoundFees<'info> {
+
+    pub authority: Signer<'info>, // protocol admin
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = authority @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>, // protocol config
+
+    pub mint: Account<'info, Mint>, // mint account
+
+    #[account(
+        mut,
+        seeds = [b"reserve".as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump = reserve.bump,
+    )]
+    pub reserve: Account<'info, Reserve>, // fee bookkeeping for this mint
+}
+
+pub fn handler(ctx: Context<CompoundFees>, _pool_id: u64) -> Result<()> {
+
+    let amount = ctx.accounts.reserve.free_fees();
+    require!(amount > 0, ProtocolError::InvalidAmount);
+
+    // the fees already sit in protocol_ata, releasing them makes them borrowable,
+    // rebates borrowers have earned stay held back
+    let reserve = &mut ctx.accounts.reserve;
+    reserve.accrued_fees = reserve.reserved_rebates;
+
+    emit!(CompoundEvent {
+        mint: ctx.accounts.mint.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::instruction::Instruction;
+    use crate::test_utils::*;
+
+    fn compound(pool: &Pool, authority: Pubkey) -> Instruction {
+        let accounts = crate::accounts::CompoundFees { authority, config: pool.config, mint: pool.mint, reserve: pool.reserve };
+        ix(accounts, crate::instruction::CompoundFees { pool_id: 0 })
+    }
+
+    #[test]
+    fn only_the_authority_compounds_fees() {
+        let mut pool = Pool::new(1_000_000);
+        pool.set_reserve(Reserve { accrued_fees: 500, ..pool.reserve() });
+
+        let stranger = pool.svm.wallet();
+        assert_eq!(pool.svm.process(&[compound(&pool, stranger)]), Err(anchor_error(ProtocolError::Unauthorized)));
+        assert_eq!(pool.reserve().accrued_fees, 500);
+
+        pool.svm.process(&[compound(&pool, pool.authority)]).unwrap();
+        assert_eq!(pool.reserve().accrued_fees, 0);
+
+        // nothing left to compound
+        assert_eq!(pool.svm.process(&[compound(&pool, pool.authority)]), Err(anchor_error(ProtocolError::InvalidAmount)));
+    }
+
+    #[test]
+    fn a_pool_only_reaches_its_own_reserve() {
+        let mut pool = Pool::new(1_000_000);
+        pool.set_reserve(Reserve { accrued_fees: 500, ..pool.reserve() });
+
+        // pool 1 derives a different reserve for the same mint
+        let accounts = crate::accounts::CompoundFees { authority: pool.authority, config: pool.config, mint: pool.mint, reserve: pool.reserve };
+        let other_pool = ix(accounts, crate::instruction::CompoundFees { pool_id: 1 });
+        assert_eq!(pool.svm.process(&[other_pool]), Err(anchor_error(ErrorCode::ConstraintSeeds)));
+        assert_eq!(pool.reserve().accrued_fees, 500);
+    }
+
+    #[test]
+    fn compounding_leaves_earned_rebates_alone() {
+        let mut pool = Pool::new(1_000_000);
+        pool.set_reserve(Reserve { accrued_fees: 500, reserved_rebates: 120, ..pool.reserve() });
+
+        pool.svm.process(&[compound(&pool, pool.authority)]).unwrap();
+        let reserve = pool.reserve();
+        assert_eq!((reserve.accrued_fees, reserve.reserved_rebates), (120, 120));
+
+        // the rebates alone can't be compounded
+        assert_eq!(pool.svm.process(&[compound(&pool, pool.authority)]), Err(anchor_error(ProtocolError::InvalidAmount)));
+    }
+}
@@ -0,0 +1,112 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Config, FeeWaiver, MAX_WAIVERS};
+use crate::errors::ProtocolError;
+
+/// Accounts for managing the fee waiver list
+#[derive(Accounts)]
+pub struct ManageWaivers<'info> {
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // protocol admin, pays for the list
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = authority @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>, // protocol config
+
+    #[account(
+        init_if_needed, // the first waiver creates the list
+        payer = authority,
+        space = FeeWaiver::INIT_SPACE + FeeWaiver::DISCRIMINATOR.len(),
+        seeds = [b"fee_waiver".as_ref()],
+        bump,
+    )]
+    pub fee_waiver: Account<'info, FeeWaiver>, // waived borrowers
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn add_waiver(ctx: Context<ManageWaivers>, borrower: Pubkey) -> Result<()> {
+
+    let fee_waiver = &mut ctx.accounts.fee_waiver;
+    fee_waiver.bump = ctx.bumps.fee_waiver;
+
+    // adding someone twice is a no-op
+    if fee_waiver.is_waived(&borrower) {
+        return Ok(());
+    }
+
+    require!(fee_waiver.borrowers.len() < MAX_WAIVERS, ProtocolError::WaiverListFull);
+    fee_waiver.borrowers.push(borrower);
+
+    Ok(())
+}
+
+pub fn remove_waiver(ctx: Context<ManageWaivers>, borrower: Pubkey) -> Result<()> {
+
+    let fee_waiver = &mut ctx.accounts.fee_waiver;
+    let index = fee_waiver.borrowers.iter().position(|waived| *waived == borrower).ok_or(ProtocolError::WaiverNotFound)?;
+
+    // order doesn't matter, swap_remove keeps it O(1)
+    fee_waiver.borrowers.swap_remove(index);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::instruction::Instruction;
+    use anchor_lang::InstructionData;
+    use crate::test_utils::*;
+
+    /// Pool with an empty waiver list, as the first `add_waiver` leaves it
+    fn pool() -> (Pool, Pubkey) {
+        let mut pool = Pool::new(0);
+        let (list, bump) = Pubkey::find_program_address(&[b"fee_waiver"], &crate::ID);
+        let waiver = FeeWaiver { borrowers: Vec::new(), bump };
+        pool.svm.set_state(list, &waiver, FeeWaiver::INIT_SPACE + FeeWaiver::DISCRIMINATOR.len());
+        (pool, list)
+    }
+
+    fn manage(pool: &Pool, list: Pubkey, authority: Pubkey, data: impl InstructionData) -> Instruction {
+        let accounts = crate::accounts::ManageWaivers { authority, config: pool.config, fee_waiver: list, system_program: system_program::ID };
+        ix(accounts, data)
+    }
+
+    #[test]
+    fn authority_adds_and_removes_waivers() {
+        let (mut pool, list) = pool();
+        let borrower = Pubkey::new_unique();
+
+        let stranger = pool.svm.wallet();
+        let add = manage(&pool, list, stranger, crate::instruction::AddWaiver { borrower });
+        assert_eq!(pool.svm.process(&[add]), Err(anchor_error(ProtocolError::Unauthorized)));
+
+        // adding twice keeps a single entry
+        let add = manage(&pool, list, pool.authority, crate::instruction::AddWaiver { borrower });
+        pool.svm.process(std::slice::from_ref(&add)).unwrap();
+        pool.svm.process(&[add]).unwrap();
+        assert_eq!(pool.svm.state::<FeeWaiver>(&list).borrowers, vec![borrower]);
+
+        let remove = manage(&pool, list, pool.authority, crate::instruction::RemoveWaiver { borrower });
+        pool.svm.process(std::slice::from_ref(&remove)).unwrap();
+        assert!(!pool.svm.state::<FeeWaiver>(&list).is_waived(&borrower));
+        assert_eq!(pool.svm.process(&[remove]), Err(anchor_error(ProtocolError::WaiverNotFound)));
+    }
+
+    #[test]
+    fn waiver_list_is_bounded() {
+        let (mut pool, list) = pool();
+        for _ in 0..MAX_WAIVERS {
+            let add = manage(&pool, list, pool.authority, crate::instruction::AddWaiver { borrower: Pubkey::new_unique() });
+            pool.svm.process(&[add]).unwrap();
+        }
+
+        let add = manage(&pool, list, pool.authority, crate::instruction::AddWaiver { borrower: Pubkey::new_unique() });
+        assert_eq!(pool.svm.process(&[add]), Err(anchor_error(ProtocolError::WaiverListFull)));
+    }
+}
@@ -0,0 +1,51 @@
+use anchor_lang::prelude::*;
+
+use crate::state::Config;
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // protocol admin
+
+    #[account(
+        init,
+        payer = authority,
+        space = Config::INIT_SPACE + Config::DISCRIMINATOR.len(),
+        seeds = [b"config".as_ref()],
+        bump,
+    )]
+    pub config: Account<'info, Config>, // pda account holding the protocol config
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<Initialize>, max_loans_per_tx: u8, min_liquidity: u64) -> Result<()> {
+
+    // a limit of 0 would make every borrow fail
+    require!(max_loans_per_tx > 0, ProtocolError::InvalidConfig);
+
+    ctx.accounts.config.set_inner(Config {
+        authority: ctx.accounts.authority.key(),
+        max_loans_per_tx,
+        paused: false,
+        min_liquidity,
+        max_borrow: 0,
+        min_fee: 0,
+        max_global_exposure: 0,
+        min_bond: 0,
+        total_borrowed: 0,
+        total_fees_collected: 0,
+        pending_authority: Pubkey::default(),
+        rebate_every: 0,
+        dex_program: Pubkey::default(),
+        usd_fee: 0,
+        fee_oracle: Pubkey::default(),
+        max_oracle_age: 0,
+        fee_tiers: Vec::new(),
+        bump: ctx.bumps.config,
+    });
+
+    Ok(())
+}
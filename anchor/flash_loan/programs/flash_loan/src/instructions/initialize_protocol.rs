@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Token, TokenAccount, Mint},
+    associated_token::AssociatedToken,
+};
+
+use crate::state::{pool_seed, Config, Reserve};
+use crate::errors::ProtocolError;
+
+/// Bootstraps the vault of one mint in a pool, safe to call again.
+///
+/// Authority and fee settings live in `Config`, created by `initialize`.
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct InitializeProtocol<'info> {
+
+    #[account(mut)]
+    pub authority: Signer<'info>, // protocol admin, pays for the accounts
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = authority @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>, // protocol config
+
+    #[account(
+        seeds = [b"protocol".as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub protocol: SystemAccount<'info>, // pda account for the pool
+
+    pub mint: Account<'info, Mint>, // mint account
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = protocol,
+    )]
+    pub protocol_ata: Account<'info, TokenAccount>, // ATA account holding the pool liquidity
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = Reserve::INIT_SPACE + Reserve::DISCRIMINATOR.len(),
+        seeds = [b"reserve".as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub reserve: Account<'info, Reserve>, // fee bookkeeping for this mint in the pool
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handler(ctx: Context<InitializeProtocol>, _pool_id: u64) -> Result<()> {
+
+    // accrued fees are left alone so a repeated call can't reset them
+    let reserve = &mut ctx.accounts.reserve;
+    reserve.mint = ctx.accounts.mint.key();
+    reserve.bump = ctx.bumps.reserve;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::instruction::Instruction;
+    use crate::test_utils::*;
+
+    fn initialize(pool: &Pool, authority: Pubkey) -> Instruction {
+        let accounts = crate::accounts::InitializeProtocol {
+            authority,
+            config: pool.config,
+            protocol: pool.protocol,
+            mint: pool.mint,
+            protocol_ata: pool.protocol_ata,
+            reserve: pool.reserve,
+            token_program: anchor_spl::token::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+        };
+        ix(accounts, crate::instruction::InitializeProtocol { pool_id: 0 })
+    }
+
+    #[test]
+    fn calling_it_again_keeps_the_fees() {
+        let mut pool = Pool::new(1_000_000);
+        pool.set_reserve(Reserve { accrued_fees: 500, total_shares: 7_000, ..pool.reserve() });
+
+        pool.svm.process(&[initialize(&pool, pool.authority)]).unwrap();
+        let reserve = pool.reserve();
+        assert_eq!((reserve.mint, reserve.accrued_fees, reserve.total_shares), (pool.mint, 500, 7_000));
+    }
+
+    #[test]
+    fn only_the_authority_bootstraps_a_pool() {
+        let mut pool = Pool::new(0);
+        let stranger = pool.svm.wallet();
+        assert_eq!(pool.svm.process(&[initialize(&pool, stranger)]), Err(anchor_error(ProtocolError::Unauthorized)));
+    }
+}
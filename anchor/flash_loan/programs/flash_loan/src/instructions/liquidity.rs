@@ -0,0 +1,198 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Token, TokenAccount, Mint, Transfer, transfer},
+    associated_token::AssociatedToken,
+};
+
+use crate::{DepositEvent, WithdrawEvent};
+use crate::errors::ProtocolError;
+use crate::state::{pool_seed, Config, LiquidityPosition, Reserve};
+
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct DepositLiquidity<'info> {
+
+    #[account(mut)]
+    pub provider: Signer<'info>, // liquidity provider
+
+    #[account(
+        seeds = [b"protocol".as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub protocol: SystemAccount<'info>, // pda account for the pool
+
+    pub mint: Account<'info, Mint>, // mint account
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = provider,
+    )]
+    pub provider_ata: Account<'info, TokenAccount>, // ATA account the liquidity comes from
+
+    #[account(
+        init_if_needed, // the first deposit creates the protocol vault
+        payer = provider,
+        associated_token::mint = mint,
+        associated_token::authority = protocol,
+    )]
+    pub protocol_ata: Account<'info, TokenAccount>, // ATA account holding the pool liquidity
+
+    #[account(
+        init_if_needed, // the first deposit or loan of this mint creates it
+        payer = provider,
+        space = Reserve::INIT_SPACE + Reserve::DISCRIMINATOR.len(),
+        seeds = [b"reserve".as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub reserve: Account<'info, Reserve>, // fee and share bookkeeping for this mint in the pool
+
+    #[account(
+        init_if_needed, // first deposit of this provider opens the position
+        payer = provider,
+        space = LiquidityPosition::INIT_SPACE + LiquidityPosition::DISCRIMINATOR.len(),
+        seeds = [b"position".as_ref(), provider.key().as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub position: Account<'info, LiquidityPosition>, // provider's shares
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct WithdrawLiquidity<'info> {
+
+    pub provider: Signer<'info>, // position owner
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>, // protocol config
+
+    #[account(
+        seeds = [b"protocol".as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub protocol: SystemAccount<'info>, // pda account for the pool
+
+    pub mint: Account<'info, Mint>, // mint account
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = provider,
+    )]
+    pub provider_ata: Account<'info, TokenAccount>, // ATA account receiving the liquidity
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = protocol,
+    )]
+    pub protocol_ata: Account<'info, TokenAccount>, // ATA account holding the pool liquidity
+
+    #[account(
+        mut,
+        seeds = [b"reserve".as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump = reserve.bump,
+    )]
+    pub reserve: Account<'info, Reserve>, // fee and share bookkeeping for this mint in the pool
+
+    #[account(
+        mut,
+        seeds = [b"position".as_ref(), provider.key().as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, LiquidityPosition>, // provider's shares
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn deposit_liquidity(ctx: Context<DepositLiquidity>, _pool_id: u64, amount: u64) -> Result<()> {
+
+    require!(amount > 0, ProtocolError::InvalidAmount);
+
+    // accrued fees belong to no one until they're compounded
+    let reserve = &mut ctx.accounts.reserve;
+    reserve.mint = ctx.accounts.mint.key();
+    reserve.bump = ctx.bumps.reserve;
+    let shares = reserve.deposit(amount, ctx.accounts.protocol_ata.amount)?;
+
+    // transfer the liquidity from the provider to the protocol
+    transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.provider_ata.to_account_info(),
+                to: ctx.accounts.protocol_ata.to_account_info(),
+                authority: ctx.accounts.provider.to_account_info(),
+            }
+        ),
+        amount
+    )?;
+
+    let position = &mut ctx.accounts.position;
+    position.owner = ctx.accounts.provider.key();
+    position.mint = ctx.accounts.mint.key();
+    position.bump = ctx.bumps.position;
+    position.shares = position.shares.checked_add(shares).ok_or(ProtocolError::Overflow)?;
+
+    emit!(DepositEvent {
+        depositor: ctx.accounts.provider.key(),
+        mint: ctx.accounts.mint.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+pub fn withdraw_liquidity(ctx: Context<WithdrawLiquidity>, pool_id: u64, amount: u64) -> Result<()> {
+
+    require!(amount > 0, ProtocolError::InvalidAmount);
+
+    // burn shares worth the amount
+    let shares = ctx.accounts.reserve.withdraw(amount, ctx.accounts.protocol_ata.amount)?;
+    let position = &mut ctx.accounts.position;
+    position.shares = position.shares.checked_sub(shares).ok_or(ProtocolError::InsufficientShares)?;
+
+    // never let the vault drop below the minimum liquidity
+    let remaining = ctx.accounts.protocol_ata.amount.checked_sub(amount).ok_or(ProtocolError::NotEnoughFunds)?;
+    require!(remaining >= ctx.accounts.config.min_liquidity, ProtocolError::BelowMinLiquidity);
+
+    // same signer seeds as borrow
+    let pool = pool_seed(pool_id);
+    let seeds = &[
+        b"protocol".as_ref(),
+        pool.as_ref(),
+        &[ctx.bumps.protocol]
+    ];
+
+    let signer_seeds = &[&seeds[..]];
+
+    // transfer the liquidity from the protocol to the provider
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.protocol_ata.to_account_info(),
+                to: ctx.accounts.provider_ata.to_account_info(),
+                authority: ctx.accounts.protocol.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+    )?;
+
+    emit!(WithdrawEvent {
+        provider: ctx.accounts.provider.key(),
+        mint: ctx.accounts.mint.key(),
+        amount,
+        shares,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::{Token, TokenAccount, Mint},
+    associated_token::AssociatedToken,    
+};
+
+use anchor_lang::solana_program::sysvar::instructions::ID as INSTRUCTIONS_SYSVAR_ID;
+
+use crate::state::{pool_seed, Bond, BorrowerStats, Config, FeeWaiver, LoanRecord, Reserve};
+
+/// Accounts shared by `borrow` and `repay`.
+///
+/// `borrow` introspects the repay instruction by account index, so new
+/// accounts must be appended at the end to keep `borrower_ata` at index 3
+/// and `protocol_ata` at index 4.
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct Loan<'info> {
+
+    #[account(mut)]
+    pub borrower: Signer<'info>, // borrower account
+
+    
+    #[account(
+        seeds = [b"protocol".as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub protocol: SystemAccount<'info>, // pda account for the pool
+
+    pub mint: Account<'info, Mint>, // mint account
+
+    #[account(
+        init_if_needed, // only initialize account if borrower doesn't have one yet
+        payer = borrower,
+        associated_token::mint = mint,
+        associated_token::authority = borrower,
+    )]
+    pub borrower_ata: Account<'info, TokenAccount>, // ATA account needed for borrower to hold mint account
+
+    #[account(
+        mut, 
+        associated_token::mint = mint,
+        associated_token::authority = protocol,
+    )]
+    pub protocol_ata: Account<'info, TokenAccount>, // ATA account needed for protocol to hold mint account
+
+    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
+    /// CHECK: InstructionSysvar account
+    pub instructions: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>, // protocol config, repay updates the lifetime totals
+
+    #[account(
+        init_if_needed, // created by the first loan of this mint
+        payer = borrower,
+        space = Reserve::INIT_SPACE + Reserve::DISCRIMINATOR.len(),
+        seeds = [b"reserve".as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub reserve: Account<'info, Reserve>, // fee bookkeeping for this mint in the pool
+
+    #[account(
+        mut,
+        seeds = [b"bond".as_ref(), borrower.key().as_ref()],
+        bump = bond.bump,
+    )]
+    pub bond: Option<Account<'info, Bond>>, // borrower bond, required by borrow_with_bond
+
+    #[account(
+        init_if_needed, // passing it opts the borrower into rebates
+        payer = borrower,
+        space = BorrowerStats::INIT_SPACE + BorrowerStats::DISCRIMINATOR.len(),
+        seeds = [b"borrower_stats".as_ref(), borrower.key().as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub borrower_stats: Option<Account<'info, BorrowerStats>>, // loyalty record, updated by repay
+
+    #[account(
+        seeds = [b"fee_waiver".as_ref()],
+        bump = fee_waiver.bump,
+    )]
+    pub fee_waiver: Option<Account<'info, FeeWaiver>>, // waived borrowers borrow without a fee
+
+    #[account(
+        init_if_needed, // created by the borrower's first loan of this mint in the pool
+        payer = borrower,
+        space = LoanRecord::INIT_SPACE + LoanRecord::DISCRIMINATOR.len(),
+        seeds = [b"loan".as_ref(), borrower.key().as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub loan_record: Account<'info, LoanRecord>, // principal and fee of the loan
+
+    /// CHECK: owner and layout checked by `PriceFeed::read`
+    pub price_feed: Option<UncheckedAccount<'info>>, // USD price of the mint, required by borrow while a USD fee is set
+}
@@ -0,0 +1,197 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{Transfer, transfer};
+use anchor_spl::token::Mint;
+
+use crate::state::{pool_seed, LoanRecord, Reserve};
+use crate::errors::ProtocolError;
+
+/// Grows a reserve created by an older version to the current layout.
+///
+/// Permissionless, the new fields start at zero and the payer only covers
+/// the extra rent.
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct MigrateReserve<'info> {
+
+    #[account(mut)]
+    pub payer: Signer<'info>, // pays the rent for the extra space
+
+    pub mint: Account<'info, Mint>, // mint account
+
+    /// CHECK: too short to load as a Reserve until it's grown, checked in the handler
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"reserve".as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub reserve: UncheckedAccount<'info>, // fee bookkeeping for this mint in the pool
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Rewrites a loan record from before `version` in the current layout
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct MigrateLoanRecord<'info> {
+
+    #[account(mut)]
+    pub borrower: Signer<'info>, // record owner, pays the rent for the extra byte
+
+    pub mint: Account<'info, Mint>, // mint account
+
+    /// CHECK: legacy layout, can't be loaded as a LoanRecord, checked in the handler
+    #[account(
+        mut,
+        owner = crate::ID,
+        seeds = [b"loan".as_ref(), borrower.key().as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub loan_record: UncheckedAccount<'info>, // principal and fee of the last loan
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn migrate_reserve(ctx: Context<MigrateReserve>, _pool_id: u64) -> Result<()> {
+
+    let reserve = ctx.accounts.reserve.to_account_info();
+    check_legacy_reserve(&reserve.try_borrow_data()?)?;
+
+    // top the account up to the rent of the new size
+    let rent = Rent::get()?.minimum_balance(Reserve::LEN).saturating_sub(reserve.lamports());
+    if rent > 0 {
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: reserve.clone(),
+                },
+            ),
+            rent,
+        )?;
+    }
+
+    // fields are only appended, so the old bytes stay where they are and the new ones read as zero
+    reserve.resize(Reserve::LEN)?;
+
+    Ok(())
+}
+
+pub fn migrate_loan_record(ctx: Context<MigrateLoanRecord>, _pool_id: u64) -> Result<()> {
+
+    let loan_record = ctx.accounts.loan_record.to_account_info();
+    let record = legacy_loan_record(&loan_record.try_borrow_data()?)?;
+
+    // top the account up to the rent of the new size
+    let rent = Rent::get()?.minimum_balance(LoanRecord::LEN).saturating_sub(loan_record.lamports());
+    if rent > 0 {
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.borrower.to_account_info(),
+                    to: loan_record.clone(),
+                },
+            ),
+            rent,
+        )?;
+    }
+
+    loan_record.resize(LoanRecord::LEN)?;
+    record.try_serialize(&mut &mut loan_record.try_borrow_mut_data()?[..])?;
+
+    Ok(())
+}
+
+/// Loan record written before `version` led the layout, in the current one.
+/// Every later field kept its order, so only the version byte is missing.
+fn legacy_loan_record(data: &[u8]) -> Result<LoanRecord> {
+
+    require!(data.starts_with(LoanRecord::DISCRIMINATOR), ErrorCode::AccountDiscriminatorMismatch);
+    require!(data.len() != LoanRecord::LEN, ProtocolError::AlreadyMigrated);
+    require!(data.len() == LoanRecord::LEGACY_LEN, ProtocolError::InvalidLoanRecord);
+
+    let (discriminator, fields) = data.split_at(LoanRecord::DISCRIMINATOR.len());
+    let current = [discriminator, &[LoanRecord::VERSION], fields].concat();
+
+    LoanRecord::try_deserialize(&mut &current[..])
+}
+
+/// Reserve data written by an older version of the program
+fn check_legacy_reserve(data: &[u8]) -> Result<()> {
+
+    require!(data.starts_with(Reserve::DISCRIMINATOR), ErrorCode::AccountDiscriminatorMismatch);
+    require!(data.len() < Reserve::LEN, ProtocolError::AlreadyMigrated);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    /// Reserve as written before LP shares: mint, accrued fees and bump
+    fn legacy_reserve(mint: Pubkey, accrued_fees: u64, bump: u8) -> Vec<u8> {
+        let mut data = Reserve::DISCRIMINATOR.to_vec();
+        data.extend_from_slice(mint.as_ref());
+        data.extend_from_slice(&accrued_fees.to_le_bytes());
+        data.push(bump);
+        data
+    }
+
+    #[test]
+    fn legacy_reserve_keeps_its_fields_once_grown() {
+        let mint = Pubkey::new_unique();
+        let mut data = legacy_reserve(mint, 700, 254);
+        assert!(Reserve::try_deserialize(&mut &data[..]).is_err());
+        check_legacy_reserve(&data).unwrap();
+
+        // what `resize` leaves behind
+        data.resize(Reserve::LEN, 0);
+        let reserve = Reserve::try_deserialize(&mut &data[..]).unwrap();
+        assert_eq!(reserve.mint, mint);
+        assert_eq!(reserve.accrued_fees, 700);
+        assert_eq!(reserve.bump, 254);
+        assert_eq!(reserve.total_shares, 0);
+        assert_eq!(reserve.reserved_rebates, 0);
+
+        // a second migration has nothing to do
+        assert_eq!(err(check_legacy_reserve(&data)), anchor_error(ProtocolError::AlreadyMigrated));
+    }
+
+    #[test]
+    fn legacy_loan_record_gets_the_current_version() {
+        let record = LoanRecord {
+            version: LoanRecord::VERSION,
+            borrower: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            amount: 1_000,
+            fee: 50,
+            open: false,
+            bump: 253,
+        };
+        let current = record.try_to_vec().unwrap();
+
+        // the same record without the version byte up front
+        let legacy = [LoanRecord::DISCRIMINATOR, &current[1..]].concat();
+        assert_eq!(legacy.len(), LoanRecord::LEGACY_LEN);
+
+        let migrated = legacy_loan_record(&legacy).unwrap();
+        migrated.check_version().unwrap();
+        assert_eq!(migrated.try_to_vec().unwrap(), current);
+
+        let mut data = Vec::new();
+        migrated.try_serialize(&mut data).unwrap();
+        assert_eq!(err(legacy_loan_record(&data).map(drop)), anchor_error(ProtocolError::AlreadyMigrated));
+        assert_eq!(err(legacy_loan_record(&legacy[..40]).map(drop)), anchor_error(ProtocolError::InvalidLoanRecord));
+    }
+
+    #[test]
+    fn only_reserves_are_migrated() {
+        let mut data = legacy_reserve(Pubkey::new_unique(), 0, 255);
+        data[0] ^= 1;
+        assert_eq!(err(check_legacy_reserve(&data)), anchor_error(ErrorCode::AccountDiscriminatorMismatch));
+    }
+}
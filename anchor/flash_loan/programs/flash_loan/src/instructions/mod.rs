@@ -0,0 +1,28 @@
+pub mod loan;
+pub use loan::*;
+pub mod initialize;
+pub use initialize::*;
+pub mod initialize_protocol;
+pub use initialize_protocol::*;
+pub mod admin;
+pub use admin::*;
+pub mod withdraw_fees;
+pub use withdraw_fees::*;
+pub mod compound_fees;
+pub use compound_fees::*;
+pub mod quote_fee;
+pub use quote_fee::*;
+pub mod bond;
+pub use bond::*;
+pub mod claim_rebate;
+pub use claim_rebate::*;
+pub mod fee_waiver;
+pub use fee_waiver::*;
+pub mod arb;
+pub use arb::*;
+pub mod liquidity;
+pub use liquidity::*;
+pub mod migrate;
+pub use migrate::*;
+pub mod borrow;
+pub mod repay;
@@ -0,0 +1,120 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::Mint;
+
+use crate::state::{Config, FeeWaiver};
+use crate::fees::{loan_repayment, RepayResult};
+use crate::QuoteEvent;
+
+/// Read-only, lets clients price a loan with the exact math `borrow` uses
+#[derive(Accounts)]
+pub struct QuoteFee<'info> {
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+    )]
+    pub config: Account<'info, Config>, // protocol config, for the fee tiers and floor
+
+    pub mint: Account<'info, Mint>, // mint to be borrowed
+
+    /// CHECK: only its key is read, to look it up in the waiver list
+    pub borrower: Option<UncheckedAccount<'info>>, // borrower to quote for, anyone when left out
+
+    #[account(
+        seeds = [b"fee_waiver".as_ref()],
+        bump = fee_waiver.bump,
+    )]
+    pub fee_waiver: Option<Account<'info, FeeWaiver>>, // waived borrowers are quoted no fee
+
+    /// CHECK: owner and layout checked by `PriceFeed::read`
+    pub price_feed: Option<UncheckedAccount<'info>>, // USD price of the mint, required while a USD fee is set
+}
+
+pub fn handler(ctx: Context<QuoteFee>, borrow_amount: u64) -> Result<RepayResult> {
+
+    let waived = match (&ctx.accounts.borrower, &ctx.accounts.fee_waiver) {
+        (Some(borrower), Some(list)) => list.is_waived(borrower.key),
+        _ => false,
+    };
+    let result = loan_repayment(
+        &ctx.accounts.config,
+        borrow_amount,
+        waived,
+        ctx.accounts.price_feed.as_deref(),
+        &ctx.accounts.mint.key(),
+        ctx.accounts.mint.decimals,
+        Clock::get()?.unix_timestamp,
+    )?;
+
+    emit!(QuoteEvent {
+        amount: result.principal,
+        fee: result.fee,
+        total: result.total,
+    });
+
+    // also handed back as return data for simulated calls
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::solana_program::instruction::Instruction;
+    use crate::errors::ProtocolError;
+    use crate::fees::FEE_BPS;
+    use crate::state::FeeTier;
+    use crate::test_utils::*;
+
+    fn quote(pool: &Pool, borrower: Option<Pubkey>, borrow_amount: u64) -> Instruction {
+        let accounts = crate::accounts::QuoteFee {
+            config: pool.config,
+            mint: pool.mint,
+            borrower,
+            fee_waiver: borrower.map(|_| pda(&[b"fee_waiver"])),
+            price_feed: None,
+        };
+        ix(accounts, crate::instruction::QuoteFee { borrow_amount })
+    }
+
+    fn fee(config: &Config, borrow_amount: u64) -> u64 {
+        loan_repayment(config, borrow_amount, false, None, &Pubkey::new_unique(), 6, 0).unwrap().fee
+    }
+
+    #[test]
+    fn small_loans_round_the_fee_up() {
+        let mut config = default_config(Pubkey::new_unique());
+        assert_eq!(fee(&config, 1), 1);
+        assert_eq!(fee(&config, 20), 1);
+        assert_eq!(fee(&config, 21), 2);
+
+        // tiers round the same way
+        config.fee_tiers = vec![FeeTier { threshold: 1_000, fee_bps: 30 }];
+        assert_eq!(fee(&config, 999), (999 * FEE_BPS).div_ceil(10_000));
+        assert_eq!(fee(&config, 1_000), 3);
+        assert_eq!(fee(&config, 1_001), 4);
+
+        // and the floor still wins over a rounded-up fee
+        config.min_fee = 10;
+        assert_eq!(fee(&config, 1_001), 10);
+    }
+
+    #[test]
+    fn quotes_go_through_the_borrow_fee_path() {
+        let mut pool = Pool::new(0);
+        let waived = pool.svm.wallet();
+        let (list, bump) = Pubkey::find_program_address(&[b"fee_waiver"], &crate::ID);
+        let waiver = FeeWaiver { borrowers: vec![waived], bump };
+        pool.svm.set_state(list, &waiver, FeeWaiver::INIT_SPACE + FeeWaiver::DISCRIMINATOR.len());
+        pool.svm.process(&[quote(&pool, None, 10_000)]).unwrap();
+
+        // a USD fee can't be quoted without a price
+        let mut config = pool.config();
+        config.usd_fee = 1_000_000;
+        pool.set_config(config);
+        assert_eq!(pool.svm.process(&[quote(&pool, None, 10_000)]), Err(anchor_error(ProtocolError::MissingOracle)));
+        assert_eq!(pool.svm.process(&[quote(&pool, Some(Pubkey::new_unique()), 10_000)]), Err(anchor_error(ProtocolError::MissingOracle)));
+
+        // unless the borrower is waived and owes no fee at all
+        pool.svm.process(&[quote(&pool, Some(waived), 10_000)]).unwrap();
+    }
+}
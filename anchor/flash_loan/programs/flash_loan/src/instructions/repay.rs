@@ -0,0 +1,65 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Transfer, transfer};
+
+use crate::RepayEvent;
+use crate::fees::RepayResult;
+use crate::errors::ProtocolError;
+use super::Loan;
+
+pub fn handler(ctx: Context<Loan>, _pool_id: u64) -> Result<RepayResult> {
+
+    // the loan was recorded by the borrow earlier in this transaction
+    let loan_record = &ctx.accounts.loan_record;
+    loan_record.check_version()?;
+    require!(loan_record.open, ProtocolError::MissingBorrowIx);
+
+    // principal plus the fee borrow fixed, and settle the loan
+    let result = loan_record.repayment()?;
+    ctx.accounts.loan_record.open = false;
+
+    // Transfer the funds from the protocol to the borrower
+    transfer(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(), 
+            Transfer {
+                from: ctx.accounts.borrower_ata.to_account_info(),
+                to: ctx.accounts.protocol_ata.to_account_info(),
+                authority: ctx.accounts.borrower.to_account_info(),
+            }
+        ), 
+        result.total
+    )?;
+
+    // keep the fee out of the lendable liquidity until it's withdrawn or compounded
+    let reserve = &mut ctx.accounts.reserve;
+    reserve.accrued_fees = reserve.accrued_fees.checked_add(result.fee).ok_or(ProtocolError::Overflow)?;
+
+    // lifetime volume and revenue
+    ctx.accounts.config.settle_loan(result.principal, result.fee)?;
+
+    // successful loans count towards the borrower's record
+    if let Some(bond) = ctx.accounts.bond.as_mut() {
+        bond.successes = bond.successes.checked_add(1).ok_or(ProtocolError::Overflow)?;
+    }
+
+    // count the loan towards the borrower's rebate, and hold back what it has earned
+    if let Some(stats) = ctx.accounts.borrower_stats.as_mut() {
+        let rebate_every = ctx.accounts.config.rebate_every;
+        let owed = stats.owed(rebate_every);
+        stats.borrower = ctx.accounts.borrower.key();
+        stats.mint = ctx.accounts.mint.key();
+        stats.bump = ctx.bumps.borrower_stats.ok_or(ProtocolError::InvalidIx)?;
+        stats.record_loan(result.fee)?;
+        ctx.accounts.reserve.rebook_rebates(owed, stats.owed(rebate_every))?;
+    }
+
+    emit!(RepayEvent {
+        borrower: ctx.accounts.borrower.key(),
+        mint: ctx.accounts.mint.key(),
+        amount: result.principal,
+        fee: result.fee,
+    });
+
+    // handed back to the caller as return data
+    Ok(result)
+}
@@ -0,0 +1,91 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount, Mint, Transfer, transfer};
+
+use crate::state::{pool_seed, Config, Reserve};
+use crate::errors::ProtocolError;
+
+#[derive(Accounts)]
+#[instruction(pool_id: u64)]
+pub struct WithdrawFees<'info> {
+
+    pub authority: Signer<'info>, // protocol admin
+
+    #[account(
+        seeds = [b"config".as_ref()],
+        bump = config.bump,
+        has_one = authority @ ProtocolError::Unauthorized,
+    )]
+    pub config: Account<'info, Config>, // protocol config
+
+    #[account(
+        seeds = [b"protocol".as_ref(), pool_seed(pool_id).as_ref()],
+        bump,
+    )]
+    pub protocol: SystemAccount<'info>, // pda account for the pool
+
+    pub mint: Account<'info, Mint>, // mint account
+
+    #[account(
+        mut, 
+        associated_token::mint = mint,
+        associated_token::authority = protocol,
+    )]
+    pub protocol_ata: Account<'info, TokenAccount>, // ATA account holding the protocol liquidity and fees
+
+    #[account(
+        mut,
+        seeds = [b"reserve".as_ref(), mint.key().as_ref(), pool_seed(pool_id).as_ref()],
+        bump = reserve.bump,
+    )]
+    pub reserve: Account<'info, Reserve>, // fee bookkeeping for this mint
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = authority,
+    )]
+    pub destination: Account<'info, TokenAccount>, // authority ATA receiving the fees
+
+    pub token_program: Program<'info, Token>,
+}
+
+pub fn handler(ctx: Context<WithdrawFees>, pool_id: u64, amount: u64) -> Result<()> {
+
+    require!(amount > 0, ProtocolError::InvalidAmount);
+
+    // only accrued fees can be withdrawn, the rest is lending liquidity,
+    // and rebates borrowers have earned are held back for them
+    let reserve = &mut ctx.accounts.reserve;
+    require!(amount <= reserve.free_fees(), ProtocolError::NotEnoughFunds);
+    reserve.accrued_fees = reserve.accrued_fees.checked_sub(amount).ok_or(ProtocolError::NotEnoughFunds)?;
+
+    // never let the vault drop below the minimum liquidity
+    let remaining = ctx.accounts.protocol_ata.amount.checked_sub(amount).ok_or(ProtocolError::NotEnoughFunds)?;
+    require!(remaining >= ctx.accounts.config.min_liquidity, ProtocolError::BelowMinLiquidity);
+
+    // same signer seeds as borrow
+    let pool = pool_seed(pool_id);
+    let seeds = &[
+        b"protocol".as_ref(),
+        pool.as_ref(),
+        &[ctx.bumps.protocol]
+    ];
+
+    let signer_seeds = &[&seeds[..]];
+
+    // transfer the fees from the protocol to the destination
+    transfer(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.protocol_ata.to_account_info(),
+                to: ctx.accounts.destination.to_account_info(),
+                authority: ctx.accounts.protocol.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        amount,
+    )?;
+
+    Ok(())
+}
@@ -1,21 +1,15 @@
 use anchor_lang::prelude::*;
-use anchor_spl::{
-    token::{Token, TokenAccount, Mint, Transfer, transfer},
-    associated_token::AssociatedToken,    
-};
 
-use anchor_lang::{
-    Discriminator,
-    solana_program::sysvar::instructions::{
-        ID as INSTRUCTIONS_SYSVAR_ID,
-        load_current_index_checked,
-        load_instruction_at_checked
-    }
-};
-
-mod state;
+pub mod state;
+pub mod fees;
+pub mod oracle;
 mod errors;
-use errors::*;
+mod instructions;
+#[cfg(test)]
+mod test_utils;
+use instructions::*;
+use fees::RepayResult;
+use state::FeeTier;
 
 declare_id!("22222222222222222222222222222222222222222222");
 
@@ -23,139 +17,186 @@ declare_id!("22222222222222222222222222222222222222222222");
 pub mod flash_loan {
     use super::*;
 
-    pub fn borrow(ctx: Context<Loan>, borrow_amount: u64) -> Result<()> {
-        
-        // check if borrow amount is greater than 0
-        require!(borrow_amount > 0, ProtocolError::InvalidAmount);
-
-        // derive signer seeds for the protocol account necessary to sign tranfer transaction
-        let seeds = &[
-            b"protocol".as_ref(),
-            &[ctx.bumps.protocol]
-        ];
-
-        let signer_seeds = &[&seeds[..]];
-
-        // transfer the funds from the protocol to the borrower
-        transfer(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.protocol_ata.to_account_info(),
-                    to: ctx.accounts.borrower_ata.to_account_info(),
-                    authority: ctx.accounts.protocol.to_account_info(),
-                },
-                signer_seeds,
-            ),
-            borrow_amount,
-        )?;
-
-        // Instruction Introspection to verify repayment instruction
-        let ixs = ctx.accounts.instructions.to_account_info();
-
-        // Check if borrow instruction is the first instruction in the transaction.
-        let current_index = load_current_index_checked(&ctx.accounts.instructions)?;
-        require_eq!(current_index, 0, ProtocolError::InvalidIx); 
-
-        // Check how many instruction we have in this transaction
-        let instruction_sysvar = ixs.try_borrow_data()?;
-        let len = u16::from_le_bytes(instruction_sysvar[0..2].try_into().unwrap());
-
-        // Ensure we have a repay instruction
-        if let Ok(repay_ix) = load_instruction_at_checked(len as usize - 1, &ixs) {
-
-            // Instruction checks
-            require_keys_eq!(repay_ix.program_id, ID, ProtocolError::InvalidProgram);
-            require!(repay_ix.data[0..8].eq(instruction::Repay::DISCRIMINATOR), ProtocolError::InvalidIx);
-
-            // verify ATA accounts
-            require_keys_eq!(repay_ix.accounts.get(3).ok_or(ProtocolError::InvalidBorrowerAta)?.pubkey, ctx.accounts.borrower_ata.key(), ProtocolError::InvalidBorrowerAta);
-            require_keys_eq!(repay_ix.accounts.get(4).ok_or(ProtocolError::InvalidProtocolAta)?.pubkey, ctx.accounts.protocol_ata.key(), ProtocolError::InvalidProtocolAta);
-
-        } else {
-            return Err(ProtocolError::MissingRepayIx.into());
-        }
-
-
-        Ok(())
-    }
-
-    pub fn repay(ctx: Context<Loan>) -> Result<()> {
-
-        
-        let ixs = ctx.accounts.instructions.to_account_info();
-
-        let mut amount_borrowed: u64;
-
-        if let Ok(borrow_ix) = load_instruction_at_checked(0, &ixs) {
-            
-            // Check the amount borrowed:
-            let mut borrowed_data: [u8;8] = [0u8;8];
-            borrowed_data.copy_from_slice(&borrow_ix.data[8..16]);
-            amount_borrowed = u64::from_le_bytes(borrowed_data)
-
-        } else {
-            return Err(ProtocolError::MissingBorrowIx.into());
-        }
-
-        // Add the fee to the amount borrowed (hardcoded to 500 basis point)
-        let fee = (amount_borrowed as u128).checked_mul(500).unwrap().checked_div(10_000).ok_or(ProtocolError::Overflow)? as u64;
-        amount_borrowed = amount_borrowed.checked_add(fee).ok_or(ProtocolError::Overflow)?;
-
-        // Transfer the funds from the protocol to the borrower
-        transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(), 
-                Transfer {
-                    from: ctx.accounts.borrower_ata.to_account_info(),
-                    to: ctx.accounts.protocol_ata.to_account_info(),
-                    authority: ctx.accounts.borrower.to_account_info(),
-                }
-            ), 
-            amount_borrowed
-        )?;
-
-        Ok(())
+    pub fn initialize(ctx: Context<Initialize>, max_loans_per_tx: u8, min_liquidity: u64) -> Result<()> {
+        instructions::initialize::handler(ctx, max_loans_per_tx, min_liquidity)
+    }
+
+    pub fn initialize_protocol(ctx: Context<InitializeProtocol>, pool_id: u64) -> Result<()> {
+        instructions::initialize_protocol::handler(ctx, pool_id)
+    }
+
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused: bool) -> Result<()> {
+        instructions::admin::set_paused(ctx, paused)
+    }
+
+    pub fn set_max_borrow(ctx: Context<UpdateConfig>, max_borrow: u64) -> Result<()> {
+        instructions::admin::set_max_borrow(ctx, max_borrow)
+    }
+
+    pub fn set_min_liquidity(ctx: Context<UpdateConfig>, min_liquidity: u64) -> Result<()> {
+        instructions::admin::set_min_liquidity(ctx, min_liquidity)
+    }
+
+    pub fn set_min_fee(ctx: Context<UpdateConfig>, min_fee: u64) -> Result<()> {
+        instructions::admin::set_min_fee(ctx, min_fee)
+    }
+
+    pub fn set_max_global_exposure(ctx: Context<UpdateConfig>, max_global_exposure: u64) -> Result<()> {
+        instructions::admin::set_max_global_exposure(ctx, max_global_exposure)
+    }
+
+    pub fn set_min_bond(ctx: Context<UpdateConfig>, min_bond: u64) -> Result<()> {
+        instructions::admin::set_min_bond(ctx, min_bond)
+    }
+
+    pub fn set_rebate_every(ctx: Context<UpdateConfig>, rebate_every: u64) -> Result<()> {
+        instructions::admin::set_rebate_every(ctx, rebate_every)
+    }
+
+    pub fn set_dex_program(ctx: Context<UpdateConfig>, dex_program: Pubkey) -> Result<()> {
+        instructions::admin::set_dex_program(ctx, dex_program)
+    }
+
+    pub fn set_fee_tiers(ctx: Context<UpdateConfig>, fee_tiers: Vec<FeeTier>) -> Result<()> {
+        instructions::admin::set_fee_tiers(ctx, fee_tiers)
+    }
+
+    pub fn set_usd_fee(ctx: Context<UpdateConfig>, usd_fee: u64, fee_oracle: Pubkey, max_oracle_age: i64) -> Result<()> {
+        instructions::admin::set_usd_fee(ctx, usd_fee, fee_oracle, max_oracle_age)
+    }
+
+    pub fn add_waiver(ctx: Context<ManageWaivers>, borrower: Pubkey) -> Result<()> {
+        instructions::fee_waiver::add_waiver(ctx, borrower)
+    }
+
+    pub fn remove_waiver(ctx: Context<ManageWaivers>, borrower: Pubkey) -> Result<()> {
+        instructions::fee_waiver::remove_waiver(ctx, borrower)
+    }
+
+    pub fn propose_authority(ctx: Context<UpdateConfig>, new_authority: Pubkey) -> Result<()> {
+        instructions::admin::propose_authority(ctx, new_authority)
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        instructions::admin::accept_authority(ctx)
+    }
+
+    pub fn post_bond(ctx: Context<PostBond>, amount: u64) -> Result<()> {
+        instructions::bond::post_bond(ctx, amount)
+    }
+
+    pub fn request_bond_unlock(ctx: Context<WithdrawBond>) -> Result<()> {
+        instructions::bond::request_bond_unlock(ctx)
+    }
+
+    pub fn withdraw_bond(ctx: Context<WithdrawBond>, amount: u64) -> Result<()> {
+        instructions::bond::withdraw_bond(ctx, amount)
+    }
+
+    pub fn report_failures(ctx: Context<KeeperBond>, count: u64) -> Result<()> {
+        instructions::bond::report_failures(ctx, count)
+    }
+
+    pub fn slash_bond(ctx: Context<KeeperBond>) -> Result<()> {
+        instructions::bond::slash_bond(ctx)
+    }
+
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, pool_id: u64, amount: u64) -> Result<()> {
+        instructions::withdraw_fees::handler(ctx, pool_id, amount)
+    }
+
+    pub fn deposit_liquidity(ctx: Context<DepositLiquidity>, pool_id: u64, amount: u64) -> Result<()> {
+        instructions::liquidity::deposit_liquidity(ctx, pool_id, amount)
+    }
+
+    pub fn withdraw_liquidity(ctx: Context<WithdrawLiquidity>, pool_id: u64, amount: u64) -> Result<()> {
+        instructions::liquidity::withdraw_liquidity(ctx, pool_id, amount)
+    }
+
+    pub fn migrate_reserve(ctx: Context<MigrateReserve>, pool_id: u64) -> Result<()> {
+        instructions::migrate::migrate_reserve(ctx, pool_id)
+    }
+
+    pub fn migrate_loan_record(ctx: Context<MigrateLoanRecord>, pool_id: u64) -> Result<()> {
+        instructions::migrate::migrate_loan_record(ctx, pool_id)
+    }
+
+    pub fn compound_fees(ctx: Context<CompoundFees>, pool_id: u64) -> Result<()> {
+        instructions::compound_fees::handler(ctx, pool_id)
+    }
+
+    pub fn claim_rebate(ctx: Context<ClaimRebate>, pool_id: u64) -> Result<()> {
+        instructions::claim_rebate::handler(ctx, pool_id)
+    }
+
+    pub fn quote_fee(ctx: Context<QuoteFee>, borrow_amount: u64) -> Result<RepayResult> {
+        instructions::quote_fee::handler(ctx, borrow_amount)
+    }
+
+    pub fn borrow(ctx: Context<Loan>, pool_id: u64, borrow_amount: u64) -> Result<()> {
+        instructions::borrow::handler(ctx, pool_id, borrow_amount)
+    }
+
+    pub fn borrow_with_bond(ctx: Context<Loan>, pool_id: u64, borrow_amount: u64) -> Result<()> {
+        instructions::borrow::handler_with_bond(ctx, pool_id, borrow_amount)
+    }
+
+    pub fn repay(ctx: Context<Loan>, pool_id: u64) -> Result<RepayResult> {
+        instructions::repay::handler(ctx, pool_id)
     } 
 
+    pub fn arb<'info>(ctx: Context<'_, '_, 'info, 'info, Arb<'info>>, pool_id: u64, borrow_amount: u64, legs: Vec<SwapLeg>) -> Result<RepayResult> {
+        instructions::arb::handler(ctx, pool_id, borrow_amount, legs)
+    }
+
+}
+
+#[event]
+pub struct BorrowEvent {
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
 }
 
-#[derive(Accounts)]
-pub struct Loan<'info> {
-
-    #[account(mut)]
-    pub borrower: Signer<'info>, // borrower account
-
-    
-    #[account(
-        seeds = [b"protocol".as_ref()],
-        bump,
-    )]
-    pub protocol: SystemAccount<'info>, // pda account for protocol
-
-    pub mint: Account<'info, Mint>, // mint account
-
-    #[account(
-        init_if_needed, // only initialize account if borrower doesn't have one yet
-        payer = borrower,
-        associated_token::mint = mint,
-        associated_token::authority = borrower,
-    )]
-    pub borrower_ata: Account<'info, TokenAccount>, // ATA account needed for borrower to hold mint account
-
-    #[account(
-        mut, 
-        associated_token::mint = mint,
-        associated_token::authority = protocol,
-    )]
-    pub protocol_ata: Account<'info, TokenAccount>, // ATA account needed for protocol to hold mint account
-
-    #[account(address = INSTRUCTIONS_SYSVAR_ID)]
-    /// CHECK: InstructionSysvar account
-    instructions: UncheckedAccount<'info>,
-
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct RepayEvent {
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
 }
 
+#[event]
+pub struct DepositEvent {
+    pub depositor: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct WithdrawEvent {
+    pub provider: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub shares: u64,
+}
+
+#[event]
+pub struct CompoundEvent {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct RebateEvent {
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct QuoteEvent {
+    pub amount: u64,
+    pub fee: u64,
+    pub total: u64,
+}
@@ -0,0 +1,119 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::ProtocolError;
+
+/// Decimals of `PriceFeed::price` and `Config::usd_fee`
+pub const USD_DECIMALS: u32 = 6;
+
+/// Layout a price feed account must have to be read by `repay`.
+///
+/// Feeds are only trusted when owned by `Config::fee_oracle`, the program
+/// that publishes them.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct PriceFeed {
+    pub mint: Pubkey,
+    pub price: u64, // USD per whole token, with USD_DECIMALS decimals
+    pub updated_at: i64, // unix timestamp of the last update
+}
+
+impl PriceFeed {
+    pub fn read(account: &AccountInfo, oracle_program: &Pubkey, mint: &Pubkey) -> Result<Self> {
+
+        // a feed published by anyone else could name any price
+        require_keys_eq!(*account.owner, *oracle_program, ProtocolError::InvalidOracle);
+
+        let data = account.try_borrow_data()?;
+        let feed = Self::deserialize(&mut &data[..]).map_err(|_| ProtocolError::InvalidOracle)?;
+
+        require_keys_eq!(feed.mint, *mint, ProtocolError::InvalidOracle);
+        require!(feed.price > 0, ProtocolError::InvalidOracle);
+
+        Ok(feed)
+    }
+
+    /// Reject prices older than `max_age` seconds
+    pub fn check_fresh(&self, now: i64, max_age: i64) -> Result<()> {
+        require!(now.saturating_sub(self.updated_at) <= max_age, ProtocolError::StaleOracle);
+
+        Ok(())
+    }
+
+    /// Token base units worth `usd_amount`, rounded up so the fee never falls short
+    pub fn usd_to_tokens(&self, usd_amount: u64, decimals: u8) -> Result<u64> {
+        let tokens = (usd_amount as u128)
+            .checked_mul(10u128.checked_pow(decimals as u32).ok_or(ProtocolError::Overflow)?)
+            .ok_or(ProtocolError::Overflow)?
+            .div_ceil(self.price as u128);
+
+        u64::try_from(tokens).map_err(|_| ProtocolError::Overflow.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fees::loan_repayment;
+    use crate::test_utils::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    /// Price feed account published by `oracle` at `updated_at`
+    fn feed(oracle: Pubkey, mint: Pubkey, price: u64, updated_at: i64) -> AccountInfo<'static> {
+        let data = PriceFeed { mint, price, updated_at }.try_to_vec().unwrap();
+        account_info(Pubkey::new_unique(), TestAccount { lamports: 1, data, owner: oracle })
+    }
+
+    fn usd_config(usd_fee: u64) -> crate::state::Config {
+        let mut config = default_config(Pubkey::new_unique());
+        config.usd_fee = usd_fee;
+        config.fee_oracle = Pubkey::new_unique();
+        config.max_oracle_age = 60;
+        config
+    }
+
+    #[test]
+    fn usd_fee_is_converted_at_the_feed_price() {
+        let config = usd_config(2_000_000);
+        let mint = Pubkey::new_unique();
+
+        // $2 at $0.50 per token is 4 tokens, whatever the principal
+        let price_feed = feed(config.fee_oracle, mint, 500_000, NOW);
+        let result = loan_repayment(&config, 1_000_000_000, false, Some(&price_feed), &mint, 6, NOW).unwrap();
+        assert_eq!(result.fee, 4_000_000);
+        assert_eq!(loan_repayment(&config, 1, false, Some(&price_feed), &mint, 9, NOW).unwrap().fee, 4_000_000_000);
+
+        // rounded up so the fee never falls short
+        let price_feed = feed(config.fee_oracle, mint, 3_000_000, NOW);
+        assert_eq!(loan_repayment(&config, 1, false, Some(&price_feed), &mint, 0, NOW).unwrap().fee, 1);
+        assert_eq!(loan_repayment(&config, 1, false, Some(&price_feed), &mint, 6, NOW).unwrap().fee, 666_667);
+    }
+
+    #[test]
+    fn stale_prices_are_rejected() {
+        let config = usd_config(1_000_000);
+        let mint = Pubkey::new_unique();
+
+        let price_feed = feed(config.fee_oracle, mint, 1_000_000, NOW - 60);
+        assert!(loan_repayment(&config, 1_000, false, Some(&price_feed), &mint, 6, NOW).is_ok());
+
+        let price_feed = feed(config.fee_oracle, mint, 1_000_000, NOW - 61);
+        assert_eq!(err(loan_repayment(&config, 1_000, false, Some(&price_feed), &mint, 6, NOW)), anchor_error(ProtocolError::StaleOracle));
+    }
+
+    #[test]
+    fn only_feeds_of_the_configured_oracle_are_read() {
+        let config = usd_config(1_000_000);
+        let mint = Pubkey::new_unique();
+
+        let forged = feed(Pubkey::new_unique(), mint, 1_000_000, NOW);
+        assert_eq!(err(loan_repayment(&config, 1_000, false, Some(&forged), &mint, 6, NOW)), anchor_error(ProtocolError::InvalidOracle));
+
+        let other_mint = feed(config.fee_oracle, Pubkey::new_unique(), 1_000_000, NOW);
+        assert_eq!(err(loan_repayment(&config, 1_000, false, Some(&other_mint), &mint, 6, NOW)), anchor_error(ProtocolError::InvalidOracle));
+
+        let zero = feed(config.fee_oracle, mint, 0, NOW);
+        assert_eq!(err(loan_repayment(&config, 1_000, false, Some(&zero), &mint, 6, NOW)), anchor_error(ProtocolError::InvalidOracle));
+
+        assert_eq!(err(loan_repayment(&config, 1_000, false, None, &mint, 6, NOW)), anchor_error(ProtocolError::MissingOracle));
+    }
+}
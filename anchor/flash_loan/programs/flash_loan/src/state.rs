@@ -1,11 +1,553 @@
 use anchor_lang::prelude::*;
+
+use crate::errors::ProtocolError;
+use crate::fees::{flat_repayment, RepayResult, FEE_BPS, MAX_BORROW};
  
+/// Last loan of a borrower for one mint in one pool.
+///
+/// `borrow` records the principal and the fee it owes and opens it, `repay`
+/// charges exactly `amount + fee` from the record and closes it again.
+/// `version` leads the layout so later fields can be migrated in.
 #[derive(InitSpace)]
 #[account]
-pub struct Loan {
+pub struct LoanRecord {
+    pub version: u8,
     pub borrower: Pubkey,
     pub mint: Pubkey,
     pub amount: u64,
     pub fee: u64,
+    pub open: bool, // only true between borrow and repay of the same tx
     pub bump: u8,
-}
\ No newline at end of file
+}
+
+impl LoanRecord {
+    /// Layout written by this program
+    pub const VERSION: u8 = 1;
+
+    pub const LEN: usize = Self::DISCRIMINATOR.len() + Self::INIT_SPACE;
+
+    /// Records written before `version` led the layout, `migrate_loan_record` grows them
+    pub const LEGACY_LEN: usize = Self::LEN - 1;
+
+    pub fn check_version(&self) -> Result<()> {
+        require!(self.version == Self::VERSION, ProtocolError::UnsupportedLoanVersion);
+
+        Ok(())
+    }
+
+    /// What `repay` charges, the fee fixed by `borrow`
+    pub fn repayment(&self) -> Result<RepayResult> {
+        flat_repayment(self.amount, self.fee, 0)
+    }
+}
+
+/// Extra PDA seed for a liquidity pool.
+///
+/// Pool 0 maps to an empty seed, so its `protocol` and `reserve` addresses
+/// are the ones from before pools existed and its liquidity stays put.
+pub fn pool_seed(pool_id: u64) -> Vec<u8> {
+    if pool_id == 0 {
+        Vec::new()
+    } else {
+        pool_id.to_le_bytes().to_vec()
+    }
+}
+
+#[derive(InitSpace)]
+#[account]
+pub struct Config {
+    pub authority: Pubkey,
+    pub max_loans_per_tx: u8,
+    pub paused: bool,
+    pub min_liquidity: u64,
+    pub max_borrow: u64, // 0 means unlimited
+    pub min_fee: u64, // fee floor per loan
+    pub max_global_exposure: u64, // cap on the principal open at once in a tx, 0 means unlimited
+    pub min_bond: u64, // lamports every borrower must have bonded, 0 disables bonds
+    pub total_borrowed: u128,
+    pub total_fees_collected: u128,
+    pub pending_authority: Pubkey, // proposed next authority, default when none
+    pub rebate_every: u64, // loans per claimable rebate, 0 disables rebates
+    pub dex_program: Pubkey, // program arb routes its swaps through, default disables arb
+    pub usd_fee: u64, // flat fee per loan in USD with USD_DECIMALS decimals, 0 keeps the bps fee
+    pub fee_oracle: Pubkey, // program owning the price feeds the USD fee is converted with
+    pub max_oracle_age: i64, // seconds a price feed stays usable
+    #[max_len(MAX_FEE_TIERS)]
+    pub fee_tiers: Vec<FeeTier>, // ascending breakpoints, empty means FEE_BPS for every loan
+    pub bump: u8,
+}
+
+/// Breakpoints a fee schedule can hold
+pub const MAX_FEE_TIERS: usize = 4;
+
+/// Loans of at least `threshold` pay `fee_bps`, up to the next tier
+#[derive(InitSpace, AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct FeeTier {
+    pub threshold: u64,
+    pub fee_bps: u64,
+}
+
+impl Config {
+    /// Gates every new loan goes through, borrow and arb alike
+    pub fn check_borrow(&self, amount: u64) -> Result<()> {
+
+        // no new loans while the protocol is paused
+        require!(!self.paused, ProtocolError::Paused);
+
+        // check if borrow amount is greater than 0
+        require!(amount > 0, ProtocolError::InvalidAmount);
+
+        // make sure the repayment can't overflow
+        require!(amount <= MAX_BORROW, ProtocolError::AmountTooLarge);
+
+        // check the per-loan cap, 0 means unlimited
+        require!(self.max_borrow == 0 || amount <= self.max_borrow, ProtocolError::MaxBorrowExceeded);
+
+        Ok(())
+    }
+
+    /// Checks the principal out at once in a transaction against the global cap.
+    /// Worked out from the instructions sysvar, so loans don't write it anywhere.
+    pub fn check_exposure(&self, open_exposure: u64) -> Result<()> {
+
+        // 0 means unlimited
+        require!(
+            self.max_global_exposure == 0 || open_exposure <= self.max_global_exposure,
+            ProtocolError::GlobalExposureExceeded
+        );
+
+        Ok(())
+    }
+
+    /// Adds a repaid loan to the lifetime totals
+    pub fn settle_loan(&mut self, principal: u64, fee: u64) -> Result<()> {
+
+        self.total_borrowed = self.total_borrowed.checked_add(principal as u128).ok_or(ProtocolError::Overflow)?;
+        self.total_fees_collected = self.total_fees_collected.checked_add(fee as u128).ok_or(ProtocolError::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Rate of the highest tier the loan reaches, FEE_BPS below the first one
+    pub fn fee_bps(&self, amount: u64) -> u64 {
+        self.fee_tiers
+            .iter()
+            .rev()
+            .find(|tier| amount >= tier.threshold)
+            .map_or(FEE_BPS, |tier| tier.fee_bps)
+    }
+}
+
+/// Shares nobody owns priced into every deposit and withdrawal.
+///
+/// Together with one virtual token of liquidity they make donating to the vault
+/// to inflate the share price cost the donor more than it can take from the next LP.
+pub const VIRTUAL_SHARES: u64 = 1_000;
+
+/// Per-mint bookkeeping for the protocol vault.
+///
+/// Fees paid on repay land in `protocol_ata` but are tracked as
+/// `accrued_fees` until they are either withdrawn by the authority or
+/// compounded into the lending reserve, only the rest can be borrowed.
+/// Fields are only ever appended, `migrate_reserve` grows older accounts.
+#[derive(InitSpace)]
+#[account]
+pub struct Reserve {
+    pub mint: Pubkey,
+    pub accrued_fees: u64,
+    pub bump: u8,
+    pub total_shares: u64, // LP shares outstanding, plus the ones seeded for out-of-band liquidity
+    pub reserved_rebates: u64, // part of accrued_fees borrowers have earned back as rebates
+}
+
+impl Reserve {
+    pub const LEN: usize = Self::DISCRIMINATOR.len() + Self::INIT_SPACE;
+
+    /// Accrued fees no borrower has a rebate on, what can be withdrawn or compounded
+    pub fn free_fees(&self) -> u64 {
+        self.accrued_fees.saturating_sub(self.reserved_rebates)
+    }
+
+    /// Holds back `after` instead of `before` for one borrower's unclaimed rebates
+    pub fn rebook_rebates(&mut self, before: u64, after: u64) -> Result<()> {
+        self.reserved_rebates = self.reserved_rebates.saturating_sub(before).checked_add(after).ok_or(ProtocolError::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Part of the vault LPs own and borrowers can take, the accrued fees excluded
+    pub fn lendable(&self, vault: u64) -> u64 {
+        vault.saturating_sub(self.accrued_fees)
+    }
+
+    /// Shares minted for depositing `amount` into `liquidity`, rounded down
+    pub fn shares_for_deposit(&self, amount: u64, liquidity: u64) -> Option<u64> {
+        let shares = (amount as u128)
+            .checked_mul(self.total_shares as u128 + VIRTUAL_SHARES as u128)?
+            / (liquidity as u128 + 1);
+        u64::try_from(shares).ok()
+    }
+
+    /// Shares burned for withdrawing `amount` out of `liquidity`, rounded up
+    pub fn shares_for_withdrawal(&self, amount: u64, liquidity: u64) -> Option<u64> {
+        let shares = (amount as u128)
+            .checked_mul(self.total_shares as u128 + VIRTUAL_SHARES as u128)?
+            .div_ceil(liquidity as u128 + 1);
+        u64::try_from(shares).ok()
+    }
+
+    /// Books a deposit of `amount` into a vault holding `vault`, returns the shares minted
+    pub fn deposit(&mut self, amount: u64, vault: u64) -> Result<u64> {
+        let liquidity = self.lendable(vault);
+
+        // liquidity funded out of band before any LP joined stays with the protocol,
+        // it's seeded as shares nobody owns
+        if self.total_shares == 0 {
+            self.total_shares = liquidity;
+        }
+
+        let shares = self.shares_for_deposit(amount, liquidity).ok_or(ProtocolError::Overflow)?;
+        require!(shares > 0, ProtocolError::InvalidAmount);
+        self.total_shares = self.total_shares.checked_add(shares).ok_or(ProtocolError::Overflow)?;
+
+        Ok(shares)
+    }
+
+    /// Books a withdrawal of `amount` out of a vault holding `vault`, returns the shares burned
+    pub fn withdraw(&mut self, amount: u64, vault: u64) -> Result<u64> {
+        let liquidity = self.lendable(vault);
+        require!(amount <= liquidity, ProtocolError::NotEnoughFunds);
+
+        // rounding in the pool's favour
+        let shares = self.shares_for_withdrawal(amount, liquidity).ok_or(ProtocolError::Overflow)?;
+        self.total_shares = self.total_shares.checked_sub(shares).ok_or(ProtocolError::InsufficientShares)?;
+
+        Ok(shares)
+    }
+}
+
+/// One LP's stake in a pool's lendable liquidity for a mint.
+///
+/// Shares are priced against the vault minus the accrued fees, so fees reach
+/// LPs pro rata once they are compounded.
+#[derive(InitSpace)]
+#[account]
+pub struct LiquidityPosition {
+    pub owner: Pubkey,
+    pub mint: Pubkey,
+    pub shares: u64,
+    pub bump: u8,
+}
+
+/// Failure share, in basis points, above which a keeper may slash a bond
+pub const SLASH_FAILURE_BPS: u64 = 5_000;
+
+/// Seconds between an unlock request and the withdrawal, for the keeper to report the last loans
+pub const BOND_UNLOCK_DELAY: i64 = 24 * 60 * 60;
+
+/// SOL a borrower locks up as a rate-limit bond.
+///
+/// Failed loans revert, so they can't be counted on-chain. A keeper reports
+/// them instead, successes are counted by `repay`, and once failures make up
+/// more than `SLASH_FAILURE_BPS` of all attempts the keeper can slash the bond.
+/// Withdrawing takes an unlock request `BOND_UNLOCK_DELAY` ahead, and a bond
+/// being unlocked no longer backs loans, so every loan gets reported in time.
+#[derive(InitSpace)]
+#[account]
+pub struct Bond {
+    pub borrower: Pubkey,
+    pub amount: u64, // lamports posted, on top of rent
+    pub successes: u64,
+    pub failures: u64,
+    pub unlock_requested_at: i64, // 0 unless a withdrawal is pending
+    pub bump: u8,
+}
+
+impl Bond {
+    pub fn is_slashable(&self) -> bool {
+        let attempts = self.successes as u128 + self.failures as u128;
+        attempts > 0 && self.failures as u128 * 10_000 > attempts * SLASH_FAILURE_BPS as u128
+    }
+
+    pub fn is_unlocking(&self) -> bool {
+        self.unlock_requested_at != 0
+    }
+
+    /// Whether the unlock request has cooled down at `now`
+    pub fn is_unlocked(&self, now: i64) -> bool {
+        self.is_unlocking() && now >= self.unlock_requested_at.saturating_add(BOND_UNLOCK_DELAY)
+    }
+}
+
+/// Per-borrower loyalty record for one mint in one pool.
+///
+/// `repay` counts the loan and its fee, and every `Config::rebate_every`
+/// loans the borrower can claim back their average fee, one loan for free.
+#[derive(InitSpace)]
+#[account]
+pub struct BorrowerStats {
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub loans: u64, // lifetime repaid loans
+    pub loans_since_claim: u64,
+    pub fees_since_claim: u64,
+    pub bump: u8,
+}
+
+impl BorrowerStats {
+    /// Average fee since the last claim, 0 until enough loans were repaid
+    pub fn rebate(&self, rebate_every: u64) -> u64 {
+        if rebate_every == 0 || self.loans_since_claim < rebate_every {
+            return 0;
+        }
+        self.fees_since_claim / self.loans_since_claim
+    }
+
+    /// Every rebate earned and not claimed yet
+    pub fn owed(&self, rebate_every: u64) -> u64 {
+        match self.loans_since_claim.checked_div(rebate_every) {
+            Some(rebates) => self.rebate(rebate_every).saturating_mul(rebates),
+            None => 0,
+        }
+    }
+
+    pub fn record_loan(&mut self, fee: u64) -> Result<()> {
+        self.loans = self.loans.checked_add(1).ok_or(ProtocolError::Overflow)?;
+        self.loans_since_claim = self.loans_since_claim.checked_add(1).ok_or(ProtocolError::Overflow)?;
+        self.fees_since_claim = self.fees_since_claim.checked_add(fee).ok_or(ProtocolError::Overflow)?;
+
+        Ok(())
+    }
+
+    /// Pays out one rebate, the loans past it keep counting towards the next one
+    pub fn claim(&mut self, rebate_every: u64) -> Result<u64> {
+        let amount = self.rebate(rebate_every);
+        require!(amount > 0, ProtocolError::NoRebate);
+
+        self.loans_since_claim -= rebate_every;
+        self.fees_since_claim = self.fees_since_claim.saturating_sub(amount.saturating_mul(rebate_every));
+
+        Ok(amount)
+    }
+}
+
+/// Borrowers a `FeeWaiver` list can hold
+pub const MAX_WAIVERS: usize = 16;
+
+/// Borrowers that repay without a fee, managed by the protocol authority
+#[derive(InitSpace)]
+#[account]
+pub struct FeeWaiver {
+    #[max_len(MAX_WAIVERS)]
+    pub borrowers: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl FeeWaiver {
+    pub fn is_waived(&self, borrower: &Pubkey) -> bool {
+        self.borrowers.contains(borrower)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn pool_zero_keeps_the_addresses_from_before_pools() {
+        let mint = Pubkey::new_unique();
+        assert_eq!(pda(&[b"protocol", &pool_seed(0)]), pda(&[b"protocol"]));
+        assert_eq!(pda(&[b"reserve", mint.as_ref(), &pool_seed(0)]), pda(&[b"reserve", mint.as_ref()]));
+
+        // every other pool gets vaults of its own
+        let protocols: Vec<Pubkey> = (0..4).map(|pool_id| pda(&[b"protocol", &pool_seed(pool_id)])).collect();
+        for (index, protocol) in protocols.iter().enumerate() {
+            assert!(!protocols[index + 1..].contains(protocol));
+        }
+        assert_eq!(pool_seed(1), 1u64.to_le_bytes());
+    }
+
+    #[test]
+    fn borrows_above_the_cap_are_rejected() {
+        let mut config = default_config(Pubkey::new_unique());
+        config.max_borrow = 1_000;
+
+        assert!(config.check_borrow(1_000).is_ok());
+        assert_eq!(err(config.check_borrow(1_001)), anchor_error(ProtocolError::MaxBorrowExceeded));
+
+        // 0 lifts the cap
+        config.max_borrow = 0;
+        assert!(config.check_borrow(1_000_000_000).is_ok());
+    }
+
+    #[test]
+    fn nothing_can_be_borrowed_while_paused() {
+        let mut config = default_config(Pubkey::new_unique());
+        config.paused = true;
+
+        assert_eq!(err(config.check_borrow(1)), anchor_error(ProtocolError::Paused));
+        assert_eq!(err(default_config(Pubkey::new_unique()).check_borrow(0)), anchor_error(ProtocolError::InvalidAmount));
+    }
+
+    #[test]
+    fn open_exposure_is_capped_globally() {
+        let mut config = default_config(Pubkey::new_unique());
+        config.max_global_exposure = 1_000;
+
+        config.check_exposure(1_000).unwrap();
+        assert_eq!(err(config.check_exposure(1_001)), anchor_error(ProtocolError::GlobalExposureExceeded));
+    }
+
+    #[test]
+    fn repaid_loans_add_up_in_the_totals() {
+        let mut config = default_config(Pubkey::new_unique());
+
+        config.settle_loan(600, 30).unwrap();
+        config.settle_loan(400, 20).unwrap();
+        assert_eq!(config.total_borrowed, 1_000);
+        assert_eq!(config.total_fees_collected, 50);
+    }
+
+    fn reserve() -> Reserve {
+        Reserve { mint: Pubkey::new_unique(), accrued_fees: 0, bump: 255, total_shares: 0, reserved_rebates: 0 }
+    }
+
+    /// Tokens `shares` can be redeemed for out of `liquidity`
+    fn value(reserve: &Reserve, shares: u64, liquidity: u64) -> u64 {
+        (shares as u128 * (liquidity as u128 + 1) / (reserve.total_shares as u128 + VIRTUAL_SHARES as u128)) as u64
+    }
+
+    #[test]
+    fn deposit_into_an_out_of_band_vault_mints_shares_at_par() {
+        let mut reserve = reserve();
+
+        // the vault was funded before any LP, those tokens stay with the protocol
+        let shares = reserve.deposit(1_000, 9_000).unwrap();
+        assert_eq!(reserve.total_shares, 9_000 + shares);
+        assert!((999..=1_000).contains(&value(&reserve, shares, 10_000)));
+
+        // the next deposit is priced against everything in the vault
+        let more = reserve.deposit(1_000, 10_000).unwrap();
+        assert!((999..=1_000).contains(&value(&reserve, more, 11_000)));
+        assert!((999..=1_000).contains(&value(&reserve, shares, 11_000)));
+    }
+
+    #[test]
+    fn deposits_too_small_for_a_share_are_rejected() {
+        let mut reserve = reserve();
+        assert_eq!(err(reserve.deposit(0, 0)), anchor_error(ProtocolError::InvalidAmount));
+
+        // accrued fees don't count as liquidity, so they can't dilute a deposit
+        reserve.accrued_fees = 5_000;
+        let shares = reserve.deposit(100, 5_000).unwrap();
+        assert_eq!(shares, 100 * VIRTUAL_SHARES);
+    }
+
+    #[test]
+    fn donation_to_an_empty_vault_doesnt_steal_the_next_deposit() {
+        let mut reserve = reserve();
+
+        // attacker deposits one token, then donates straight to the vault
+        let attacker = reserve.deposit(1, 0).unwrap();
+        let donation = 1_000_000;
+        let vault = 1 + donation;
+
+        // the victim still gets shares and can withdraw nearly all of the deposit
+        let victim = reserve.deposit(1_000_000, vault).unwrap();
+        assert!(victim > 0);
+        let vault = vault + 1_000_000;
+        assert!(value(&reserve, victim, vault) >= 999_000);
+
+        // while the attacker gets back less than they put in
+        assert!(value(&reserve, attacker, vault) < donation);
+    }
+
+    #[test]
+    fn shares_are_priced_without_dividing_by_zero() {
+        let mut reserve = reserve();
+        reserve.total_shares = 500;
+
+        // every share withdrawn, fees left the vault empty
+        assert_eq!(reserve.shares_for_deposit(100, 0), Some(150_000));
+        assert_eq!(reserve.shares_for_withdrawal(0, 0), Some(0));
+        assert_eq!(err(reserve.withdraw(1, 0)), anchor_error(ProtocolError::NotEnoughFunds));
+    }
+
+    #[test]
+    fn accrued_fees_are_not_lendable() {
+        let mut reserve = reserve();
+        reserve.accrued_fees = 300;
+
+        assert_eq!(reserve.lendable(1_000), 700);
+        assert_eq!(reserve.lendable(200), 0);
+    }
+
+    #[test]
+    fn claim_keeps_the_loans_past_the_rebate() {
+        let mut stats = BorrowerStats { borrower: Pubkey::new_unique(), mint: Pubkey::new_unique(), loans: 0, loans_since_claim: 0, fees_since_claim: 0, bump: 255 };
+        for _ in 0..7 {
+            stats.record_loan(10).unwrap();
+        }
+
+        // two rebates of the average fee earned, one paid per claim
+        assert_eq!(stats.owed(3), 20);
+        assert_eq!(stats.claim(3).unwrap(), 10);
+        assert_eq!((stats.loans_since_claim, stats.fees_since_claim), (4, 40));
+        assert_eq!(stats.claim(3).unwrap(), 10);
+        assert_eq!((stats.loans_since_claim, stats.fees_since_claim), (1, 10));
+
+        // the last loan still counts towards the next one
+        assert_eq!(err(stats.claim(3)), anchor_error(ProtocolError::NoRebate));
+        assert_eq!(stats.loans, 7);
+    }
+
+    #[test]
+    fn earned_rebates_are_held_back_from_the_free_fees() {
+        let mut reserve = reserve();
+        let mut stats = BorrowerStats { borrower: Pubkey::new_unique(), mint: Pubkey::new_unique(), loans: 0, loans_since_claim: 0, fees_since_claim: 0, bump: 255 };
+
+        // repay: the fee accrues and the rebate it completes is held back
+        for _ in 0..2 {
+            let owed = stats.owed(2);
+            stats.record_loan(50).unwrap();
+            reserve.accrued_fees += 50;
+            reserve.rebook_rebates(owed, stats.owed(2)).unwrap();
+        }
+        assert_eq!(reserve.reserved_rebates, 50);
+        assert_eq!(reserve.free_fees(), 50);
+
+        // claim: the rebate leaves the accrued fees and the hold-back with it
+        let owed = stats.owed(2);
+        let amount = stats.claim(2).unwrap();
+        reserve.accrued_fees -= amount;
+        reserve.rebook_rebates(owed, stats.owed(2)).unwrap();
+        assert_eq!((reserve.accrued_fees, reserve.reserved_rebates), (50, 0));
+        assert_eq!(reserve.free_fees(), 50);
+    }
+
+    #[test]
+    fn only_the_current_loan_record_version_is_accepted() {
+        let mut record = LoanRecord {
+            version: LoanRecord::VERSION,
+            borrower: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            amount: 1_000,
+            fee: 50,
+            open: true,
+            bump: 255,
+        };
+        let mut data = Vec::new();
+        record.try_serialize(&mut data).unwrap();
+        assert_eq!(data.len(), LoanRecord::LEN);
+        LoanRecord::try_deserialize(&mut &data[..]).unwrap().check_version().unwrap();
+
+        record.version = LoanRecord::VERSION + 1;
+        assert_eq!(err(record.check_version()), anchor_error(ProtocolError::UnsupportedLoanVersion));
+    }
+
+    #[test]
+    fn zero_global_exposure_means_unlimited() {
+        default_config(Pubkey::new_unique()).check_exposure(u64::MAX).unwrap();
+    }
+}
@@ -0,0 +1,449 @@
+//! In-process runtime for instruction tests.
+//!
+//! Instructions run natively against accounts serialized the way the loader
+//! does it, so Anchor's constraints and `exit` behave as on-chain. CPIs can't
+//! run off-chain, so only instructions that don't make any are driven through
+//! here, against accounts set up directly.
+
+#![allow(dead_code)]
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::Once;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    entrypoint::{deserialize, MAX_PERMITTED_DATA_INCREASE},
+    instruction::{BorrowedAccountMeta, BorrowedInstruction, Instruction},
+    program_pack::Pack,
+};
+use anchor_lang::InstructionData;
+use anchor_spl::associated_token::{get_associated_token_address, spl_associated_token_account::solana_program};
+use anchor_spl::token::spl_token;
+#[allow(deprecated)]
+use solana_program::sysvar::instructions::{construct_instructions_data, store_current_index};
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+
+use crate::state::{pool_seed, Config, LoanRecord, Reserve};
+
+thread_local! {
+    static NOW: Cell<i64> = const { Cell::new(0) };
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, _message: &str) {}
+
+    fn sol_log_data(&self, _fields: &[&[u8]]) {}
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock { unix_timestamp: NOW.with(Cell::get), ..Clock::default() };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TestAccount {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+}
+
+/// Accounts and a clock, transactions either apply as a whole or not at all
+pub struct Svm {
+    accounts: HashMap<Pubkey, TestAccount>,
+    pub now: i64,
+}
+
+impl Svm {
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(Stubs));
+        });
+
+        Self { accounts: HashMap::new(), now: 1_700_000_000 }
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: TestAccount) {
+        self.accounts.insert(key, account);
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.accounts.get(key).map_or(0, |account| account.lamports)
+    }
+
+    pub fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        self.accounts.entry(*key).or_default().lamports += lamports;
+    }
+
+    /// Funded system account
+    pub fn wallet(&mut self) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.set_account(key, TestAccount { lamports: 10_000_000_000, ..TestAccount::default() });
+        key
+    }
+
+    fn set_packed<T: Pack>(&mut self, key: Pubkey, state: T) {
+        let mut data = vec![0; T::LEN];
+        state.pack_into_slice(&mut data);
+        let lamports = Rent::default().minimum_balance(T::LEN);
+        self.set_account(key, TestAccount { lamports, data, owner: spl_token::ID });
+    }
+
+    pub fn add_mint(&mut self, decimals: u8) -> Pubkey {
+        let key = Pubkey::new_unique();
+        self.set_packed(
+            key,
+            spl_token::state::Mint {
+                mint_authority: Some(Pubkey::new_unique()).into(),
+                supply: u64::MAX / 2,
+                decimals,
+                is_initialized: true,
+                freeze_authority: None.into(),
+            },
+        );
+        key
+    }
+
+    pub fn set_token_account(&mut self, key: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) {
+        self.set_packed(
+            key,
+            spl_token::state::Account {
+                mint,
+                owner,
+                amount,
+                state: spl_token::state::AccountState::Initialized,
+                ..spl_token::state::Account::default()
+            },
+        );
+    }
+
+    /// Program account holding `state`, with `space` bytes of data
+    pub fn set_state<T: AccountSerialize>(&mut self, key: Pubkey, state: &T, space: usize) {
+        let mut data = Vec::with_capacity(space);
+        state.try_serialize(&mut data).unwrap();
+        data.resize(space, 0);
+        let lamports = Rent::default().minimum_balance(space);
+        self.set_account(key, TestAccount { lamports, data, owner: crate::ID });
+    }
+
+    pub fn state<T: AccountDeserialize>(&self, key: &Pubkey) -> T {
+        let account = self.accounts.get(key).expect("program account");
+        T::try_deserialize(&mut &account.data[..]).unwrap()
+    }
+
+    /// Runs the instructions as one transaction. Nothing is written back
+    /// unless all of them succeed.
+    pub fn process(&mut self, instructions: &[Instruction]) -> std::result::Result<(), ProgramError> {
+        self.process_range(instructions, 0..instructions.len())
+    }
+
+    /// Runs only instruction `index` of the transaction, the others are only
+    /// seen through the instructions sysvar. Reaches a loan's checks when the
+    /// loans before it would have to make CPIs.
+    pub fn process_at(&mut self, instructions: &[Instruction], index: usize) -> std::result::Result<(), ProgramError> {
+        self.process_range(instructions, index..index + 1)
+    }
+
+    fn process_range(&mut self, instructions: &[Instruction], range: std::ops::Range<usize>) -> std::result::Result<(), ProgramError> {
+        NOW.with(|now| now.set(self.now));
+        let mut accounts = self.accounts.clone();
+        for index in range {
+            accounts.insert(solana_program::sysvar::instructions::ID, instructions_sysvar(instructions, index));
+            execute(&mut accounts, &instructions[index])?;
+        }
+
+        accounts.remove(&solana_program::sysvar::instructions::ID);
+        accounts.retain(|_, account| account.lamports > 0);
+        self.accounts = accounts;
+        Ok(())
+    }
+}
+
+/// The instructions sysvar as the runtime fills it for instruction `index`
+#[allow(deprecated)]
+pub fn instructions_sysvar(instructions: &[Instruction], index: usize) -> TestAccount {
+    let borrowed: Vec<BorrowedInstruction> = instructions
+        .iter()
+        .map(|instruction| BorrowedInstruction {
+            program_id: &instruction.program_id,
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta { pubkey: &meta.pubkey, is_signer: meta.is_signer, is_writable: meta.is_writable })
+                .collect(),
+            data: &instruction.data,
+        })
+        .collect();
+    let mut data = construct_instructions_data(&borrowed);
+    store_current_index(&mut data, index as u16);
+
+    TestAccount { lamports: 1, data, owner: solana_program::sysvar::ID }
+}
+
+/// Programs an instruction can name, passed as executable accounts
+fn is_program(key: &Pubkey) -> bool {
+    [crate::ID, system_program::ID, spl_token::ID, anchor_spl::associated_token::ID].contains(key)
+}
+
+/// Serializes the instruction's accounts like the loader, runs it and reads
+/// the writable accounts back
+fn execute(accounts: &mut HashMap<Pubkey, TestAccount>, instruction: &Instruction) -> std::result::Result<(), ProgramError> {
+    let mut keys: Vec<Pubkey> = Vec::new();
+    let mut input: Vec<u8> = Vec::new();
+    input.extend_from_slice(&(instruction.accounts.len() as u64).to_le_bytes());
+    for meta in &instruction.accounts {
+        if let Some(position) = keys.iter().position(|key| *key == meta.pubkey) {
+            input.push(position as u8);
+            input.extend_from_slice(&[0; 7]);
+            continue;
+        }
+        keys.push(meta.pubkey);
+
+        let account = accounts.get(&meta.pubkey).cloned().unwrap_or_default();
+        let (is_signer, is_writable) = instruction
+            .accounts
+            .iter()
+            .filter(|other| other.pubkey == meta.pubkey)
+            .fold((false, false), |(signer, writable), other| (signer || other.is_signer, writable || other.is_writable));
+        let executable = is_program(&meta.pubkey);
+        input.extend_from_slice(&[u8::MAX, is_signer as u8, is_writable as u8, executable as u8, 0, 0, 0, 0]);
+        input.extend_from_slice(meta.pubkey.as_ref());
+        input.extend_from_slice(account.owner.as_ref());
+        input.extend_from_slice(&account.lamports.to_le_bytes());
+        input.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+        input.extend_from_slice(&account.data);
+        input.resize(input.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+        input.resize(input.len().next_multiple_of(8), 0);
+        input.extend_from_slice(&u64::MAX.to_le_bytes());
+    }
+    input.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+    input.extend_from_slice(&instruction.data);
+    input.extend_from_slice(instruction.program_id.as_ref());
+
+    // u64 words keep the buffer aligned like the loader's
+    let buffer = Box::leak(vec![0u64; input.len().div_ceil(8)].into_boxed_slice());
+    unsafe { std::ptr::copy_nonoverlapping(input.as_ptr(), buffer.as_mut_ptr() as *mut u8, input.len()) };
+    let (program_id, infos, data) = unsafe { deserialize(buffer.as_mut_ptr() as *mut u8) };
+    let infos = Box::leak(infos.into_boxed_slice());
+
+    crate::entry(program_id, infos, data)?;
+
+    for info in infos.iter().filter(|info| info.is_writable) {
+        accounts.insert(
+            *info.key,
+            TestAccount { lamports: info.lamports(), data: info.data.borrow().to_vec(), owner: *info.owner },
+        );
+    }
+    Ok(())
+}
+
+pub fn pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &crate::ID).0
+}
+
+pub fn ix(accounts: impl ToAccountMetas, data: impl InstructionData) -> Instruction {
+    Instruction { program_id: crate::ID, accounts: accounts.to_account_metas(None), data: data.data() }
+}
+
+/// Anchor error code as the runtime reports it
+pub fn anchor_error(code: impl Into<u32>) -> ProgramError {
+    ProgramError::Custom(code.into())
+}
+
+/// Error of a helper call, in the form the runtime reports it
+pub fn err<T: std::fmt::Debug>(result: Result<T>) -> ProgramError {
+    result.unwrap_err().into()
+}
+
+/// Account handed to a helper directly, outside of any instruction
+pub fn account_info(key: Pubkey, account: TestAccount) -> AccountInfo<'static> {
+    AccountInfo::new(
+        Box::leak(Box::new(key)),
+        false,
+        true,
+        Box::leak(Box::new(account.lamports)),
+        Box::leak(account.data.into_boxed_slice()),
+        Box::leak(Box::new(account.owner)),
+        false,
+        0,
+    )
+}
+
+/// The config `initialize` writes
+pub fn default_config(authority: Pubkey) -> Config {
+    Config {
+        authority,
+        max_loans_per_tx: 2,
+        paused: false,
+        min_liquidity: 0,
+        max_borrow: 0,
+        min_fee: 0,
+        max_global_exposure: 0,
+        min_bond: 0,
+        total_borrowed: 0,
+        total_fees_collected: 0,
+        pending_authority: Pubkey::default(),
+        rebate_every: 0,
+        dex_program: Pubkey::default(),
+        usd_fee: 0,
+        fee_oracle: Pubkey::default(),
+        max_oracle_age: 0,
+        fee_tiers: Vec::new(),
+        bump: Pubkey::find_program_address(&[b"config"], &crate::ID).1,
+    }
+}
+
+/// Pool 0 of one mint, as `initialize` and `initialize_protocol` leave it,
+/// with `liquidity` in the vault
+pub struct Pool {
+    pub svm: Svm,
+    pub authority: Pubkey,
+    pub mint: Pubkey,
+    pub config: Pubkey,
+    pub protocol: Pubkey,
+    pub protocol_ata: Pubkey,
+    pub reserve: Pubkey,
+}
+
+impl Pool {
+    pub fn new(liquidity: u64) -> Self {
+        let mut svm = Svm::new();
+        let authority = svm.wallet();
+        let mint = svm.add_mint(6);
+        let protocol = pda(&[b"protocol", &pool_seed(0)]);
+        let (reserve, reserve_bump) = Pubkey::find_program_address(&[b"reserve", mint.as_ref(), &pool_seed(0)], &crate::ID);
+        let mut pool = Self {
+            authority,
+            mint,
+            config: pda(&[b"config"]),
+            protocol,
+            protocol_ata: get_associated_token_address(&protocol, &mint),
+            reserve,
+            svm,
+        };
+
+        pool.set_config(default_config(authority));
+        pool.set_reserve(Reserve { mint, accrued_fees: 0, bump: reserve_bump, total_shares: 0, reserved_rebates: 0 });
+        pool.fund(liquidity);
+        pool
+    }
+
+    pub fn config(&self) -> Config {
+        self.svm.state(&self.config)
+    }
+
+    pub fn set_config(&mut self, config: Config) {
+        self.svm.set_state(self.config, &config, Config::INIT_SPACE + Config::DISCRIMINATOR.len());
+    }
+
+    pub fn reserve(&self) -> Reserve {
+        self.svm.state(&self.reserve)
+    }
+
+    pub fn set_reserve(&mut self, reserve: Reserve) {
+        self.svm.set_state(self.reserve, &reserve, Reserve::LEN);
+    }
+
+    /// Sets the vault balance, like a transfer in from outside the program
+    pub fn fund(&mut self, amount: u64) {
+        self.svm.set_token_account(self.protocol_ata, self.mint, self.protocol, amount);
+    }
+
+    /// Runs an authority-gated config setter signed by `authority`
+    pub fn admin_as(&mut self, authority: Pubkey, data: impl InstructionData) -> std::result::Result<(), ProgramError> {
+        let update = ix(crate::accounts::UpdateConfig { authority, config: self.config }, data);
+        self.svm.process(&[update])
+    }
+
+    pub fn admin(&mut self, data: impl InstructionData) -> std::result::Result<(), ProgramError> {
+        self.admin_as(self.authority, data)
+    }
+
+    /// Another mint lent out of pool 0, with `liquidity` in its own vault
+    pub fn add_vault(&mut self, liquidity: u64) -> Pubkey {
+        let mint = self.svm.add_mint(6);
+        let (reserve, bump) = Pubkey::find_program_address(&[b"reserve", mint.as_ref(), &pool_seed(0)], &crate::ID);
+        self.svm.set_state(reserve, &Reserve { mint, accrued_fees: 0, bump, total_shares: 0, reserved_rebates: 0 }, Reserve::LEN);
+        self.svm.set_token_account(get_associated_token_address(&self.protocol, &mint), mint, self.protocol, liquidity);
+        mint
+    }
+
+    /// Funded borrower that already has an ATA and a loan record of `mints`,
+    /// so borrowing makes no CPI before the transfer
+    pub fn borrower(&mut self, mints: &[Pubkey]) -> Pubkey {
+        let borrower = self.svm.wallet();
+        for mint in mints {
+            let (loan_record, bump) = Pubkey::find_program_address(&[b"loan", borrower.as_ref(), mint.as_ref(), &pool_seed(0)], &crate::ID);
+            let record = LoanRecord { version: LoanRecord::VERSION, borrower, mint: *mint, amount: 0, fee: 0, open: false, bump };
+            self.svm.set_state(loan_record, &record, LoanRecord::LEN);
+            self.svm.set_token_account(get_associated_token_address(&borrower, mint), *mint, borrower, 0);
+        }
+        borrower
+    }
+
+    pub fn loan_accounts(&self, borrower: Pubkey) -> crate::accounts::Loan {
+        self.loan_accounts_of(borrower, self.mint)
+    }
+
+    /// Loan accounts for `mint`'s vault in pool 0
+    pub fn loan_accounts_of(&self, borrower: Pubkey, mint: Pubkey) -> crate::accounts::Loan {
+        let seed = pool_seed(0);
+        crate::accounts::Loan {
+            borrower,
+            protocol: self.protocol,
+            mint,
+            borrower_ata: get_associated_token_address(&borrower, &mint),
+            protocol_ata: get_associated_token_address(&self.protocol, &mint),
+            instructions: solana_program::sysvar::instructions::ID,
+            token_program: spl_token::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+            config: self.config,
+            reserve: pda(&[b"reserve", mint.as_ref(), &seed]),
+            bond: None,
+            borrower_stats: None,
+            fee_waiver: None,
+            loan_record: pda(&[b"loan", borrower.as_ref(), mint.as_ref(), &seed]),
+            price_feed: None,
+        }
+    }
+
+    /// Arb on `mint`'s vault in pool 0 without any swap leg, through the configured DEX
+    pub fn arb_ix(&self, borrower: Pubkey, mint: Pubkey, borrow_amount: u64) -> Instruction {
+        let seed = pool_seed(0);
+        let accounts = crate::accounts::Arb {
+            borrower,
+            protocol: self.protocol,
+            mint,
+            borrower_ata: get_associated_token_address(&borrower, &mint),
+            protocol_ata: get_associated_token_address(&self.protocol, &mint),
+            instructions: solana_program::sysvar::instructions::ID,
+            config: self.config,
+            reserve: pda(&[b"reserve", mint.as_ref(), &seed]),
+            dex_program: self.config().dex_program,
+            token_program: spl_token::ID,
+            associated_token_program: anchor_spl::associated_token::ID,
+            system_program: system_program::ID,
+            fee_waiver: None,
+            price_feed: None,
+        };
+        ix(accounts, crate::instruction::Arb { pool_id: 0, borrow_amount, legs: Vec::new() })
+    }
+
+    pub fn borrow_ix(&self, borrower: Pubkey, borrow_amount: u64) -> Instruction {
+        ix(self.loan_accounts(borrower), crate::instruction::Borrow { pool_id: 0, borrow_amount })
+    }
+
+    pub fn repay_ix(&self, borrower: Pubkey) -> Instruction {
+        ix(self.loan_accounts(borrower), crate::instruction::Repay { pool_id: 0 })
+    }
+}
@@ -0,0 +1,142 @@
+//! Off-chain builders for the escrow instructions.
+//!
+//! Each builder derives the escrow PDA and every ATA, then lays the account
+//! metas out in the exact order the matching `*Accounts` parser expects.
+
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+use crate::{Escrow, Make, MakeInstructionData, Refund, Take, TimeoutRefund, Update};
+
+pub fn program_id() -> Pubkey {
+    Pubkey::new_from_array(crate::ID)
+}
+
+pub fn escrow_address(maker: &Pubkey, seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[Escrow::SEED, maker.as_ref(), &seed.to_le_bytes()], &program_id())
+}
+
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &Pubkey::new_from_array(pinocchio_associated_token_account::ID),
+    )
+    .0
+}
+
+/// Trailing accounts every instruction passes for its CPIs
+fn program_metas(token_program: &Pubkey) -> [AccountMeta; 3] {
+    [
+        AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_system::ID), false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_associated_token_account::ID), false),
+    ]
+}
+
+/// Open escrow with no deadline that anyone can take
+pub fn make_ix(maker: &Pubkey, mint_a: &Pubkey, mint_b: &Pubkey, token_program: &Pubkey, seed: u64, receive: u64, amount: u64) -> Instruction {
+    make_ix_with_data(maker, mint_a, mint_b, token_program, &MakeInstructionData {
+        seed,
+        receive,
+        amount,
+        deadline: 0,
+        allowed_taker: [0; 32],
+    })
+}
+
+pub fn make_ix_with_data(maker: &Pubkey, mint_a: &Pubkey, mint_b: &Pubkey, token_program: &Pubkey, args: &MakeInstructionData) -> Instruction {
+    let (escrow, _) = escrow_address(maker, args.seed);
+
+    let mut accounts = vec![
+        AccountMeta::new(*maker, true),
+        AccountMeta::new(escrow, false),
+        AccountMeta::new_readonly(*mint_a, false),
+        AccountMeta::new_readonly(*mint_b, false),
+        AccountMeta::new(associated_token_address(maker, mint_a, token_program), false),
+        AccountMeta::new(associated_token_address(&escrow, mint_a, token_program), false),
+    ];
+    accounts.extend(program_metas(token_program));
+
+    let mut data = vec![*Make::DISCRIMINATOR];
+    data.extend_from_slice(&args.seed.to_le_bytes());
+    data.extend_from_slice(&args.receive.to_le_bytes());
+    data.extend_from_slice(&args.amount.to_le_bytes());
+    data.extend_from_slice(&args.deadline.to_le_bytes());
+    data.extend_from_slice(&args.allowed_taker);
+
+    Instruction { program_id: program_id(), accounts, data }
+}
+
+pub fn take_ix(taker: &Pubkey, maker: &Pubkey, mint_a: &Pubkey, mint_b: &Pubkey, token_program: &Pubkey, seed: u64, min_amount_a: u64) -> Instruction {
+    let (escrow, _) = escrow_address(maker, seed);
+
+    let mut accounts = vec![
+        AccountMeta::new(*taker, true),
+        AccountMeta::new(*maker, false),
+        AccountMeta::new(escrow, false),
+        AccountMeta::new_readonly(*mint_a, false),
+        AccountMeta::new_readonly(*mint_b, false),
+        AccountMeta::new(associated_token_address(&escrow, mint_a, token_program), false),
+        AccountMeta::new(associated_token_address(taker, mint_a, token_program), false),
+        AccountMeta::new(associated_token_address(taker, mint_b, token_program), false),
+        AccountMeta::new(associated_token_address(maker, mint_b, token_program), false),
+    ];
+    accounts.extend(program_metas(token_program));
+
+    let mut data = vec![*Take::DISCRIMINATOR];
+    data.extend_from_slice(&min_amount_a.to_le_bytes());
+
+    Instruction { program_id: program_id(), accounts, data }
+}
+
+/// `amount` refunds part of the deposit, None refunds everything and closes the escrow
+pub fn refund_ix(maker: &Pubkey, mint_a: &Pubkey, token_program: &Pubkey, seed: u64, amount: Option<u64>) -> Instruction {
+    let (escrow, _) = escrow_address(maker, seed);
+
+    let mut accounts = vec![
+        AccountMeta::new(*maker, true),
+        AccountMeta::new(escrow, false),
+        AccountMeta::new_readonly(*mint_a, false),
+        AccountMeta::new(associated_token_address(&escrow, mint_a, token_program), false),
+        AccountMeta::new(associated_token_address(maker, mint_a, token_program), false),
+    ];
+    accounts.extend(program_metas(token_program));
+
+    let mut data = vec![*Refund::DISCRIMINATOR];
+    if let Some(amount) = amount {
+        data.extend_from_slice(&amount.to_le_bytes());
+    }
+
+    Instruction { program_id: program_id(), accounts, data }
+}
+
+pub fn timeout_refund_ix(caller: &Pubkey, maker: &Pubkey, mint_a: &Pubkey, token_program: &Pubkey, seed: u64) -> Instruction {
+    let (escrow, _) = escrow_address(maker, seed);
+
+    let mut accounts = vec![
+        AccountMeta::new(*caller, true),
+        AccountMeta::new(*maker, false),
+        AccountMeta::new(escrow, false),
+        AccountMeta::new_readonly(*mint_a, false),
+        AccountMeta::new(associated_token_address(&escrow, mint_a, token_program), false),
+        AccountMeta::new(associated_token_address(maker, mint_a, token_program), false),
+    ];
+    accounts.extend(program_metas(token_program));
+
+    Instruction { program_id: program_id(), accounts, data: vec![*TimeoutRefund::DISCRIMINATOR] }
+}
+
+/// The maker only signs, Update never debits it
+pub fn update_ix(maker: &Pubkey, seed: u64, new_receive: u64) -> Instruction {
+    let (escrow, _) = escrow_address(maker, seed);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*maker, true),
+        AccountMeta::new(escrow, false),
+    ];
+
+    let mut data = vec![*Update::DISCRIMINATOR];
+    data.extend_from_slice(&new_receive.to_le_bytes());
+
+    Instruction { program_id: program_id(), accounts, data }
+}
@@ -6,6 +6,20 @@ pub enum PinocchioError {
     InvalidOwner,
     InvalidAccountData,
     InvalidAddress,
+    InvalidAuthority,
+    InvalidEscrowPda,
+    InvalidMint,
+    AmountOverflow,
+    EscrowExpired,
+    EscrowNotExpired,
+    VaultNotEmpty,
+    UnauthorizedTaker,
+    InsufficientFunds,
+    SlippageExceeded,
+    InvalidMaker,
+    NotWritable,
+    InvalidVault,
+    InsufficientVaultBalance,
 }
 
 impl From<PinocchioError> for ProgramError {
@@ -15,6 +29,54 @@ impl From<PinocchioError> for ProgramError {
             PinocchioError::InvalidOwner => ProgramError::IllegalOwner,
             PinocchioError::InvalidAccountData => ProgramError::InvalidAccountData,
             PinocchioError::InvalidAddress => ProgramError::InvalidSeeds,
+            PinocchioError::InvalidAuthority => ProgramError::IncorrectAuthority,
+            PinocchioError::InvalidEscrowPda => ProgramError::Custom(0),
+            PinocchioError::InvalidMint => ProgramError::Custom(1),
+            PinocchioError::AmountOverflow => ProgramError::ArithmeticOverflow,
+            PinocchioError::EscrowExpired => ProgramError::Custom(2),
+            PinocchioError::EscrowNotExpired => ProgramError::Custom(3),
+            PinocchioError::VaultNotEmpty => ProgramError::Custom(4),
+            PinocchioError::UnauthorizedTaker => ProgramError::Custom(5),
+            PinocchioError::InsufficientFunds => ProgramError::InsufficientFunds,
+            PinocchioError::SlippageExceeded => ProgramError::Custom(6),
+            PinocchioError::InvalidMaker => ProgramError::Custom(7),
+            PinocchioError::NotWritable => ProgramError::Custom(8),
+            PinocchioError::InvalidVault => ProgramError::Custom(9),
+            PinocchioError::InsufficientVaultBalance => ProgramError::Custom(10),
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_codes_tell_every_failure_apart() {
+        let errors: Vec<ProgramError> = vec![
+            PinocchioError::InvalidEscrowPda.into(),
+            PinocchioError::InvalidMint.into(),
+            PinocchioError::EscrowExpired.into(),
+            PinocchioError::EscrowNotExpired.into(),
+            PinocchioError::VaultNotEmpty.into(),
+            PinocchioError::UnauthorizedTaker.into(),
+            PinocchioError::SlippageExceeded.into(),
+            PinocchioError::InvalidMaker.into(),
+            PinocchioError::NotWritable.into(),
+            PinocchioError::InvalidVault.into(),
+            PinocchioError::InsufficientVaultBalance.into(),
+        ];
+
+        for (code, error) in errors.into_iter().enumerate() {
+            assert_eq!(error, ProgramError::Custom(code as u32));
+        }
+    }
+
+    #[test]
+    fn the_rest_reuse_the_builtin_errors() {
+        assert_eq!(ProgramError::from(PinocchioError::NotSigner), ProgramError::MissingRequiredSignature);
+        assert_eq!(ProgramError::from(PinocchioError::InvalidOwner), ProgramError::IllegalOwner);
+        assert_eq!(ProgramError::from(PinocchioError::AmountOverflow), ProgramError::ArithmeticOverflow);
+        assert_eq!(ProgramError::from(PinocchioError::InsufficientFunds), ProgramError::InsufficientFunds);
+    }
+}
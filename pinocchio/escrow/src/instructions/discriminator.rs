@@ -0,0 +1,27 @@
+use pinocchio::program_error::ProgramError;
+
+/// First byte of every instruction, the single list of what the program accepts
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+  Make = 0,
+  Take = 1,
+  Refund = 2,
+  TimeoutRefund = 3,
+  Update = 4,
+}
+
+impl TryFrom<u8> for Instruction {
+  type Error = ProgramError;
+
+  fn try_from(discriminator: u8) -> Result<Self, Self::Error> {
+    match discriminator {
+      0 => Ok(Self::Make),
+      1 => Ok(Self::Take),
+      2 => Ok(Self::Refund),
+      3 => Ok(Self::TimeoutRefund),
+      4 => Ok(Self::Update),
+      _ => Err(ProgramError::InvalidInstructionData),
+    }
+  }
+}
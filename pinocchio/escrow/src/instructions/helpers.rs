@@ -1,11 +1,11 @@
 use pinocchio::{
     account_info::AccountInfo, instruction::{Seed, Signer},
     program_error::ProgramError, sysvars::{rent::Rent, Sysvar},
-    pubkey::find_program_address, ProgramResult
+    pubkey::{find_program_address, Pubkey}, ProgramResult
 };
 
 use pinocchio_token::{
-    instructions::{InitializeMint2, InitializeAccount3},
+    instructions::{InitializeMint2, InitializeAccount3, TransferChecked},
 };
 
 use pinocchio_system::instructions::CreateAccount;
@@ -132,10 +132,10 @@ impl AccountCheck for Mint2022 {
 
         if data.len().ne(&pinocchio_token::state::Mint::LEN) {
             if data.len().le(&TOKEN_2022_ACCOUNT_DISCRIMINATOR_OFFSET) {
-                return Err(PinocchioError::InvalidAccountData.into());
+                return Err(PinocchioError::InvalidMint.into());
             }
             if data[TOKEN_2022_ACCOUNT_DISCRIMINATOR_OFFSET].ne(&TOKEN_2022_MINT_DISCRIMINATOR) {
-                return Err(PinocchioError::InvalidAccountData.into());
+                return Err(PinocchioError::InvalidMint.into());
             }
         }
 
@@ -183,6 +183,73 @@ impl MintInit for Mint2022 {
     }
 }
 
+// Decimals sit at the same offset for Token and Token-2022 mints.
+// `pinocchio_token::state::Mint::from_account_info` would reject Token-2022
+// mints (owner and length checks), so the byte is read directly instead.
+const MINT_DECIMALS_OFFSET: usize = 44;
+
+/// Read a mint's decimals, the mint must already have passed `MintInterface::check`
+pub fn mint_decimals(mint: &AccountInfo) -> Result<u8, ProgramError> {
+    mint.try_borrow_data()?
+        .get(MINT_DECIMALS_OFFSET)
+        .copied()
+        .ok_or(PinocchioError::InvalidMint.into())
+}
+
+// Same for the owner and amount of Token and Token-2022 accounts, extensions
+// only ever come after the base layout
+const TOKEN_OWNER_OFFSET: usize = 32;
+const TOKEN_AMOUNT_OFFSET: usize = 64;
+
+/// Read a token account's owner, works for Token-2022 accounts with extensions
+pub fn token_owner(account: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    let data = account.try_borrow_data()?;
+    let bytes = data
+        .get(TOKEN_OWNER_OFFSET..TOKEN_OWNER_OFFSET + 32)
+        .ok_or(PinocchioError::InvalidAccountData)?;
+    Ok(bytes.try_into().unwrap())
+}
+
+/// Read a token account's balance, works for Token-2022 accounts with extensions
+pub fn token_amount(account: &AccountInfo) -> Result<u64, ProgramError> {
+    let data = account.try_borrow_data()?;
+    let bytes = data
+        .get(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8)
+        .ok_or(PinocchioError::InvalidAccountData)?;
+    Ok(u64::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Move the recorded deposit out of the escrow's vault, then any `surplus`
+/// donated on top of it, otherwise the vault couldn't be closed
+pub fn drain_vault(
+    vault: &AccountInfo,
+    mint: &AccountInfo,
+    to: &AccountInfo,
+    escrow: &AccountInfo,
+    signer: &Signer,
+    amount: u64,
+    surplus: u64,
+) -> ProgramResult {
+    let decimals = mint_decimals(mint)?;
+
+    for amount in [amount, surplus] {
+        if amount == 0 {
+            continue;
+        }
+
+        TransferChecked {
+            from: vault,
+            mint,
+            to,
+            authority: escrow,
+            amount,
+            decimals,
+        }.invoke_signed(&[signer.clone()])?;
+    }
+
+    Ok(())
+}
+
 // Token 2022
 pub struct Token2022;
 
@@ -244,6 +311,117 @@ impl TokenInit for Token2022 {
     }
 }
 
+// Mint (legacy Token program)
+pub struct MintLegacy;
+
+impl AccountCheck for MintLegacy {
+    fn check(account: &AccountInfo) -> Result<(), ProgramError> {
+        if !account.is_owned_by(&pinocchio_token::ID) {
+            return Err(PinocchioError::InvalidOwner.into());
+        }
+
+        if account.data_len().ne(&pinocchio_token::state::Mint::LEN) {
+            return Err(PinocchioError::InvalidMint.into());
+        }
+
+        Ok(())
+    }
+}
+
+impl MintInit for MintLegacy {
+    fn init(
+        account: &AccountInfo,
+        payer: &AccountInfo,
+        decimals: u8,
+        mint_authority: &[u8; 32],
+        freeze_authority: Option<&[u8; 32]>,
+    ) -> ProgramResult {
+        let lamports = Rent::get()?.minimum_balance(pinocchio_token::state::Mint::LEN);
+
+        CreateAccount {
+            from: payer,
+            to: account,
+            lamports,
+            space: pinocchio_token::state::Mint::LEN as u64,
+            owner: &pinocchio_token::ID,
+        }.invoke()?;
+
+        InitializeMint2 {
+            mint: account,
+            decimals,
+            mint_authority,
+            freeze_authority,
+        }.invoke()
+    }
+
+    fn init_if_needed(
+        account: &AccountInfo,
+        payer: &AccountInfo,
+        decimals: u8,
+        mint_authority: &[u8; 32],
+        freeze_authority: Option<&[u8; 32]>,
+    ) -> ProgramResult {
+        match Self::check(account) {
+            Ok(_) => Ok(()),
+            Err(_) => Self::init(account, payer, decimals, mint_authority, freeze_authority),
+        }
+    }
+}
+
+// Token account (legacy Token program)
+pub struct TokenLegacy;
+
+impl AccountCheck for TokenLegacy {
+    fn check(account: &AccountInfo) -> Result<(), ProgramError> {
+        if !account.is_owned_by(&pinocchio_token::ID) {
+            return Err(PinocchioError::InvalidOwner.into());
+        }
+
+        if account.data_len().ne(&pinocchio_token::state::TokenAccount::LEN) {
+            return Err(PinocchioError::InvalidAccountData.into());
+        }
+
+        Ok(())
+    }
+}
+
+impl TokenInit for TokenLegacy {
+    fn init(
+        account: &AccountInfo,
+        mint: &AccountInfo,
+        payer: &AccountInfo,
+        owner: &[u8; 32],
+    ) -> ProgramResult {
+        let lamports = Rent::get()?.minimum_balance(pinocchio_token::state::TokenAccount::LEN);
+
+        CreateAccount {
+            from: payer,
+            to: account,
+            lamports,
+            space: pinocchio_token::state::TokenAccount::LEN as u64,
+            owner: &pinocchio_token::ID,
+        }.invoke()?;
+
+        InitializeAccount3 {
+            account,
+            mint,
+            owner,
+        }.invoke()
+    }
+
+    fn init_if_needed(
+        account: &AccountInfo,
+        mint: &AccountInfo,
+        payer: &AccountInfo,
+        owner: &[u8; 32],
+    ) -> ProgramResult {
+        match Self::check(account) {
+            Ok(_) => Ok(()),
+            Err(_) => Self::init(account, mint, payer, owner),
+        }
+    }
+}
+
 // Mint interface (Token + Token2022)
 pub struct MintInterface;
 
@@ -253,17 +431,17 @@ impl AccountCheck for MintInterface {
             if !account.is_owned_by(&pinocchio_token::ID) {
                 return Err(PinocchioError::InvalidOwner.into());
             } else if account.data_len().ne(&pinocchio_token::state::Mint::LEN) {
-                return Err(PinocchioError::InvalidAccountData.into());
+                return Err(PinocchioError::InvalidMint.into());
             }
         } else {
             let data = account.try_borrow_data()?;
 
             if data.len().ne(&pinocchio_token::state::Mint::LEN) {
                 if data.len().le(&TOKEN_2022_ACCOUNT_DISCRIMINATOR_OFFSET) {
-                    return Err(PinocchioError::InvalidAccountData.into());
+                    return Err(PinocchioError::InvalidMint.into());
                 }
                 if data[TOKEN_2022_ACCOUNT_DISCRIMINATOR_OFFSET].ne(&TOKEN_2022_MINT_DISCRIMINATOR) {
-                    return Err(PinocchioError::InvalidAccountData.into());
+                    return Err(PinocchioError::InvalidMint.into());
                 }
             }
         }
@@ -313,6 +491,10 @@ impl AccountCheck for ProgramAccount {
             return Err(PinocchioError::InvalidAccountData.into());
         }
 
+        if account.try_borrow_data()?[crate::state::Escrow::DISCRIMINATOR_OFFSET].ne(&crate::state::Escrow::DISCRIMINATOR) {
+            return Err(PinocchioError::InvalidAccountData.into());
+        }
+
         Ok(())
     }
 }
@@ -342,9 +524,16 @@ impl ProgramAccountInit for ProgramAccount {
 
 impl AccountClose for ProgramAccount {
     fn close(account: &AccountInfo, destination: &AccountInfo) -> ProgramResult {
+        if !account.is_writable() || !destination.is_writable() {
+            return Err(PinocchioError::NotWritable.into());
+        }
+
         {
+            // Wipe the stale state and mark the discriminator so the account
+            // can't be revived as an escrow
             let mut data = account.try_borrow_mut_data()?;
-            data[0] = 0xff;
+            data.fill(0);
+            data[crate::state::Escrow::DISCRIMINATOR_OFFSET] = 0xff;
         }
 
         *destination.try_borrow_mut_lamports()? += *account.try_borrow_lamports()?;
@@ -408,4 +597,92 @@ impl AssociatedTokenAccountInit for AssociatedTokenAccount {
             Err(_) => Self::init(account, mint, payer, owner, system_program, token_program),
         }
     }
-}
\ No newline at end of file
+}
+
+/// Check that the stored bump derives the canonical escrow PDA, catching
+/// corrupted state early. Debug builds only since it costs a full bump search.
+#[cfg(feature = "debug-checks")]
+pub fn check_canonical_escrow(
+    escrow: &pinocchio::pubkey::Pubkey,
+    maker: &pinocchio::pubkey::Pubkey,
+    seed: u64,
+) -> Result<(), ProgramError> {
+    let (canonical, _) = find_program_address(
+        &[crate::state::Escrow::SEED, maker, &seed.to_le_bytes()],
+        &crate::ID,
+    );
+
+    check_canonical(escrow, &canonical)
+}
+
+/// Reject an escrow that doesn't sit at the `canonical` PDA
+pub fn check_canonical(escrow: &Pubkey, canonical: &Pubkey) -> Result<(), ProgramError> {
+    if canonical.ne(escrow) {
+        return Err(PinocchioError::InvalidEscrowPda.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn closing_wipes_the_escrow() {
+        let mut escrow = TestAccount::escrow([1; 32], &escrow([2; 32], [3; 32], [4; 32])).writable();
+        let mut maker = TestAccount::new([2; 32], [0; 32], &[]).writable();
+
+        ProgramAccount::close(&escrow.info(), &maker.info()).unwrap();
+
+        // Marked closed, and not a byte of the old state left behind
+        let data = escrow.raw_data();
+        assert_eq!(data.len(), crate::state::Escrow::LEN);
+        assert_eq!(data[crate::state::Escrow::DISCRIMINATOR_OFFSET], 0xff);
+        assert!(data[1..].iter().all(|byte| *byte == 0));
+        assert_eq!(escrow.info().data_len(), 0);
+    }
+
+    #[test]
+    fn closing_hands_the_rent_over_and_needs_both_accounts_writable() {
+        let state = escrow([2; 32], [3; 32], [4; 32]);
+        let mut readonly_escrow = TestAccount::escrow([1; 32], &state);
+        let mut readonly_maker = TestAccount::new([2; 32], [0; 32], &[]);
+        let mut escrow = TestAccount::escrow([1; 32], &state).writable();
+        let mut maker = TestAccount::new([2; 32], [0; 32], &[]).writable();
+
+        assert_eq!(ProgramAccount::close(&readonly_escrow.info(), &maker.info()), Err(PinocchioError::NotWritable.into()));
+        assert_eq!(ProgramAccount::close(&escrow.info(), &readonly_maker.info()), Err(PinocchioError::NotWritable.into()));
+        assert_eq!(escrow.info().data_len(), crate::state::Escrow::LEN);
+
+        ProgramAccount::close(&escrow.info(), &maker.info()).unwrap();
+        assert_eq!(maker.info().lamports(), 2_000_000);
+        assert_eq!(escrow.info().lamports(), 0);
+        // Nothing left that still reads as an escrow
+        assert!(crate::state::Escrow::snapshot(escrow.raw_data()).is_err());
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn escrows_off_the_canonical_bump_are_rejected() {
+        use solana_pubkey::Pubkey as Address;
+
+        let maker = Address::new_from_array([7; 32]);
+        let (canonical, bump) = crate::client::escrow_address(&maker, 42);
+        // Next bump down that is also off the curve, a valid but non-canonical PDA
+        let non_canonical = (0..bump)
+            .rev()
+            .find_map(|bump| {
+                let seeds: &[&[u8]] = &[crate::state::Escrow::SEED, maker.as_ref(), &42u64.to_le_bytes(), &[bump]];
+                Address::create_program_address(seeds, &crate::client::program_id()).ok()
+            })
+            .unwrap();
+
+        assert!(check_canonical(&canonical.to_bytes(), &canonical.to_bytes()).is_ok());
+        assert_eq!(
+            check_canonical(&non_canonical.to_bytes(), &canonical.to_bytes()),
+            Err(PinocchioError::InvalidEscrowPda.into())
+        );
+    }
+}
@@ -1,13 +1,16 @@
 use pinocchio::{
     account_info::AccountInfo, instruction::Seed,
-    program_error::ProgramError, pubkey::find_program_address,
-    ProgramResult, 
+    program_error::ProgramError, pubkey::{find_program_address, Pubkey},
+    sysvars::{clock::Clock, Sysvar}, ProgramResult, 
 };
 
-use pinocchio_token::instructions::Transfer;
+use pinocchio_token::instructions::TransferChecked;
 
 use crate::Escrow;
+use crate::errors::PinocchioError;
+use super::discriminator::Instruction;
 use super::helpers::*;
+use super::schema::*;
 
 use core::mem::size_of;
 
@@ -22,11 +25,25 @@ pub struct MakeAccounts<'a> {
   pub token_program: &'a AccountInfo,
 }
 
+impl<'a> MakeAccounts<'a> {
+  pub const SCHEMA: [AccountRole; 9] = [
+    AccountRole::signer(),   // maker
+    AccountRole::writable(), // escrow
+    AccountRole::readonly(), // mint_a
+    AccountRole::readonly(), // mint_b
+    AccountRole::writable(), // maker_ata_a
+    AccountRole::writable(), // vault
+    AccountRole::readonly(), // system_program
+    AccountRole::readonly(), // token_program
+    AccountRole::readonly(), // associated_token_program
+  ];
+}
+
 impl<'a> TryFrom<&'a [AccountInfo]> for MakeAccounts<'a> {
   type Error = ProgramError;
 
   fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-    let [maker, escrow, mint_a, mint_b, maker_ata_a, vault, system_program, token_program, _] = accounts else {
+    let [maker, escrow, mint_a, mint_b, maker_ata_a, vault, system_program, token_program, _associated_token_program] = validate_accounts(accounts, &Self::SCHEMA)? else {
       return Err(ProgramError::NotEnoughAccountKeys);
     };
 
@@ -34,7 +51,19 @@ impl<'a> TryFrom<&'a [AccountInfo]> for MakeAccounts<'a> {
     SignerAccount::check(maker)?;
     MintInterface::check(mint_a)?;
     MintInterface::check(mint_b)?;
+
+    // Swapping a mint for itself makes no sense
+    if mint_a.key() == mint_b.key() {
+      return Err(PinocchioError::InvalidMint.into());
+    }
+
+    // The vault has to be created here, never reused with a balance in it.
+    // Checked up front so a pre-funded vault fails before anything is paid for.
+    if !vault.data_is_empty() {
+      return Err(PinocchioError::VaultNotEmpty.into());
+    }
     AssociatedTokenAccount::check(maker_ata_a, maker, mint_a, token_program)?;
+    check_deposit_authority(maker_ata_a, maker)?;
 
     // Return the accounts
     Ok(Self {
@@ -50,33 +79,58 @@ impl<'a> TryFrom<&'a [AccountInfo]> for MakeAccounts<'a> {
   }
 }
 
+/// The signer must be the one controlling the deposited tokens
+fn check_deposit_authority(maker_ata_a: &AccountInfo, maker: &AccountInfo) -> Result<(), ProgramError> {
+  if &token_owner(maker_ata_a)? != maker.key() {
+    return Err(PinocchioError::InvalidAuthority.into());
+  }
+
+  Ok(())
+}
+
 pub struct MakeInstructionData {
   pub seed: u64,
   pub receive: u64,
   pub amount: u64,
+  pub deadline: i64,
+  pub allowed_taker: Pubkey,
 }
 
 impl<'a> TryFrom<&'a [u8]> for MakeInstructionData {
   type Error = ProgramError;
 
   fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
-    if data.len() != size_of::<u64>() * 3 {
+    if data.len() != size_of::<u64>() * 3 + size_of::<i64>() + size_of::<Pubkey>() {
       return Err(ProgramError::InvalidInstructionData);
     }
 
     let seed = u64::from_le_bytes(data[0..8].try_into().unwrap());
     let receive = u64::from_le_bytes(data[8..16].try_into().unwrap());
     let amount = u64::from_le_bytes(data[16..24].try_into().unwrap());
+    let deadline = i64::from_le_bytes(data[24..32].try_into().unwrap());
+    let allowed_taker: Pubkey = data[32..64].try_into().unwrap();
 
     // Instruction Checks
     if amount == 0 {
       return Err(ProgramError::InvalidInstructionData);
     }
 
+    // Asking for nothing would give Token A away on Take
+    if receive == 0 {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    // A deadline, if any, has to be in the future
+    if deadline != 0 && deadline <= Clock::get()?.unix_timestamp {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
     Ok(Self {
       seed,
       receive,
       amount,
+      deadline,
+      allowed_taker,
     })
   }
 }
@@ -96,19 +150,23 @@ impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Make<'a> {
     let instruction_data = MakeInstructionData::try_from(data)?;
 
     // Initialize the Accounts needed
-    let (_, bump) = find_program_address(
+    let (escrow_key, bump) = find_program_address(
       &[
-        b"escrow", 
+        Escrow::SEED, 
         accounts.maker.key(), 
         &instruction_data.seed.to_le_bytes()
       ], 
       &crate::ID
     );
 
+    // Take and Refund rebuild the PDA from the stored bump, so the escrow has
+    // to sit at the canonical address for the bump we store
+    check_canonical(accounts.escrow.key(), &escrow_key)?;
+
     let seed_binding = instruction_data.seed.to_le_bytes();
     let bump_binding = [bump];
     let escrow_seeds = [
-      Seed::from(b"escrow"),
+      Seed::from(Escrow::SEED),
       Seed::from(accounts.maker.key().as_ref()),
       Seed::from(&seed_binding),
       Seed::from(&bump_binding),
@@ -140,7 +198,7 @@ impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Make<'a> {
 }
 
 impl<'a> Make<'a> {
-  pub const DISCRIMINATOR: &'a u8 = &0;
+  pub const DISCRIMINATOR: &'a u8 = &(Instruction::Make as u8);
   
   pub fn process(&mut self) -> ProgramResult {
     // Populate the escrow account
@@ -153,17 +211,134 @@ impl<'a> Make<'a> {
       *self.accounts.mint_a.key(),
       *self.accounts.mint_b.key(),
       self.instruction_data.receive,
+      self.instruction_data.amount,
       [self.bump],
     );
+    escrow.set_deadline(self.instruction_data.deadline);
+    escrow.set_allowed_taker(self.instruction_data.allowed_taker);
+    escrow.set_created_at(Clock::get()?.unix_timestamp);
+
+    // Fail clearly instead of deep inside the token program
+    let balance = token_amount(self.accounts.maker_ata_a)?;
+    if balance < self.instruction_data.amount {
+      return Err(PinocchioError::InsufficientFunds.into());
+    }
 
     // Transfer tokens to vault
-    Transfer {
+    let mint_a_decimals = mint_decimals(self.accounts.mint_a)?;
+    TransferChecked {
       from: self.accounts.maker_ata_a,
+      mint: self.accounts.mint_a,
       to: self.accounts.vault,
       authority: self.accounts.maker,
-      amount: self.instruction_data.amount
+      amount: self.instruction_data.amount,
+      decimals: mint_a_decimals,
     }.invoke()?;
 
+    // Transfer-fee mints credit the vault less than was sent, record what
+    // actually arrived so Take and Refund never promise more than is there
+    escrow.set_amount(token_amount(self.accounts.vault)?);
+
     Ok(())
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+
+  fn make_data(receive: u64, amount: u64) -> Vec<u8> {
+    let mut data = Vec::new();
+    data.extend_from_slice(&42u64.to_le_bytes());
+    data.extend_from_slice(&receive.to_le_bytes());
+    data.extend_from_slice(&amount.to_le_bytes());
+    // No deadline
+    data.extend_from_slice(&0i64.to_le_bytes());
+    data.extend_from_slice(&[0; 32]);
+    data
+  }
+
+  #[test]
+  fn both_sides_need_an_amount() {
+    assert!(MakeInstructionData::try_from(make_data(1_000, 500).as_slice()).is_ok());
+    assert_eq!(
+      MakeInstructionData::try_from(make_data(0, 500).as_slice()).err(),
+      Some(ProgramError::InvalidInstructionData)
+    );
+    assert_eq!(
+      MakeInstructionData::try_from(make_data(1_000, 0).as_slice()).err(),
+      Some(ProgramError::InvalidInstructionData)
+    );
+  }
+
+  /// Runs the account checks of a make of `mint_b` for `mint_a` into `vault`
+  fn parse_make(mint_a: [u8; 32], mint_b: [u8; 32], mut vault: TestAccount) -> Result<(), ProgramError> {
+    let mut maker = TestAccount::new([1; 32], [0; 32], &[]).signer();
+    let mut escrow = TestAccount::new([3; 32], [0; 32], &[]).writable();
+    let mut mint_a_account = TestAccount::mint(mint_a, 6);
+    let mut mint_b_account = TestAccount::mint(mint_b, 6);
+    let mut maker_ata_a = TestAccount::token([4; 32], mint_a, [1; 32], 500).writable();
+    let mut programs = [[6; 32], pinocchio_token::ID, [7; 32]].map(|key| TestAccount::new(key, [0; 32], &[]));
+    let [system_program, token_program, associated_token_program] = &mut programs;
+
+    let accounts = [
+      maker.info(), escrow.info(), mint_a_account.info(), mint_b_account.info(), maker_ata_a.info(), vault.info(),
+      system_program.info(), token_program.info(), associated_token_program.info(),
+    ];
+    MakeAccounts::try_from(&accounts[..]).map(|_| ())
+  }
+
+  #[test]
+  fn mints_have_to_differ() {
+    let vault = TestAccount::new([5; 32], [0; 32], &[]).writable();
+
+    assert_eq!(parse_make([2; 32], [2; 32], vault), Err(PinocchioError::InvalidMint.into()));
+  }
+
+  #[test]
+  fn deposits_need_the_signer_to_own_the_tokens() {
+    let (maker, mint_a) = ([1; 32], [2; 32]);
+    let mut signer = TestAccount::new(maker, [0; 32], &[]).signer();
+
+    let mut owned = TestAccount::token([3; 32], mint_a, maker, 500);
+    assert!(check_deposit_authority(&owned.info(), &signer.info()).is_ok());
+
+    let mut elsewhere = TestAccount::token([3; 32], mint_a, [4; 32], 500);
+    assert_eq!(
+      check_deposit_authority(&elsewhere.info(), &signer.info()),
+      Err(PinocchioError::InvalidAuthority.into())
+    );
+
+    // Token-2022 accounts with extensions keep the owner at the same offset
+    let mut extended = [0; 200];
+    extended[32..64].copy_from_slice(&maker);
+    let mut token_2022 = TestAccount::new([3; 32], TOKEN_2022_PROGRAM_ID, &extended);
+    assert!(check_deposit_authority(&token_2022.info(), &signer.info()).is_ok());
+  }
+
+  #[test]
+  fn pre_funded_vaults_are_rejected() {
+    // Someone sent Token A to the escrow's vault address ahead of the make
+    let vault = TestAccount::token([5; 32], [2; 32], [3; 32], 1).writable();
+
+    assert_eq!(parse_make([2; 32], [8; 32], vault), Err(PinocchioError::VaultNotEmpty.into()));
+  }
+
+  #[cfg(feature = "client")]
+  #[test]
+  fn the_stored_bump_rebuilds_the_canonical_escrow() {
+    use solana_pubkey::Pubkey as Address;
+
+    let maker = Address::new_from_array([7; 32]);
+    let (canonical, bump) = crate::client::escrow_address(&maker, 42);
+    let mut escrow: Escrow = bytemuck::Zeroable::zeroed();
+    escrow.set_inner(42, maker.to_bytes(), [2; 32], [3; 32], 1_000, 500, [bump]);
+
+    // What Take and Refund derive from the stored state
+    let seeds: &[&[u8]] = &[Escrow::SEED, maker.as_ref(), &escrow.seed().to_le_bytes(), &[escrow.bump()]];
+    let rebuilt = Address::create_program_address(seeds, &crate::client::program_id()).unwrap();
+    assert_eq!(rebuilt, canonical);
+    assert!(check_canonical(&rebuilt.to_bytes(), &canonical.to_bytes()).is_ok());
+  }
+}
@@ -1,9 +1,17 @@
+pub mod discriminator;
+pub use discriminator::*;
 pub mod make;
 pub use make::*;
 pub mod take;
 pub use take::*;
 pub mod refund;
 pub use refund::*;
+pub mod timeout_refund;
+pub use timeout_refund::*;
+pub mod update;
+pub use update::*;
 pub mod helpers;
 pub use helpers::*;
+pub mod schema;
+pub use schema::*;
 
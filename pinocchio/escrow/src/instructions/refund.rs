@@ -1,18 +1,18 @@
 use pinocchio::{
-    account_info::AccountInfo, instruction::{Seed, Signer},
+    account_info::AccountInfo, instruction::Signer,
     program_error::ProgramError, pubkey::create_program_address,
     ProgramResult
     
 };
 
-use pinocchio_token::{
-    state::TokenAccount,
-    instructions::{Transfer, CloseAccount},
-};
+use pinocchio_token::instructions::{TransferChecked, CloseAccount};
 
 
 use crate::Escrow;
+use crate::errors::PinocchioError;
+use super::discriminator::Instruction;
 use super::helpers::*;
+use super::schema::*;
 
 pub struct RefundAccounts<'a> {
   pub maker: &'a AccountInfo,
@@ -24,11 +24,24 @@ pub struct RefundAccounts<'a> {
   pub token_program: &'a AccountInfo,
 }
 
+impl<'a> RefundAccounts<'a> {
+  pub const SCHEMA: [AccountRole; 8] = [
+    AccountRole::signer(),                          // maker
+    AccountRole::writable().owned_by(&crate::ID),   // escrow
+    AccountRole::readonly(),                        // mint_a
+    AccountRole::writable(),                        // vault
+    AccountRole::writable(),                        // maker_ata_a
+    AccountRole::readonly(),                        // system_program
+    AccountRole::readonly(),                        // token_program
+    AccountRole::readonly(),                        // associated_token_program
+  ];
+}
+
 impl<'a> TryFrom<&'a [AccountInfo]> for RefundAccounts<'a> {
   type Error = ProgramError;
 
   fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-    let [maker, escrow, mint_a, vault, maker_ata_a, system_program, token_program, _] = accounts else {
+    let [maker, escrow, mint_a, vault, maker_ata_a, system_program, token_program, _associated_token_program] = validate_accounts(accounts, &Self::SCHEMA)? else {
       return Err(ProgramError::NotEnoughAccountKeys);
     };
 
@@ -36,7 +49,8 @@ impl<'a> TryFrom<&'a [AccountInfo]> for RefundAccounts<'a> {
     SignerAccount::check(maker)?;
     ProgramAccount::check(escrow)?;
     MintInterface::check(mint_a)?;
-
+    AssociatedTokenAccount::check(vault, escrow, mint_a, token_program)
+      .map_err(|_| ProgramError::from(PinocchioError::InvalidVault))?;
 
     // Return the accounts
     Ok(Self {
@@ -52,15 +66,44 @@ impl<'a> TryFrom<&'a [AccountInfo]> for RefundAccounts<'a> {
 }
 
 
+pub struct RefundInstructionData {
+  pub amount: Option<u64>,
+}
+
+impl<'a> TryFrom<&'a [u8]> for RefundInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    // No data refunds everything, an amount refunds only part of the deposit
+    let amount = match data.len() {
+      0 => None,
+      8 => Some(u64::from_le_bytes(data[0..8].try_into().unwrap())),
+      _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    // Instruction Checks
+    if amount == Some(0) {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(Self {
+      amount,
+    })
+  }
+}
+
+
 pub struct Refund<'a> {
   pub accounts: RefundAccounts<'a>,
+  pub instruction_data: RefundInstructionData,
 }
 
-impl<'a> TryFrom<&'a [AccountInfo]> for Refund<'a> {
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Refund<'a> {
   type Error = ProgramError;
   
-  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
     let accounts = RefundAccounts::try_from(accounts)?;
+    let instruction_data = RefundInstructionData::try_from(data)?;
 
     // Initialize necessary accounts
     AssociatedTokenAccount::init_if_needed(
@@ -74,12 +117,13 @@ impl<'a> TryFrom<&'a [AccountInfo]> for Refund<'a> {
  
     Ok(Self {
       accounts,
+      instruction_data,
     })
   }
 }
 
 impl<'a> Refund<'a> {
-  pub const DISCRIMINATOR: &'a u8 = &2;
+  pub const DISCRIMINATOR: &'a u8 = &(Instruction::Refund as u8);
   
   pub fn process(&mut self) -> ProgramResult {
     let data = self.accounts.escrow.try_borrow_data()?;
@@ -88,40 +132,80 @@ impl<'a> Refund<'a> {
     // Check if the escrow is valid
     let escrow_key = create_program_address(
       &[
-        b"escrow", 
+        Escrow::SEED, 
         self.accounts.maker.key(), 
-        &escrow.seed.to_le_bytes(), 
-        &escrow.bump
+        &escrow.seed().to_le_bytes(), 
+        &[escrow.bump()]
         ], 
         &crate::ID
     )?;
 
     if &escrow_key != self.accounts.escrow.key() {
-      return Err(ProgramError::InvalidAccountOwner);
+      return Err(PinocchioError::InvalidEscrowPda.into());
+    }
+
+    // Don't rely on the seeds alone to tie the stored maker to the signer
+    if escrow.maker() != self.accounts.maker.key() {
+      return Err(PinocchioError::InvalidMaker.into());
+    }
+
+    #[cfg(feature = "debug-checks")]
+    check_canonical_escrow(&escrow_key, self.accounts.maker.key(), escrow.seed())?;
+
+    // The vault is derived from mint_a, so this pins it to the escrow's vault
+    if escrow.mint_a() != self.accounts.mint_a.key() {
+      return Err(PinocchioError::InvalidMint.into());
     }
     
-    let seed_binding = escrow.seed.to_le_bytes();
-    let bump_binding = escrow.bump;
-    let escrow_seeds = [
-      Seed::from(b"escrow"),
-      Seed::from(self.accounts.maker.key().as_ref()),
-      Seed::from(&seed_binding),
-      Seed::from(&bump_binding),
-    ];
+    let escrow_seeds = escrow.signer_seeds(self.accounts.maker.key());
     let signer = Signer::from(&escrow_seeds);
 
-    let amount = {
-      let vault = TokenAccount::from_account_info(self.accounts.vault)?;
-      vault.amount()
-    };
-    
-    // Transfer from the Vault to the Maker
-    Transfer {
-      from: self.accounts.vault,
-      to: self.accounts.maker_ata_a,
-      authority: self.accounts.escrow,
+    let amount = escrow.amount();
+    let balance = token_amount(self.accounts.vault)?;
+    if balance < amount {
+      return Err(PinocchioError::InsufficientVaultBalance.into());
+    }
+    let mint_a_decimals = mint_decimals(self.accounts.mint_a)?;
+
+    // A partial refund takes some of the deposit back and leaves the rest open
+    // at the same price
+    if let Some(partial) = self.instruction_data.amount {
+      if partial > amount {
+        return Err(ProgramError::InvalidInstructionData);
+      }
+
+      if partial < amount {
+        TransferChecked {
+          from: self.accounts.vault,
+          mint: self.accounts.mint_a,
+          to: self.accounts.maker_ata_a,
+          authority: self.accounts.escrow,
+          amount: partial,
+          decimals: mint_a_decimals,
+        }.invoke_signed(&[signer.clone()])?;
+
+        drop(data);
+        let mut data = self.accounts.escrow.try_borrow_mut_data()?;
+        Escrow::load_mut(data.as_mut())?.withdraw(partial)?;
+
+        return Ok(());
+      }
+    }
+
+    // Tokens donated on top of the recorded deposit are swept along,
+    // otherwise the vault couldn't be closed
+    let surplus = balance - amount;
+
+    // Transfer the recorded deposit, and any surplus, from the Vault to the Maker
+    drain_vault(
+      self.accounts.vault,
+      self.accounts.mint_a,
+      self.accounts.maker_ata_a,
+      self.accounts.escrow,
+      &signer,
       amount,
-    }.invoke_signed(&[signer.clone()])?;
+      surplus,
+    )?;
 
     // Close the Vault
     CloseAccount {
@@ -136,4 +220,29 @@ impl<'a> Refund<'a> {
 
     Ok(())
   }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+
+  #[test]
+  fn refunds_reject_a_vault_that_isnt_a_token_account() {
+    let (maker_key, mint) = ([1; 32], [2; 32]);
+    let mut maker = TestAccount::new(maker_key, [0; 32], &[]).signer();
+    let mut escrow = TestAccount::escrow([3; 32], &escrow(maker_key, mint, [4; 32])).writable();
+    let mut mint_a = TestAccount::mint(mint, 6);
+    // Right size, but the maker's own account rather than the escrow's vault
+    let mut vault = TestAccount::new([5; 32], [9; 32], &[0; 165]).writable();
+    let mut maker_ata_a = TestAccount::token([6; 32], mint, maker_key, 0).writable();
+    let mut programs = [[7; 32], pinocchio_token::ID, [8; 32]].map(|key| TestAccount::new(key, [0; 32], &[]));
+    let [system_program, token_program, associated_token_program] = &mut programs;
+
+    let accounts = [
+      maker.info(), escrow.info(), mint_a.info(), vault.info(), maker_ata_a.info(),
+      system_program.info(), token_program.info(), associated_token_program.info(),
+    ];
+    assert_eq!(RefundAccounts::try_from(&accounts[..]).err(), Some(PinocchioError::InvalidVault.into()));
+  }
+}
@@ -0,0 +1,151 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::errors::PinocchioError;
+
+/// What an instruction expects to find at one account position
+pub struct AccountRole {
+    pub signer: bool,
+    pub writable: bool,
+    pub owner: Option<&'static Pubkey>,
+}
+
+impl AccountRole {
+    pub const fn readonly() -> Self {
+        Self { signer: false, writable: false, owner: None }
+    }
+
+    pub const fn writable() -> Self {
+        Self { signer: false, writable: true, owner: None }
+    }
+
+    pub const fn signer() -> Self {
+        Self { signer: true, writable: true, owner: None }
+    }
+
+    /// Signs to authorize, but nothing is debited from it
+    pub const fn readonly_signer() -> Self {
+        Self { signer: true, writable: false, owner: None }
+    }
+
+    pub const fn owned_by(self, owner: &'static Pubkey) -> Self {
+        Self { owner: Some(owner), ..self }
+    }
+}
+
+/// Which expectation an account slot broke
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotViolation {
+    Missing,
+    NotSigner,
+    NotWritable,
+    InvalidOwner,
+}
+
+/// First account slot that doesn't match its role, `index` is its position
+#[derive(Debug, PartialEq, Eq)]
+pub struct AccountSlotError {
+    pub index: usize,
+    pub violation: SlotViolation,
+}
+
+/// Slot errors surface as custom codes from here on, so clients can tell which
+/// account was wrong: the slot is `(code - ACCOUNT_SLOT_ERROR) / 4` and the
+/// remainder is the `SlotViolation`. Read-only slots are the exception, they keep
+/// reporting `PinocchioError::NotWritable` like every other writable check.
+pub const ACCOUNT_SLOT_ERROR: u32 = 0x100;
+
+impl AccountSlotError {
+    pub const fn code(&self) -> u32 {
+        ACCOUNT_SLOT_ERROR + self.index as u32 * 4 + self.violation as u32
+    }
+}
+
+impl From<AccountSlotError> for ProgramError {
+    fn from(e: AccountSlotError) -> Self {
+        match e.violation {
+            SlotViolation::NotWritable => PinocchioError::NotWritable.into(),
+            _ => ProgramError::Custom(e.code()),
+        }
+    }
+}
+
+/// Check `accounts` positionally against `schema`, reporting the first bad slot.
+/// Accounts past the end of the schema aren't checked, clients may append extras,
+/// so only the slots the schema covers are handed back.
+pub fn validate_accounts<'a>(accounts: &'a [AccountInfo], schema: &[AccountRole]) -> Result<&'a [AccountInfo], AccountSlotError> {
+    if accounts.len() < schema.len() {
+        return Err(AccountSlotError { index: accounts.len(), violation: SlotViolation::Missing });
+    }
+
+    for (index, (account, role)) in accounts.iter().zip(schema).enumerate() {
+        let violation = if role.signer && !account.is_signer() {
+            Some(SlotViolation::NotSigner)
+        } else if role.writable && !account.is_writable() {
+            Some(SlotViolation::NotWritable)
+        } else if role.owner.is_some_and(|owner| !account.is_owned_by(owner)) {
+            Some(SlotViolation::InvalidOwner)
+        } else {
+            None
+        };
+
+        if let Some(violation) = violation {
+            return Err(AccountSlotError { index, violation });
+        }
+    }
+
+    Ok(&accounts[..schema.len()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    const SCHEMA: [AccountRole; 2] = [
+        AccountRole::signer(),
+        AccountRole::writable().owned_by(&crate::ID),
+    ];
+
+    #[test]
+    fn reports_the_first_bad_slot() {
+        let mut signer = TestAccount::new([1; 32], [0; 32], &[]).signer();
+        let mut readonly = TestAccount::new([1; 32], [0; 32], &[]);
+        let mut owned = TestAccount::new([2; 32], crate::ID, &[]).writable();
+        let mut foreign = TestAccount::new([2; 32], [9; 32], &[]).writable();
+
+        assert_eq!(validate_accounts(&[signer.info(), owned.info()], &SCHEMA).map(<[_]>::len), Ok(2));
+        // Extras aren't checked or handed back
+        assert_eq!(validate_accounts(&[signer.info(), owned.info(), readonly.info()], &SCHEMA).map(<[_]>::len), Ok(2));
+        assert_eq!(
+            validate_accounts(&[signer.info()], &SCHEMA).err(),
+            Some(AccountSlotError { index: 1, violation: SlotViolation::Missing })
+        );
+        assert_eq!(
+            validate_accounts(&[readonly.info(), foreign.info()], &SCHEMA).err(),
+            Some(AccountSlotError { index: 0, violation: SlotViolation::NotSigner })
+        );
+        assert_eq!(
+            validate_accounts(&[signer.info(), readonly.info()], &SCHEMA).err(),
+            Some(AccountSlotError { index: 1, violation: SlotViolation::NotWritable })
+        );
+        assert_eq!(
+            validate_accounts(&[signer.info(), foreign.info()], &SCHEMA).err(),
+            Some(AccountSlotError { index: 1, violation: SlotViolation::InvalidOwner })
+        );
+    }
+
+    #[test]
+    fn the_error_code_carries_the_slot() {
+        let error = AccountSlotError { index: 3, violation: SlotViolation::InvalidOwner };
+        assert_eq!(ProgramError::from(error), ProgramError::Custom(ACCOUNT_SLOT_ERROR + 15));
+
+        let missing = AccountSlotError { index: 0, violation: SlotViolation::Missing };
+        assert_eq!(ProgramError::from(missing), ProgramError::Custom(ACCOUNT_SLOT_ERROR));
+    }
+
+    #[test]
+    fn read_only_slots_keep_the_not_writable_error() {
+        let error = AccountSlotError { index: 1, violation: SlotViolation::NotWritable };
+        assert_eq!(ProgramError::from(error), PinocchioError::NotWritable.into());
+    }
+}
@@ -1,16 +1,16 @@
 use pinocchio::{
-    account_info::AccountInfo, instruction::{Seed, Signer},
+    account_info::AccountInfo, instruction::Signer,
     pubkey::create_program_address, program_error::ProgramError, 
-    ProgramResult
+    sysvars::{clock::Clock, Sysvar}, ProgramResult
 };
 
-use pinocchio_token::{
-    state::TokenAccount,
-    instructions::{Transfer, CloseAccount},
-};
+use pinocchio_token::instructions::{TransferChecked, CloseAccount};
 
 use crate::Escrow;
+use crate::errors::PinocchioError;
+use super::discriminator::Instruction;
 use super::helpers::*;
+use super::schema::*;
 
 pub struct TakeAccounts<'a> {
   pub taker: &'a AccountInfo,
@@ -26,11 +26,28 @@ pub struct TakeAccounts<'a> {
   pub token_program: &'a AccountInfo,
 }
 
+impl<'a> TakeAccounts<'a> {
+  pub const SCHEMA: [AccountRole; 12] = [
+    AccountRole::signer(),                          // taker
+    AccountRole::writable(),                        // maker
+    AccountRole::writable().owned_by(&crate::ID),   // escrow
+    AccountRole::readonly(),                        // mint_a
+    AccountRole::readonly(),                        // mint_b
+    AccountRole::writable(),                        // vault
+    AccountRole::writable(),                        // taker_ata_a
+    AccountRole::writable(),                        // taker_ata_b
+    AccountRole::writable(),                        // maker_ata_b
+    AccountRole::readonly(),                        // system_program
+    AccountRole::readonly(),                        // token_program
+    AccountRole::readonly(),                        // associated_token_program
+  ];
+}
+
 impl<'a> TryFrom<&'a [AccountInfo]> for TakeAccounts<'a> {
   type Error = ProgramError;
 
   fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-    let [taker, maker, escrow, mint_a, mint_b, vault, taker_ata_a, taker_ata_b, maker_ata_b, system_program, token_program, _] = accounts else {
+    let [taker, maker, escrow, mint_a, mint_b, vault, taker_ata_a, taker_ata_b, maker_ata_b, system_program, token_program, _associated_token_program] = validate_accounts(accounts, &Self::SCHEMA)? else {
       return Err(ProgramError::NotEnoughAccountKeys);
     };
 
@@ -40,7 +57,8 @@ impl<'a> TryFrom<&'a [AccountInfo]> for TakeAccounts<'a> {
     MintInterface::check(mint_a)?;
     MintInterface::check(mint_b)?;
     AssociatedTokenAccount::check(taker_ata_b, taker, mint_b, token_program)?;
-    AssociatedTokenAccount::check(vault, escrow, mint_a, token_program)?;
+    AssociatedTokenAccount::check(vault, escrow, mint_a, token_program)
+      .map_err(|_| ProgramError::from(PinocchioError::InvalidVault))?;
 
     // Return the accounts
     Ok(Self {
@@ -59,15 +77,39 @@ impl<'a> TryFrom<&'a [AccountInfo]> for TakeAccounts<'a> {
   }
 }
 
+pub struct TakeInstructionData {
+  pub min_amount_a: u64,
+}
+
+impl<'a> TryFrom<&'a [u8]> for TakeInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    // No data takes without a slippage guard, anything else must be exactly the minimum
+    let min_amount_a = match data.len() {
+      0 => 0,
+      8 => u64::from_le_bytes(data[0..8].try_into().unwrap()),
+      _ => return Err(ProgramError::InvalidInstructionData),
+    };
+
+    Ok(Self {
+      min_amount_a,
+    })
+  }
+}
+
+
 pub struct Take<'a> {
   pub accounts: TakeAccounts<'a>,
+  pub instruction_data: TakeInstructionData,
 }
 
-impl<'a> TryFrom<&'a [AccountInfo]> for Take<'a> {
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Take<'a> {
   type Error = ProgramError;
   
-  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
     let accounts = TakeAccounts::try_from(accounts)?;
+    let instruction_data = TakeInstructionData::try_from(data)?;
 
     // Initialize necessary accounts
     AssociatedTokenAccount::init_if_needed(
@@ -90,12 +132,13 @@ impl<'a> TryFrom<&'a [AccountInfo]> for Take<'a> {
 
     Ok(Self {
       accounts,
+      instruction_data,
     })
   }
 }
 
 impl<'a> Take<'a> {
-  pub const DISCRIMINATOR: &'a u8 = &1;
+  pub const DISCRIMINATOR: &'a u8 = &(Instruction::Take as u8);
   
   pub fn process(&mut self) -> ProgramResult {
     let data = self.accounts.escrow.try_borrow_data()?;
@@ -104,40 +147,72 @@ impl<'a> Take<'a> {
     // Check if the escrow is valid
     let escrow_key = create_program_address(
       &[
-        b"escrow", 
+        Escrow::SEED, 
         self.accounts.maker.key(), 
-        &escrow.seed.to_le_bytes(), 
-        &escrow.bump
+        &escrow.seed().to_le_bytes(), 
+        &[escrow.bump()]
         ], 
         &crate::ID
     )?;
 
     if &escrow_key != self.accounts.escrow.key() {
-      return Err(ProgramError::InvalidAccountOwner);
+      return Err(PinocchioError::InvalidEscrowPda.into());
+    }
+
+    // The maker doesn't sign a take, so the seeds are all that tie this account
+    // to the escrow. Check the stored maker too, it's who gets paid.
+    if escrow.maker() != self.accounts.maker.key() {
+      return Err(PinocchioError::InvalidMaker.into());
+    }
+
+    #[cfg(feature = "debug-checks")]
+    check_canonical_escrow(&escrow_key, self.accounts.maker.key(), escrow.seed())?;
+
+    // The vault is derived from mint_a, so this pins it to the escrow's vault,
+    // and mint_b has to be what the maker asked for
+    if escrow.mint_a() != self.accounts.mint_a.key() || escrow.mint_b() != self.accounts.mint_b.key() {
+      return Err(PinocchioError::InvalidMint.into());
+    }
+
+    // Expired escrows can only be refunded
+    if escrow.is_expired(Clock::get()?.unix_timestamp) {
+      return Err(PinocchioError::EscrowExpired.into());
+    }
+
+    // Private escrows can only be filled by their counterparty
+    if !escrow.can_take(self.accounts.taker.key()) {
+      return Err(PinocchioError::UnauthorizedTaker.into());
     }
     
-    let seed_binding = escrow.seed.to_le_bytes();
-    let bump_binding = escrow.bump;
-    let escrow_seeds = [
-      Seed::from(b"escrow"),
-      Seed::from(self.accounts.maker.key().as_ref()),
-      Seed::from(&seed_binding),
-      Seed::from(&bump_binding),
-    ];
+    let escrow_seeds = escrow.signer_seeds(self.accounts.maker.key());
     let signer = Signer::from(&escrow_seeds);
 
-    let amount = {
-      let vault = TokenAccount::from_account_info(self.accounts.vault)?;
-      vault.amount()
-    };
-        
-    // Transfer from the Vault to the Taker
-    Transfer {
-      from: self.accounts.vault,
-      to: self.accounts.taker_ata_a,
-      authority: self.accounts.escrow,
+    // Tokens donated on top of the recorded deposit are swept along,
+    // otherwise the vault couldn't be closed
+    let amount = escrow.amount();
+    let balance = token_amount(self.accounts.vault)?;
+    if balance < amount {
+      return Err(PinocchioError::InsufficientVaultBalance.into());
+    }
+    let surplus = balance - amount;
+
+    // Protect the taker against terms changing under them
+    if amount.saturating_add(surplus) < self.instruction_data.min_amount_a {
+      return Err(PinocchioError::SlippageExceeded.into());
+    }
+
+    let mint_b_decimals = mint_decimals(self.accounts.mint_b)?;
+
+    // Transfer the recorded deposit, and any surplus, from the Vault to the Taker
+    drain_vault(
+      self.accounts.vault,
+      self.accounts.mint_a,
+      self.accounts.taker_ata_a,
+      self.accounts.escrow,
+      &signer,
       amount,
-    }.invoke_signed(&[signer.clone()])?;
+      surplus,
+    )?;
 
     // Close the Vault
     CloseAccount {
@@ -147,11 +222,13 @@ impl<'a> Take<'a> {
     }.invoke_signed(&[signer.clone()])?;
 
     // Transfer from the Taker to the Maker
-    Transfer {
+    TransferChecked {
       from: self.accounts.taker_ata_b,
+      mint: self.accounts.mint_b,
       to: self.accounts.maker_ata_b,
       authority: self.accounts.taker,
-      amount: escrow.receive,
+      amount: escrow.receive(),
+      decimals: mint_b_decimals,
     }.invoke()?;
 
     // Close the Escrow
@@ -0,0 +1,175 @@
+use pinocchio::{
+    account_info::AccountInfo, instruction::Signer,
+    program_error::ProgramError, pubkey::create_program_address,
+    sysvars::{clock::Clock, Sysvar}, ProgramResult
+    
+};
+
+use pinocchio_token::instructions::CloseAccount;
+
+
+use crate::Escrow;
+use crate::errors::PinocchioError;
+use super::discriminator::Instruction;
+use super::helpers::*;
+use super::schema::*;
+
+pub struct TimeoutRefundAccounts<'a> {
+  pub caller: &'a AccountInfo,
+  pub maker: &'a AccountInfo,
+  pub escrow: &'a AccountInfo,
+  pub mint_a: &'a AccountInfo,
+  pub vault: &'a AccountInfo,
+  pub maker_ata_a: &'a AccountInfo,
+  pub system_program: &'a AccountInfo,
+  pub token_program: &'a AccountInfo,
+}
+
+impl<'a> TimeoutRefundAccounts<'a> {
+  pub const SCHEMA: [AccountRole; 9] = [
+    AccountRole::signer(),                          // caller
+    AccountRole::writable(),                        // maker
+    AccountRole::writable().owned_by(&crate::ID),   // escrow
+    AccountRole::readonly(),                        // mint_a
+    AccountRole::writable(),                        // vault
+    AccountRole::writable(),                        // maker_ata_a
+    AccountRole::readonly(),                        // system_program
+    AccountRole::readonly(),                        // token_program
+    AccountRole::readonly(),                        // associated_token_program
+  ];
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for TimeoutRefundAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [caller, maker, escrow, mint_a, vault, maker_ata_a, system_program, token_program, _associated_token_program] = validate_accounts(accounts, &Self::SCHEMA)? else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Basic Accounts Checks
+    SignerAccount::check(caller)?;
+    ProgramAccount::check(escrow)?;
+    MintInterface::check(mint_a)?;
+    AssociatedTokenAccount::check(vault, escrow, mint_a, token_program)
+      .map_err(|_| ProgramError::from(PinocchioError::InvalidVault))?;
+
+    // Return the accounts
+    Ok(Self {
+      caller,
+      maker,
+      escrow,
+      mint_a,
+      vault,
+      maker_ata_a,
+      system_program,
+      token_program,
+    })
+  }
+}
+
+
+pub struct TimeoutRefund<'a> {
+  pub accounts: TimeoutRefundAccounts<'a>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for TimeoutRefund<'a> {
+  type Error = ProgramError;
+  
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let accounts = TimeoutRefundAccounts::try_from(accounts)?;
+
+    // Initialize necessary accounts
+    AssociatedTokenAccount::init_if_needed(
+      accounts.maker_ata_a,
+      accounts.mint_a,
+      accounts.caller,
+      accounts.maker,
+      accounts.system_program,
+      accounts.token_program,
+    )?;
+ 
+    Ok(Self {
+      accounts,
+    })
+  }
+}
+
+impl<'a> TimeoutRefund<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &(Instruction::TimeoutRefund as u8);
+  
+  pub fn process(&mut self) -> ProgramResult {
+    let data = self.accounts.escrow.try_borrow_data()?;
+    let escrow = Escrow::load(&data)?;
+
+    // Check if the escrow is valid
+    let escrow_key = create_program_address(
+      &[
+        Escrow::SEED, 
+        self.accounts.maker.key(), 
+        &escrow.seed().to_le_bytes(), 
+        &[escrow.bump()]
+        ], 
+        &crate::ID
+    )?;
+
+    if &escrow_key != self.accounts.escrow.key() {
+      return Err(PinocchioError::InvalidEscrowPda.into());
+    }
+
+    // Anyone can call this and the maker doesn't sign, so the seeds are all that
+    // tie this account to the escrow. Check the stored maker too, it's who gets the refund.
+    if escrow.maker() != self.accounts.maker.key() {
+      return Err(PinocchioError::InvalidMaker.into());
+    }
+
+    #[cfg(feature = "debug-checks")]
+    check_canonical_escrow(&escrow_key, self.accounts.maker.key(), escrow.seed())?;
+
+    // The vault is derived from mint_a, so this pins it to the escrow's vault
+    if escrow.mint_a() != self.accounts.mint_a.key() {
+      return Err(PinocchioError::InvalidMint.into());
+    }
+
+    // Anyone can refund, but only once the deadline has passed
+    if !escrow.is_expired(Clock::get()?.unix_timestamp) {
+      return Err(PinocchioError::EscrowNotExpired.into());
+    }
+    
+    let escrow_seeds = escrow.signer_seeds(self.accounts.maker.key());
+    let signer = Signer::from(&escrow_seeds);
+
+    // Tokens donated on top of the recorded deposit are swept along,
+    // otherwise the vault couldn't be closed
+    let amount = escrow.amount();
+    let balance = token_amount(self.accounts.vault)?;
+    if balance < amount {
+      return Err(PinocchioError::InsufficientVaultBalance.into());
+    }
+    let surplus = balance - amount;
+
+    // Transfer the recorded deposit, and any surplus, from the Vault to the Maker
+    drain_vault(
+      self.accounts.vault,
+      self.accounts.mint_a,
+      self.accounts.maker_ata_a,
+      self.accounts.escrow,
+      &signer,
+      amount,
+      surplus,
+    )?;
+
+    // Close the Vault
+    CloseAccount {
+      account: self.accounts.vault,
+      destination: self.accounts.maker,
+      authority: self.accounts.escrow,
+    }.invoke_signed(&[signer.clone()])?;
+
+    // Close the Escrow
+    drop(data);
+    ProgramAccount::close(self.accounts.escrow, self.accounts.maker)?;
+
+    Ok(())
+  }
+}
\ No newline at end of file
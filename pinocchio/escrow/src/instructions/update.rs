@@ -0,0 +1,180 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError,
+    pubkey::create_program_address, ProgramResult
+};
+
+use crate::Escrow;
+use crate::errors::PinocchioError;
+use super::discriminator::Instruction;
+use super::helpers::*;
+use super::schema::*;
+
+use core::mem::size_of;
+
+pub struct UpdateAccounts<'a> {
+  pub maker: &'a AccountInfo,
+  pub escrow: &'a AccountInfo,
+}
+
+impl<'a> UpdateAccounts<'a> {
+  pub const SCHEMA: [AccountRole; 2] = [
+    AccountRole::readonly_signer(),                 // maker
+    AccountRole::writable().owned_by(&crate::ID),   // escrow
+  ];
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for UpdateAccounts<'a> {
+  type Error = ProgramError;
+
+  fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+    let [maker, escrow] = validate_accounts(accounts, &Self::SCHEMA)? else {
+      return Err(ProgramError::NotEnoughAccountKeys);
+    };
+
+    // Basic Accounts Checks
+    SignerAccount::check(maker)?;
+    ProgramAccount::check(escrow)?;
+
+    // Return the accounts
+    Ok(Self {
+      maker,
+      escrow,
+    })
+  }
+}
+
+
+pub struct UpdateInstructionData {
+  pub new_receive: u64,
+}
+
+impl<'a> TryFrom<&'a [u8]> for UpdateInstructionData {
+  type Error = ProgramError;
+
+  fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+    if data.len() != size_of::<u64>() {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let new_receive = u64::from_le_bytes(data[0..8].try_into().unwrap());
+
+    // Asking for nothing would give Token A away on Take
+    if new_receive == 0 {
+      return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok(Self {
+      new_receive,
+    })
+  }
+}
+
+
+pub struct Update<'a> {
+  pub accounts: UpdateAccounts<'a>,
+  pub instruction_data: UpdateInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Update<'a> {
+  type Error = ProgramError;
+
+  fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+    let accounts = UpdateAccounts::try_from(accounts)?;
+    let instruction_data = UpdateInstructionData::try_from(data)?;
+
+    Ok(Self {
+      accounts,
+      instruction_data,
+    })
+  }
+}
+
+impl<'a> Update<'a> {
+  pub const DISCRIMINATOR: &'a u8 = &(Instruction::Update as u8);
+
+  pub fn process(&mut self) -> ProgramResult {
+    let mut data = self.accounts.escrow.try_borrow_mut_data()?;
+    let escrow = Escrow::load_mut(data.as_mut())?;
+
+    // Check if the escrow is valid
+    let escrow_key = create_program_address(
+      &[
+        Escrow::SEED,
+        self.accounts.maker.key(),
+        &escrow.seed().to_le_bytes(),
+        &[escrow.bump()]
+        ],
+        &crate::ID
+    )?;
+
+    if &escrow_key != self.accounts.escrow.key() {
+      return Err(PinocchioError::InvalidEscrowPda.into());
+    }
+
+    // Only the maker can reprice
+    if escrow.maker() != self.accounts.maker.key() {
+      return Err(PinocchioError::InvalidMaker.into());
+    }
+
+    escrow.set_receive(self.instruction_data.new_receive);
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::test_utils::*;
+
+  #[test]
+  fn trailing_accounts_are_ignored_but_the_slots_still_checked() {
+    let maker_key = [1; 32];
+    // Update never debits the maker, so a read-only signer is enough
+    let mut maker = TestAccount::new(maker_key, [0; 32], &[]).signed();
+    let mut unsigned = TestAccount::new(maker_key, [0; 32], &[]).writable();
+    let state = escrow(maker_key, [3; 32], [4; 32]);
+    let mut escrow = TestAccount::escrow([2; 32], &state).writable();
+    let mut readonly_escrow = TestAccount::escrow([2; 32], &state);
+    let mut extras = [[5; 32], [6; 32]].map(|key| TestAccount::new(key, [9; 32], &[]));
+    let [first, second] = &mut extras;
+    let data = 2_000u64.to_le_bytes();
+
+    let accounts = [maker.info(), escrow.info(), first.info(), second.info()];
+    let update = Update::try_from((&data[..], &accounts[..])).unwrap();
+    assert_eq!((update.accounts.maker.key(), update.accounts.escrow.key()), (&maker_key, &[2; 32]));
+    assert_eq!(update.instruction_data.new_receive, 2_000);
+
+    let accounts = [unsigned.info(), escrow.info(), first.info(), second.info()];
+    assert_eq!(
+      Update::try_from((&data[..], &accounts[..])).err(),
+      Some(AccountSlotError { index: 0, violation: SlotViolation::NotSigner }.into())
+    );
+
+    let accounts = [maker.info(), readonly_escrow.info(), first.info(), second.info()];
+    assert_eq!(Update::try_from((&data[..], &accounts[..])).err(), Some(PinocchioError::NotWritable.into()));
+  }
+
+  #[cfg(feature = "client")]
+  #[test]
+  fn accounts_from_the_client_builder_parse() {
+    let maker = solana_pubkey::Pubkey::new_from_array([1; 32]);
+    let ix = crate::client::update_ix(&maker, 42, 2_000);
+    let state = escrow(maker.to_bytes(), [3; 32], [4; 32]);
+
+    let mut accounts: Vec<TestAccount> = ix.accounts.iter().map(|meta| {
+      let account = if meta.pubkey == maker {
+        TestAccount::new(maker.to_bytes(), [0; 32], &[])
+      } else {
+        TestAccount::escrow(meta.pubkey.to_bytes(), &state)
+      };
+      let account = if meta.is_writable { account.writable() } else { account };
+      if meta.is_signer { account.signed() } else { account }
+    }).collect();
+    let accounts: Vec<AccountInfo> = accounts.iter_mut().map(TestAccount::info).collect();
+
+    let update = Update::try_from((&ix.data[1..], &accounts[..])).unwrap();
+    assert_eq!(update.accounts.maker.key(), &maker.to_bytes());
+    assert_eq!(update.instruction_data.new_receive, 2_000);
+  }
+}
@@ -15,6 +15,12 @@ pub use state::*;
 pub mod errors;
 pub use errors::*;
 
+#[cfg(feature = "client")]
+pub mod client;
+
+#[cfg(test)]
+mod test_utils;
+
 // 22222222222222222222222222222222222222222222
 pub const ID: Pubkey = [
     0x0f, 0x1e, 0x6b, 0x14, 0x21, 0xc0, 0x4a, 0x07,
@@ -28,10 +34,16 @@ fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    match instruction_data.split_first() {
-        Some((Make::DISCRIMINATOR, data)) => Make::try_from((data, accounts))?.process(),
-        Some((Take::DISCRIMINATOR, _)) => Take::try_from(accounts)?.process(),
-        Some((Refund::DISCRIMINATOR, _)) => Refund::try_from(accounts)?.process(),
+    let (discriminator, data) = instruction_data
+        .split_first()
+        .ok_or(ProgramError::InvalidInstructionData)?;
+
+    match Instruction::try_from(*discriminator)? {
+        Instruction::Make => Make::try_from((data, accounts))?.process(),
+        Instruction::Take => Take::try_from((data, accounts))?.process(),
+        Instruction::Refund => Refund::try_from((data, accounts))?.process(),
+        Instruction::TimeoutRefund if data.is_empty() => TimeoutRefund::try_from(accounts)?.process(),
+        Instruction::Update => Update::try_from((data, accounts))?.process(),
         _ => Err(ProgramError::InvalidInstructionData)
     }
 }
\ No newline at end of file
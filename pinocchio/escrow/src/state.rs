@@ -1,43 +1,206 @@
-use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
+use bytemuck::{Pod, Zeroable};
+use pinocchio::{instruction::Seed, program_error::ProgramError, pubkey::Pubkey};
 use core::mem::size_of;
 
+/// Every field is a byte array so the struct has alignment 1, no padding, and
+/// can be viewed in place over account data, which is only guaranteed 1-byte aligned.
 #[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
 pub struct Escrow {
-    pub seed: u64,        // Random seed for PDA derivation
-    pub maker: Pubkey,    // Creator of the escrow
-    pub mint_a: Pubkey,   // Token being deposited
-    pub mint_b: Pubkey,   // Token being requested
-    pub receive: u64,     // Amount of token B wanted
-    pub bump: [u8;1]      // PDA bump seed
+    discriminator: u8,    // Account kind, always Escrow::DISCRIMINATOR
+    seed: [u8;8],         // Random seed for PDA derivation (LE u64)
+    maker: Pubkey,        // Creator of the escrow
+    mint_a: Pubkey,       // Token being deposited
+    mint_b: Pubkey,       // Token being requested
+    receive: [u8;8],      // Amount of token B wanted (LE u64)
+    amount: [u8;8],       // Amount of token A deposited (LE u64)
+    deadline: [u8;8],     // Unix timestamp after which anyone can refund, 0 = never (LE i64)
+    allowed_taker: Pubkey, // Only taker allowed to fill, default = anyone
+    created_at: [u8;8],   // Unix timestamp of the make (LE i64)
+    bump: [u8;1]          // PDA bump seed
+}
+
+// bytemuck rejects misaligned or wrongly sized buffers at runtime, this keeps
+// `LEN` and the 1-byte alignment account data relies on honest at compile time
+const _: () = assert!(core::mem::align_of::<Escrow>() == 1);
+const _: () = assert!(size_of::<Escrow>() == Escrow::LEN);
+
+/// Owned copy of the escrow fields, read without any pointer casts
+pub struct EscrowView {
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub receive: u64,
+    pub amount: u64,
+    pub deadline: i64,
+    pub allowed_taker: Pubkey,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+/// Read a little-endian u64 at `offset`, failing on short buffers
+#[inline(always)]
+pub fn read_u64_at(data: &[u8], offset: usize) -> Result<u64, ProgramError> {
+    let end = offset.checked_add(size_of::<u64>()).ok_or(ProgramError::InvalidAccountData)?;
+    let bytes = data.get(offset..end).ok_or(ProgramError::InvalidAccountData)?;
+    Ok(u64::from_le_bytes(bytes.try_into().map_err(|_| ProgramError::InvalidAccountData)?))
+}
+
+/// Read a little-endian i64 at `offset`, failing on short buffers
+#[inline(always)]
+pub fn read_i64_at(data: &[u8], offset: usize) -> Result<i64, ProgramError> {
+    read_u64_at(data, offset).map(|value| value as i64)
+}
+
+/// Read a pubkey at `offset`, failing on short buffers
+#[inline(always)]
+pub fn read_pubkey_at(data: &[u8], offset: usize) -> Result<Pubkey, ProgramError> {
+    let end = offset.checked_add(size_of::<Pubkey>()).ok_or(ProgramError::InvalidAccountData)?;
+    let bytes = data.get(offset..end).ok_or(ProgramError::InvalidAccountData)?;
+    bytes.try_into().map_err(|_| ProgramError::InvalidAccountData)
 }
 
 impl Escrow {
-    pub const LEN: usize = size_of::<u64>() 
+    pub const DISCRIMINATOR: u8 = 0x01;
+
+    /// First PDA seed, followed by the maker and the LE seed. The Anchor
+    /// escrow derives the same address, so this has to stay in step with it.
+    pub const SEED: &'static [u8] = b"escrow";
+
+    pub const DISCRIMINATOR_OFFSET: usize = 0;
+    pub const SEED_OFFSET: usize = Self::DISCRIMINATOR_OFFSET + size_of::<u8>();
+    pub const MAKER_OFFSET: usize = Self::SEED_OFFSET + size_of::<u64>();
+    pub const MINT_A_OFFSET: usize = Self::MAKER_OFFSET + size_of::<Pubkey>();
+    pub const MINT_B_OFFSET: usize = Self::MINT_A_OFFSET + size_of::<Pubkey>();
+    pub const RECEIVE_OFFSET: usize = Self::MINT_B_OFFSET + size_of::<Pubkey>();
+    pub const AMOUNT_OFFSET: usize = Self::RECEIVE_OFFSET + size_of::<u64>();
+    pub const DEADLINE_OFFSET: usize = Self::AMOUNT_OFFSET + size_of::<u64>();
+    pub const ALLOWED_TAKER_OFFSET: usize = Self::DEADLINE_OFFSET + size_of::<i64>();
+    pub const CREATED_AT_OFFSET: usize = Self::ALLOWED_TAKER_OFFSET + size_of::<Pubkey>();
+    pub const BUMP_OFFSET: usize = Self::CREATED_AT_OFFSET + size_of::<i64>();
+
+    pub const LEN: usize = size_of::<u8>()
+    + size_of::<u64>() 
     + size_of::<Pubkey>() 
     + size_of::<Pubkey>() 
     + size_of::<Pubkey>() 
     + size_of::<u64>()
+    + size_of::<u64>()
+    + size_of::<i64>()
+    + size_of::<Pubkey>()
+    + size_of::<i64>()
     + size_of::<[u8;1]>();
 
     #[inline(always)]
     pub fn load_mut(bytes: &mut [u8]) -> Result<&mut Self, ProgramError> {
-        if bytes.len() != Escrow::LEN {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        Ok(unsafe { &mut *core::mem::transmute::<*mut u8, *mut Self>(bytes.as_mut_ptr()) })
+        bytemuck::try_from_bytes_mut(bytes).map_err(|_| ProgramError::InvalidAccountData)
     }
 
     #[inline(always)]
     pub fn load(bytes: &[u8]) -> Result<&Self, ProgramError> {
-        if bytes.len() != Escrow::LEN {
+        let escrow: &Self = bytemuck::try_from_bytes(bytes).map_err(|_| ProgramError::InvalidAccountData)?;
+        if escrow.discriminator != Self::DISCRIMINATOR {
             return Err(ProgramError::InvalidAccountData);
         }
-        Ok(unsafe { &*core::mem::transmute::<*const u8, *const Self>(bytes.as_ptr()) })
+        Ok(escrow)
+    }
+
+    /// Panic-free read of every field, shared by tests and clients
+    pub fn snapshot(data: &[u8]) -> Result<EscrowView, ProgramError> {
+        if data.len() != Escrow::LEN || data[Self::DISCRIMINATOR_OFFSET] != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(EscrowView {
+            seed: read_u64_at(data, Self::SEED_OFFSET)?,
+            maker: read_pubkey_at(data, Self::MAKER_OFFSET)?,
+            mint_a: read_pubkey_at(data, Self::MINT_A_OFFSET)?,
+            mint_b: read_pubkey_at(data, Self::MINT_B_OFFSET)?,
+            receive: read_u64_at(data, Self::RECEIVE_OFFSET)?,
+            amount: read_u64_at(data, Self::AMOUNT_OFFSET)?,
+            deadline: read_i64_at(data, Self::DEADLINE_OFFSET)?,
+            allowed_taker: read_pubkey_at(data, Self::ALLOWED_TAKER_OFFSET)?,
+            created_at: read_i64_at(data, Self::CREATED_AT_OFFSET)?,
+            bump: *data.get(Self::BUMP_OFFSET).ok_or(ProgramError::InvalidAccountData)?,
+        })
+    }
+
+    #[inline(always)]
+    pub fn seed(&self) -> u64 {
+        u64::from_le_bytes(self.seed)
+    }
+
+    #[inline(always)]
+    pub fn maker(&self) -> &Pubkey {
+        &self.maker
+    }
+
+    #[inline(always)]
+    pub fn mint_a(&self) -> &Pubkey {
+        &self.mint_a
+    }
+
+    #[inline(always)]
+    pub fn mint_b(&self) -> &Pubkey {
+        &self.mint_b
+    }
+
+    #[inline(always)]
+    pub fn receive(&self) -> u64 {
+        u64::from_le_bytes(self.receive)
+    }
+
+    #[inline(always)]
+    pub fn amount(&self) -> u64 {
+        u64::from_le_bytes(self.amount)
+    }
+
+    #[inline(always)]
+    pub fn deadline(&self) -> i64 {
+        i64::from_le_bytes(self.deadline)
+    }
+
+    #[inline(always)]
+    pub fn allowed_taker(&self) -> &Pubkey {
+        &self.allowed_taker
+    }
+
+    #[inline(always)]
+    pub fn created_at(&self) -> i64 {
+        i64::from_le_bytes(self.created_at)
+    }
+
+    #[inline(always)]
+    pub fn bump(&self) -> u8 {
+        self.bump[0]
+    }
+
+    /// Signer seeds for the escrow PDA. The seed and bump are borrowed straight
+    /// from the stored LE bytes, so no local bindings are needed to outlive the CPI.
+    #[inline(always)]
+    pub fn signer_seeds<'a>(&'a self, maker: &'a Pubkey) -> [Seed<'a>; 4] {
+        [
+            Seed::from(Self::SEED),
+            Seed::from(maker.as_ref()),
+            Seed::from(&self.seed),
+            Seed::from(&self.bump),
+        ]
+    }
+
+    #[inline(always)]
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.deadline() != 0 && now > self.deadline()
+    }
+
+    #[inline(always)]
+    pub fn can_take(&self, taker: &Pubkey) -> bool {
+        self.allowed_taker == Pubkey::default() || &self.allowed_taker == taker
     }
 
     #[inline(always)]
     pub fn set_seed(&mut self, seed: u64) {
-        self.seed = seed;
+        self.seed = seed.to_le_bytes();
     }
 
     #[inline(always)]
@@ -57,7 +220,27 @@ impl Escrow {
 
     #[inline(always)]
     pub fn set_receive(&mut self, receive: u64) {
-        self.receive = receive;
+        self.receive = receive.to_le_bytes();
+    }
+
+    #[inline(always)]
+    pub fn set_amount(&mut self, amount: u64) {
+        self.amount = amount.to_le_bytes();
+    }
+
+    #[inline(always)]
+    pub fn set_deadline(&mut self, deadline: i64) {
+        self.deadline = deadline.to_le_bytes();
+    }
+
+    #[inline(always)]
+    pub fn set_allowed_taker(&mut self, allowed_taker: Pubkey) {
+        self.allowed_taker = allowed_taker;
+    }
+
+    #[inline(always)]
+    pub fn set_created_at(&mut self, created_at: i64) {
+        self.created_at = created_at.to_le_bytes();
     }
 
     #[inline(always)]
@@ -65,13 +248,178 @@ impl Escrow {
         self.bump = bump;
     }
 
+    /// Takes `partial` of the deposit back out, leaving the escrow open. `receive`
+    /// shrinks in proportion, rounded up so the rest never sells for less.
+    pub fn withdraw(&mut self, partial: u64) -> Result<(), ProgramError> {
+        let amount = self.amount();
+        let left = amount
+            .checked_sub(partial)
+            .filter(|left| *left > 0)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+
+        // At most `receive`, since `left` is below `amount`
+        let receive = (self.receive() as u128 * left as u128).div_ceil(amount as u128);
+        self.set_receive(receive as u64);
+        self.set_amount(left);
+        Ok(())
+    }
+
     #[inline(always)]
-    pub fn set_inner(&mut self, seed: u64, maker: Pubkey, mint_a: Pubkey, mint_b: Pubkey, receive: u64, bump: [u8;1]) {
-        self.seed = seed;
+    #[allow(clippy::too_many_arguments)]
+    pub fn set_inner(&mut self, seed: u64, maker: Pubkey, mint_a: Pubkey, mint_b: Pubkey, receive: u64, amount: u64, bump: [u8;1]) {
+        self.discriminator = Self::DISCRIMINATOR;
+        self.seed = seed.to_le_bytes();
         self.maker = maker;
         self.mint_a = mint_a;
         self.mint_b = mint_b;
-        self.receive = receive;
+        self.receive = receive.to_le_bytes();
+        self.amount = amount.to_le_bytes();
         self.bump = bump;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escrow() -> Escrow {
+        let mut escrow: Escrow = Zeroable::zeroed();
+        escrow.set_inner(42, [1; 32], [2; 32], [3; 32], 1_000, 500, [254]);
+        escrow.set_deadline(1_700_000_060);
+        escrow.set_allowed_taker([4; 32]);
+        escrow.set_created_at(1_700_000_000);
+        escrow
+    }
+
+    #[test]
+    fn reads_fail_on_short_buffers_instead_of_panicking() {
+        let data = [7u8; 12];
+
+        assert_eq!(read_u64_at(&data, 4), Ok(u64::from_le_bytes([7; 8])));
+        assert_eq!(read_u64_at(&data, 5), Err(ProgramError::InvalidAccountData));
+        assert_eq!(read_u64_at(&data, usize::MAX), Err(ProgramError::InvalidAccountData));
+        assert_eq!(read_i64_at(&(-3i64).to_le_bytes(), 0), Ok(-3));
+        assert_eq!(read_pubkey_at(&data, 0), Err(ProgramError::InvalidAccountData));
+        assert_eq!(read_pubkey_at(&[9; 40], 8), Ok([9; 32]));
+    }
+
+    #[test]
+    fn snapshot_reads_every_field_at_its_offset() {
+        let escrow = escrow();
+        let view = Escrow::snapshot(bytemuck::bytes_of(&escrow)).unwrap();
+
+        assert_eq!((view.seed, view.receive, view.amount, view.bump), (42, 1_000, 500, 254));
+        assert_eq!((view.maker, view.mint_a, view.mint_b), ([1; 32], [2; 32], [3; 32]));
+        assert_eq!((view.deadline, view.created_at), (1_700_000_060, 1_700_000_000));
+        assert_eq!(view.allowed_taker, [4; 32]);
+    }
+
+    #[test]
+    fn snapshot_rejects_other_accounts() {
+        let escrow = escrow();
+        let data = bytemuck::bytes_of(&escrow);
+        assert!(Escrow::snapshot(&data[..Escrow::LEN - 1]).is_err());
+
+        let mut closed = data.to_vec();
+        closed[Escrow::DISCRIMINATOR_OFFSET] = 0xff;
+        assert!(Escrow::snapshot(&closed).is_err());
+    }
+
+    #[test]
+    fn loads_work_at_any_alignment() {
+        // Backing u64 words, so offset 1 is misaligned for anything wider than a byte
+        let mut words = [0u64; Escrow::LEN / 8 + 2];
+        let bytes: &mut [u8] = bytemuck::cast_slice_mut(&mut words);
+        let data = &mut bytes[1..Escrow::LEN + 1];
+        data.copy_from_slice(bytemuck::bytes_of(&escrow()));
+
+        Escrow::load_mut(data).unwrap().set_amount(250);
+        let escrow = Escrow::load(data).unwrap();
+        assert_eq!((escrow.amount(), escrow.receive(), escrow.seed()), (250, 1_000, 42));
+    }
+
+    #[test]
+    fn loads_check_the_length_instead_of_reading_past_it() {
+        let mut data = bytemuck::bytes_of(&escrow()).to_vec();
+        data.push(0);
+
+        assert!(Escrow::load(&data[..Escrow::LEN - 1]).is_err());
+        assert!(Escrow::load(&data).is_err());
+        assert!(Escrow::load_mut(&mut data).is_err());
+
+        // Only initialized escrows load read-only
+        data.truncate(Escrow::LEN);
+        data[Escrow::DISCRIMINATOR_OFFSET] = 0;
+        assert!(Escrow::load(&data).is_err());
+        assert!(Escrow::load_mut(&mut data).is_ok());
+    }
+
+    #[test]
+    fn loads_round_trip_through_the_account_bytes() {
+        let mut data = vec![0; Escrow::LEN];
+        let written = Escrow::load_mut(&mut data).unwrap();
+        written.set_inner(42, [1; 32], [2; 32], [3; 32], 1_000, 500, [254]);
+        written.set_deadline(1_700_000_060);
+        written.set_allowed_taker([4; 32]);
+        written.set_created_at(1_700_000_000);
+        assert_eq!(data, bytemuck::bytes_of(&escrow()));
+
+        let escrow = Escrow::load(&data).unwrap();
+        assert_eq!((escrow.seed(), escrow.receive(), escrow.amount(), escrow.bump()), (42, 1_000, 500, 254));
+        assert_eq!((escrow.maker(), escrow.mint_a(), escrow.mint_b()), (&[1; 32], &[2; 32], &[3; 32]));
+        assert_eq!((escrow.deadline(), escrow.created_at()), (1_700_000_060, 1_700_000_000));
+        assert_eq!(escrow.allowed_taker(), &[4; 32]);
+        assert_eq!(bytemuck::bytes_of(escrow), &data[..]);
+    }
+
+    #[test]
+    fn private_escrows_only_take_their_counterparty() {
+        let private = escrow();
+        assert!(private.can_take(&[4; 32]));
+        assert!(!private.can_take(&[5; 32]));
+
+        // All zeroes leaves it open to anyone
+        let mut public = escrow();
+        public.set_allowed_taker(Pubkey::default());
+        assert!(public.can_take(&[5; 32]));
+    }
+
+    #[test]
+    fn partial_withdrawals_keep_the_price() {
+        let mut escrow = escrow();
+        escrow.withdraw(200).unwrap();
+        assert_eq!((escrow.amount(), escrow.receive()), (300, 600));
+
+        // Rounded up in the maker's favour
+        escrow.set_inner(42, [1; 32], [2; 32], [3; 32], 10, 3, [254]);
+        escrow.withdraw(1).unwrap();
+        assert_eq!((escrow.amount(), escrow.receive()), (2, 7));
+    }
+
+    #[test]
+    fn withdrawals_leave_something_behind() {
+        let mut escrow = escrow();
+
+        assert_eq!(escrow.withdraw(500), Err(ProgramError::InvalidInstructionData));
+        assert_eq!(escrow.withdraw(501), Err(ProgramError::InvalidInstructionData));
+        assert_eq!((escrow.amount(), escrow.receive()), (500, 1_000));
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn escrow_pda_matches_the_anchor_escrow() {
+        // Maker `[7; 32]` and seed 42, the Anchor escrow's state tests derive
+        // the same address and compare it to these bytes
+        const SHARED_ESCROW_PDA: [u8; 32] = [
+            0x0a, 0xa2, 0x7e, 0x63, 0x88, 0x09, 0xb0, 0x41,
+            0x8d, 0x53, 0x2a, 0x8f, 0x4e, 0xfa, 0x2e, 0x4d,
+            0x99, 0xa8, 0x42, 0xef, 0x68, 0x0c, 0xfe, 0x9b,
+            0x57, 0x24, 0x48, 0x62, 0xda, 0x93, 0xac, 0xce,
+        ];
+
+        let maker = solana_pubkey::Pubkey::new_from_array([7; 32]);
+        let (escrow, _) = crate::client::escrow_address(&maker, 42);
+
+        assert_eq!(escrow.to_bytes(), SHARED_ESCROW_PDA);
+    }
+}
@@ -0,0 +1,104 @@
+//! Accounts laid out the way the runtime serializes them, so the parsers and
+//! helpers can be run off-chain
+
+use pinocchio::{account_info::AccountInfo, pubkey::Pubkey};
+
+use crate::Escrow;
+
+/// Borrow state, signer, writable and executable flags, resize delta, key,
+/// owner, lamports and data length, the data follows right after
+const HEADER_LEN: usize = 88;
+const NOT_BORROWED: u8 = u8::MAX;
+
+const KEY_OFFSET: usize = 8;
+const OWNER_OFFSET: usize = 40;
+const LAMPORTS_OFFSET: usize = 72;
+const DATA_LEN_OFFSET: usize = 80;
+
+pub struct TestAccount {
+    // u64 words keep the header aligned like the runtime's
+    buffer: Vec<u64>,
+    len: usize,
+}
+
+impl TestAccount {
+    pub fn new(key: Pubkey, owner: Pubkey, data: &[u8]) -> Self {
+        let len = HEADER_LEN + data.len();
+        let mut account = Self { buffer: vec![0; len.div_ceil(8)], len };
+
+        let bytes = account.bytes_mut();
+        bytes[0] = NOT_BORROWED;
+        bytes[KEY_OFFSET..OWNER_OFFSET].copy_from_slice(&key);
+        bytes[OWNER_OFFSET..LAMPORTS_OFFSET].copy_from_slice(&owner);
+        bytes[LAMPORTS_OFFSET..DATA_LEN_OFFSET].copy_from_slice(&1_000_000u64.to_le_bytes());
+        bytes[DATA_LEN_OFFSET..HEADER_LEN].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        bytes[HEADER_LEN..].copy_from_slice(data);
+        account
+    }
+
+    /// Token program account holding `amount` of `mint` for `owner`
+    pub fn token(key: Pubkey, mint: Pubkey, owner: Pubkey, amount: u64) -> Self {
+        let mut data = [0; pinocchio_token::state::TokenAccount::LEN];
+        data[0..32].copy_from_slice(&mint);
+        data[32..64].copy_from_slice(&owner);
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        // Initialized
+        data[108] = 1;
+        Self::new(key, pinocchio_token::ID, &data)
+    }
+
+    pub fn mint(key: Pubkey, decimals: u8) -> Self {
+        let mut data = [0; pinocchio_token::state::Mint::LEN];
+        data[44] = decimals;
+        // Initialized
+        data[45] = 1;
+        Self::new(key, pinocchio_token::ID, &data)
+    }
+
+    pub fn escrow(key: Pubkey, escrow: &Escrow) -> Self {
+        Self::new(key, crate::ID, bytemuck::bytes_of(escrow))
+    }
+
+    pub fn signer(self) -> Self {
+        self.signed().writable()
+    }
+
+    /// Signer that stays read-only
+    pub fn signed(mut self) -> Self {
+        self.bytes_mut()[1] = 1;
+        self
+    }
+
+    pub fn writable(mut self) -> Self {
+        self.bytes_mut()[2] = 1;
+        self
+    }
+
+    /// View of the account, valid for as long as this buffer is
+    pub fn info(&mut self) -> AccountInfo {
+        // SAFETY: `AccountInfo` is a single pointer to the serialized header
+        unsafe { core::mem::transmute::<*mut u8, AccountInfo>(self.buffer.as_mut_ptr() as *mut u8) }
+    }
+
+    /// Every data byte as first laid out, even past a shrink or close
+    pub fn raw_data(&self) -> &[u8] {
+        &self.bytes()[HEADER_LEN..]
+    }
+
+    fn bytes(&self) -> &[u8] {
+        // SAFETY: the buffer holds at least `len` initialized bytes
+        unsafe { core::slice::from_raw_parts(self.buffer.as_ptr() as *const u8, self.len) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: the buffer holds at least `len` initialized bytes
+        unsafe { core::slice::from_raw_parts_mut(self.buffer.as_mut_ptr() as *mut u8, self.len) }
+    }
+}
+
+/// Escrow of 500 Token A for 1_000 Token B that anyone can take
+pub fn escrow(maker: Pubkey, mint_a: Pubkey, mint_b: Pubkey) -> Escrow {
+    let mut escrow: Escrow = bytemuck::Zeroable::zeroed();
+    escrow.set_inner(42, maker, mint_a, mint_b, 1_000, 500, [255]);
+    escrow
+}